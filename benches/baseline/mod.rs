@@ -0,0 +1,300 @@
+//! The interpreter as it was before instructions were cached and run in
+//! batches, condensed from the original `chip8`, `instruction`, and `screen`
+//! modules (keeping what every instruction cost), so that the speedup is
+//! measured against it. The library's own
+//! [`instruction_cycle`](biscuit8::chip8::Chip8::instruction_cycle) can't stand
+//! in for it, since it goes through the cache too now.
+
+use biscuit8::keys::Keys;
+use fastrand::Rng;
+use std::time::{Duration, Instant};
+
+/// How many bytes to allocate for the emulator's RAM.
+const RAM_SIZE: usize = 0x1000;
+
+/// Where to put the ROM in the emulator's RAM.
+const ROM_LOC: usize = 0x200;
+
+/// The width of the emulator's screen.
+const WIDTH: usize = 64;
+
+/// The height of the emulator's screen.
+const HEIGHT: usize = 32;
+
+/// Used to represent an instruction (opcode and values).
+#[derive(Clone, Copy, Debug)]
+struct Instruction {
+    raw: u16,
+}
+
+impl Instruction {
+    /// Returns all four nibbles (4 bits each) of the instruction.
+    const fn nibbles(&self) -> (u8, u8, u8, u8) {
+        (
+            ((self.raw & 0xF000) >> 12) as u8,
+            ((self.raw & 0x0F00) >> 8) as u8,
+            ((self.raw & 0x00F0) >> 4) as u8,
+            (self.raw & 0x000F) as u8,
+        )
+    }
+
+    /// Returns the second 4 bits (a register).
+    const fn x(&self) -> usize {
+        ((self.raw & 0x0F00) >> 8) as usize
+    }
+
+    /// Returns the third 4 bits (a register).
+    const fn y(&self) -> usize {
+        ((self.raw & 0x00F0) >> 4) as usize
+    }
+
+    /// Returns the last 4 bits (a size).
+    const fn n(&self) -> usize {
+        (self.raw & 0x000F) as usize
+    }
+
+    /// Returns the last 8 bits (a constant).
+    const fn nn(&self) -> u8 {
+        (self.raw & 0x00FF) as u8
+    }
+
+    /// Returns the last 12 bits (a memory address).
+    const fn nnn(&self) -> usize {
+        (self.raw & 0x0FFF) as usize
+    }
+}
+
+/// Represents the screen of the emulator.
+#[derive(Clone, Debug)]
+pub struct Screen {
+    raw: [bool; WIDTH * HEIGHT],
+}
+
+impl Screen {
+    /// Draws the given sprite at the specified location. Returns true if a pixel
+    /// is erased.
+    fn draw_sprite(&mut self, sprite: &[u8], mut x: usize, mut y: usize) -> bool {
+        x %= WIDTH;
+        y %= HEIGHT;
+        let mut erased = false;
+        for (i, row) in sprite.iter().enumerate() {
+            if y + i >= HEIGHT {
+                break;
+            }
+            for j in 0..8 {
+                if x + j >= WIDTH {
+                    break;
+                }
+                let bit = (row & 0b10000000 >> j) << j;
+                let pos = (y + i) * WIDTH + x + j;
+                let pixel = self.raw[pos];
+                self.raw[pos] ^= bit != 0;
+                if pixel && !self.raw[pos] {
+                    erased = true;
+                }
+            }
+        }
+        erased
+    }
+}
+
+/// Used to represent the emulator.
+pub struct Chip8 {
+    ram: [u8; RAM_SIZE],
+    v: [u8; 0x10],
+    i: usize,
+    pc: usize,
+    dt: u8,
+    st: u8,
+    stack: Vec<usize>,
+    instruction: Instruction,
+    keys: Keys,
+    screen: Screen,
+    last_decrement: Instant,
+    rng: Rng,
+}
+
+impl Chip8 {
+    /// Create an emulator from the given ROM (which has to fit), seeded so
+    /// every run is the same.
+    pub fn new(rom: &[u8], seed: u64) -> Self {
+        let mut ram = [0; RAM_SIZE];
+        // The font is still the same.
+        let font = biscuit8::chip8::Chip8::new(&[]).unwrap();
+        ram[..ROM_LOC].copy_from_slice(&font.memory()[..ROM_LOC]);
+        ram[ROM_LOC..rom.len() + ROM_LOC].copy_from_slice(rom);
+        Self {
+            ram,
+            v: [0; 0x10],
+            i: 0,
+            pc: ROM_LOC,
+            dt: 0,
+            st: 0,
+            stack: Vec::new(),
+            instruction: Instruction { raw: 0 },
+            keys: Keys::new(),
+            screen: Screen {
+                raw: [false; WIDTH * HEIGHT],
+            },
+            last_decrement: Instant::now(),
+            rng: Rng::with_seed(seed),
+        }
+    }
+
+    /// Performs one iteration of the fetch-decode-execute cycle and returns the
+    /// screen as well as whether the frontend should beep or not, if it was
+    /// updated. An error is returned if there isn't another instruction to be
+    /// decoded and executed or its opcode is unknown (and where the original
+    /// panicked, like returning without a subroutine or going past the end of
+    /// RAM).
+    pub fn instruction_cycle(&mut self, keys: Keys) -> Result<(Option<Screen>, bool), ()> {
+        self.decrement_timers();
+        let Some(instruction) = self.fetch_instruction() else {
+            return Err(());
+        };
+        self.keys = keys;
+        self.instruction = instruction;
+        self.pc += 2;
+        if self.decode_execute()? {
+            return Ok((Some(self.screen.clone()), self.st > 0));
+        }
+        Ok((None, self.st > 0))
+    }
+
+    /// Decrements the delay and sound timers at a rate of 60 hertz.
+    fn decrement_timers(&mut self) {
+        if self.last_decrement.elapsed() >= Duration::new(0, 16666666) {
+            if self.dt != 0 {
+                self.dt -= 1;
+            }
+            if self.st != 0 {
+                self.st -= 1;
+            }
+            self.last_decrement = Instant::now();
+        }
+    }
+
+    /// Fetches the current instruction from the program counter (if there still
+    /// is one).
+    fn fetch_instruction(&self) -> Option<Instruction> {
+        let first = self.ram.get(self.pc)?;
+        let second = self.ram.get(self.pc + 1)?;
+        Some(Instruction {
+            raw: u16::from_be_bytes([*first, *second]),
+        })
+    }
+
+    /// Decodes the current instruction and executes it, returning whether the
+    /// screen was updated.
+    fn decode_execute(&mut self) -> Result<bool, ()> {
+        let instruction = self.instruction;
+        let (x, y, n, nn, nnn) = (
+            instruction.x(),
+            instruction.y(),
+            instruction.n(),
+            instruction.nn(),
+            instruction.nnn(),
+        );
+        match instruction.nibbles() {
+            (0, 0, 0, 0) => (),
+            (0x0, 0x0, 0xE, 0x0) => {
+                self.screen.raw.fill(false);
+                return Ok(true);
+            }
+            (0x0, 0x0, 0xE, 0xE) => self.pc = self.stack.pop().ok_or(())?,
+            (0x1, _, _, _) => self.pc = nnn,
+            (0x2, _, _, _) => {
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            }
+            (0x3, _, _, _) => self.skip(self.v[x] == nn),
+            (0x4, _, _, _) => self.skip(self.v[x] != nn),
+            (0x5, _, _, 0x0) => self.skip(self.v[x] == self.v[y]),
+            (0x6, _, _, _) => self.v[x] = nn,
+            (0x7, _, _, _) => self.v[x] = self.v[x].wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.v[x] = self.v[y],
+            (0x8, _, _, 0x1) => {
+                self.v[x] |= self.v[y];
+                self.v[0xF] = 0;
+            }
+            (0x8, _, _, 0x2) => {
+                self.v[x] &= self.v[y];
+                self.v[0xF] = 0;
+            }
+            (0x8, _, _, 0x3) => {
+                self.v[x] ^= self.v[y];
+                self.v[0xF] = 0;
+            }
+            (0x8, _, _, 0x4) => {
+                let result = self.v[x].overflowing_add(self.v[y]);
+                self.v[x] = result.0;
+                self.v[0xF] = result.1 as u8;
+            }
+            (0x8, _, _, 0x5) => {
+                let result = self.v[x].overflowing_sub(self.v[y]);
+                self.v[x] = result.0;
+                self.v[0xF] = !result.1 as u8;
+            }
+            (0x8, _, _, 0x6) => {
+                let lsb = self.v[x] & 1;
+                self.v[x] = self.v[y] >> 1;
+                self.v[0xF] = lsb;
+            }
+            (0x8, _, _, 0x7) => {
+                let result = self.v[y].overflowing_sub(self.v[x]);
+                self.v[x] = result.0;
+                self.v[0xF] = !result.1 as u8;
+            }
+            (0x8, _, _, 0xE) => {
+                let msb = (self.v[x] >> 7) & 1;
+                self.v[x] = self.v[y] << 1;
+                self.v[0xF] = msb;
+            }
+            (0x9, _, _, 0x0) => self.skip(self.v[x] != self.v[y]),
+            (0xA, _, _, _) => self.i = nnn,
+            (0xB, _, _, _) => self.pc = nnn + self.v[0x0] as usize,
+            (0xC, _, _, _) => self.v[x] = self.rng.u8(0..255) & nn,
+            (0xD, _, _, _) => {
+                let sprite = self.ram.get(self.i..self.i + n).ok_or(())?;
+                let (x, y) = (self.v[x] as usize, self.v[y] as usize);
+                self.v[0xF] = self.screen.draw_sprite(sprite, x, y) as u8;
+                return Ok(true);
+            }
+            (0xE, _, 0x9, 0xE) => self.skip(self.keys.key_pressed(self.v[x])),
+            (0xE, _, 0xA, 0x1) => self.skip(!self.keys.key_pressed(self.v[x])),
+            (0xF, _, 0x0, 0x7) => self.v[x] = self.dt,
+            (0xF, _, 0x0, 0xA) => match self.keys.last_pressed() {
+                Some(key) => self.v[x] = key,
+                None => self.pc -= 2,
+            },
+            (0xF, _, 0x1, 0x5) => self.dt = self.v[x],
+            (0xF, _, 0x1, 0x8) => self.st = self.v[x],
+            (0xF, _, 0x1, 0xE) => self.i = self.i.wrapping_add(self.v[x] as usize),
+            (0xF, _, 0x2, 0x9) => self.i = 5 * self.v[x] as usize,
+            (0xF, _, 0x3, 0x3) => {
+                let vx = self.v[x];
+                let digits = self.ram.get_mut(self.i..self.i + 3).ok_or(())?;
+                digits.copy_from_slice(&[vx / 100 % 10, vx / 10 % 10, vx % 10]);
+            }
+            (0xF, _, 0x5, 0x5) => {
+                let registers = self.ram.get_mut(self.i..=self.i + x).ok_or(())?;
+                registers.copy_from_slice(&self.v[0x0..=x]);
+                self.i += x + 1;
+            }
+            (0xF, _, 0x6, 0x5) => {
+                let registers = self.ram.get(self.i..=self.i + x).ok_or(())?;
+                self.v[0x0..=x].copy_from_slice(registers);
+                self.i += x + 1;
+            }
+            _ => return Err(()),
+        }
+        Ok(false)
+    }
+
+    /// Skips the next instruction if the condition holds.
+    fn skip(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Measures how many instructions a second the emulator executes on the bundled
+//! ROMs, which fast-forwarding, bulk headless runs, and training depend on:
+//! with the original interpreter (see [`baseline`]) as the baseline, one at a
+//! time with `instruction_cycle` (copying the screen on every draw), in batches
+//! with `run` (the way the runner drives it), and with the JIT when the `jit`
+//! feature is enabled. A ROM that does nothing but draw is measured too, since
+//! drawing is what no cache can speed up. Criterion reports the throughput in
+//! instructions a second (`elem/s`); run it with `cargo bench --bench
+//! throughput`, adding `--features jit` for the JIT.
+
+mod baseline;
+
+use biscuit8::{chip8::Chip8, keys::Keys};
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, criterion_main, BenchmarkGroup, Criterion, Throughput};
+use std::{fs, hint::black_box, time::Duration};
+
+/// How many instructions are executed per iteration (a frame at a high speed).
+const BATCH: usize = 1000;
+
+/// A ROM drawing a digit over and over (`A000 D015 1202`).
+const DRAW_LOOP: [u8; 6] = [0xA0, 0x00, 0xD0, 0x15, 0x12, 0x02];
+
+/// Returns the bundled ROMs (sorted by name) and the draw loop.
+fn roms() -> Vec<(String, Vec<u8>)> {
+    let mut paths: Vec<_> = fs::read_dir("roms")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    let mut roms: Vec<_> = paths
+        .iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(path).unwrap())
+        })
+        .collect();
+    roms.push(("draw loop".to_string(), DRAW_LOOP.to_vec()));
+    roms
+}
+
+/// Constructs an emulator running the ROM, seeded so every run is the same.
+fn emulator(rom: &[u8]) -> Chip8 {
+    let mut chip8 = Chip8::new(rom).unwrap();
+    chip8.set_seed(0);
+    chip8
+}
+
+/// Benchmarks executing a batch of instructions on an emulator, given a way of
+/// executing up to a number of them that returns how many it did (or nothing
+/// if the ROM failed, in which case it starts over).
+fn bench<T>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    name: &str,
+    emulator: impl Fn() -> T,
+    mut execute: impl FnMut(&mut T, usize) -> Option<usize>,
+) {
+    let mut chip8 = emulator();
+    group.bench_function(name, |b| {
+        b.iter(|| {
+            let mut executed = 0;
+            while executed < BATCH {
+                match execute(&mut chip8, BATCH - executed) {
+                    Some(cycles) => executed += cycles,
+                    None => {
+                        executed += 1;
+                        chip8 = emulator();
+                    }
+                }
+            }
+        })
+    });
+}
+
+fn throughput(c: &mut Criterion) {
+    for (name, rom) in roms() {
+        let mut group = c.benchmark_group(name);
+        group.throughput(Throughput::Elements(BATCH as u64));
+        group.warm_up_time(Duration::from_secs(1));
+        group.measurement_time(Duration::from_secs(2));
+        let original = || baseline::Chip8::new(&rom, 0);
+        bench(&mut group, "baseline", original, |chip8, _| {
+            let (screen, _) = chip8.instruction_cycle(Keys::new()).ok()?;
+            black_box(screen);
+            Some(1)
+        });
+        let current = || emulator(&rom);
+        bench(&mut group, "instruction_cycle", current, |chip8, _| {
+            let (screen, _) = chip8.instruction_cycle(Keys::new()).ok()?;
+            black_box(screen);
+            Some(1)
+        });
+        bench(&mut group, "run", current, |chip8, cycles| {
+            let (executed, drew) = chip8.run(Keys::new(), cycles).ok()?;
+            if drew {
+                black_box(chip8.screen());
+            }
+            Some(executed)
+        });
+        #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+        {
+            let mut jit = biscuit8::chip8::jit::Jit::new().unwrap();
+            bench(&mut group, "jit", current, |chip8, cycles| {
+                let (executed, drew) = jit.run(chip8, Keys::new(), cycles).ok()?;
+                if drew {
+                    black_box(chip8.screen());
+                }
+                Some(executed)
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
@@ -0,0 +1,212 @@
+#ifndef BISCUIT8_H
+#define BISCUIT8_H
+
+/* Generated by cbindgen from biscuit8-ffi; don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The width of the framebuffer in pixels.
+ */
+#define BISCUIT8_WIDTH 64
+
+/**
+ * The height of the framebuffer in pixels.
+ */
+#define BISCUIT8_HEIGHT 32
+
+/**
+ * How many bytes a saved state takes.
+ */
+#define BISCUIT8_STATE_SIZE 4420
+
+/**
+ * How many instructions are executed every frame by default.
+ */
+#define BISCUIT8_DEFAULT_SPEED 10
+
+/**
+ * The CHIP-8 platforms whose quirks are known.
+ */
+typedef enum Biscuit8Platform {
+  BISCUIT8_PLATFORM_CHIP8,
+  BISCUIT8_PLATFORM_SCHIP,
+  BISCUIT8_PLATFORM_XO_CHIP,
+} Biscuit8Platform;
+
+/**
+ * An emulator running a ROM.
+ */
+typedef struct Biscuit8 Biscuit8;
+
+/**
+ * The behaviors that differ between CHIP-8 interpreters.
+ */
+typedef struct Biscuit8Quirks {
+  /**
+   * Whether the bitwise operations reset the flag register.
+   */
+  bool vf_reset;
+  /**
+   * Whether storing and loading registers moves the index register past
+   * them.
+   */
+  bool memory;
+  /**
+   * Whether the shift instructions shift the first register in place.
+   */
+  bool shifting;
+  /**
+   * Whether `BXNN` jumps to `XNN` plus the register `X`.
+   */
+  bool jumping;
+  /**
+   * Whether sprites wrap around the edges of the screen.
+   */
+  bool wrapping;
+} Biscuit8Quirks;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns a description of the last error on this thread (valid until the
+ * next error), or `NULL` if there hasn't been one.
+ */
+const char *biscuit8_last_error(void);
+
+/**
+ * Creates an emulator running the ROM, returning `NULL` if it can't be loaded.
+ * It has to be freed with [`biscuit8_free`].
+ *
+ * # Safety
+ *
+ * `rom` has to point to `len` readable bytes.
+ */
+struct Biscuit8 *biscuit8_new(const uint8_t *rom, uintptr_t len);
+
+/**
+ * Frees an emulator created by [`biscuit8_new`] (doing nothing if it's
+ * `NULL`).
+ *
+ * # Safety
+ *
+ * `emulator` has to be `NULL` or created by [`biscuit8_new`], and can't be
+ * used afterwards.
+ */
+void biscuit8_free(struct Biscuit8 *emulator);
+
+/**
+ * Returns the quirks of the platform.
+ */
+struct Biscuit8Quirks biscuit8_platform_quirks(enum Biscuit8Platform platform);
+
+/**
+ * Returns the quirks the emulator is running with.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+struct Biscuit8Quirks biscuit8_quirks(const struct Biscuit8 *emulator);
+
+/**
+ * Changes the quirks the emulator is running with.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+void biscuit8_set_quirks(struct Biscuit8 *emulator, struct Biscuit8Quirks quirks);
+
+/**
+ * Sets how many instructions are executed every frame.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+void biscuit8_set_speed(struct Biscuit8 *emulator, uint32_t speed);
+
+/**
+ * Seeds the random number generator, so that the emulator behaves the same
+ * every time it's run with the same input.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+void biscuit8_set_seed(struct Biscuit8 *emulator, uint64_t seed);
+
+/**
+ * Sets the held keys as a bitmask (with bit `n` set for key `n`). Keys that
+ * weren't held before count as pressed.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+void biscuit8_set_keys(struct Biscuit8 *emulator, uint16_t keys);
+
+/**
+ * Runs a frame, executing as many instructions as the speed says, which should
+ * be done 60 times a second. Returns `false` if the emulator fails.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+bool biscuit8_run_frame(struct Biscuit8 *emulator);
+
+/**
+ * Returns whether the beep should be playing.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator.
+ */
+bool biscuit8_beeping(const struct Biscuit8 *emulator);
+
+/**
+ * Copies the screen into `framebuffer` a byte per pixel (0 when unlit and
+ * 255 when lit), row by row. Returns `false` if it's shorter than
+ * [`BISCUIT8_WIDTH`] times [`BISCUIT8_HEIGHT`] bytes.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator, and `framebuffer` has to point to
+ * `len` writable bytes.
+ */
+bool biscuit8_framebuffer(const struct Biscuit8 *emulator, uint8_t *framebuffer, uintptr_t len);
+
+/**
+ * Saves the emulator's state into `state`. Returns `false` if it's shorter
+ * than [`BISCUIT8_STATE_SIZE`] bytes.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator, and `state` has to point to `len`
+ * writable bytes.
+ */
+bool biscuit8_save_state(const struct Biscuit8 *emulator, uint8_t *state, uintptr_t len);
+
+/**
+ * Restores a state saved by [`biscuit8_save_state`]. Returns `false` (leaving
+ * the emulator as it was) if it isn't a valid state.
+ *
+ * # Safety
+ *
+ * `emulator` has to be a valid emulator, and `state` has to point to `len`
+ * readable bytes.
+ */
+bool biscuit8_load_state(struct Biscuit8 *emulator, const uint8_t *state, uintptr_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BISCUIT8_H */
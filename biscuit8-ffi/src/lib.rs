@@ -0,0 +1,332 @@
+//! C bindings for [`biscuit8`], built as a shared and a static library, so that
+//! it can be driven from C and C++ tools and from game engines. The emulator is
+//! an opaque [`Biscuit8`] handle created from a ROM; everything else is done
+//! through functions taking it. Functions that can fail return `false` (or
+//! `NULL`), and [`biscuit8_last_error`] describes why. The header in `include`
+//! is generated by cbindgen (see `cbindgen.toml`).
+
+use biscuit8::{
+    chip8::{self, Chip8, Platform, Quirks},
+    keys::Keys,
+    screen,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    ptr, slice,
+};
+
+/// The width of the framebuffer in pixels.
+pub const BISCUIT8_WIDTH: usize = screen::WIDTH;
+
+/// The height of the framebuffer in pixels.
+pub const BISCUIT8_HEIGHT: usize = screen::HEIGHT;
+
+/// How many bytes a saved state takes.
+pub const BISCUIT8_STATE_SIZE: usize = chip8::STATE_SIZE;
+
+/// How many instructions are executed every frame by default.
+pub const BISCUIT8_DEFAULT_SPEED: u32 = 10;
+
+thread_local! {
+    /// The description of the last error on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An emulator running a ROM.
+pub struct Biscuit8 {
+    chip8: Chip8,
+    keys: Keys,
+    speed: u32,
+}
+
+/// The CHIP-8 platforms whose quirks are known.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Biscuit8Platform {
+    Chip8,
+    Schip,
+    XoChip,
+}
+
+/// The behaviors that differ between CHIP-8 interpreters.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Biscuit8Quirks {
+    /// Whether the bitwise operations reset the flag register.
+    pub vf_reset: bool,
+    /// Whether storing and loading registers moves the index register past
+    /// them.
+    pub memory: bool,
+    /// Whether the shift instructions shift the first register in place.
+    pub shifting: bool,
+    /// Whether `BXNN` jumps to `XNN` plus the register `X`.
+    pub jumping: bool,
+    /// Whether sprites wrap around the edges of the screen.
+    pub wrapping: bool,
+}
+
+impl From<Quirks> for Biscuit8Quirks {
+    fn from(quirks: Quirks) -> Self {
+        Self {
+            vf_reset: quirks.vf_reset,
+            memory: quirks.memory,
+            shifting: quirks.shifting,
+            jumping: quirks.jumping,
+            wrapping: quirks.wrapping,
+        }
+    }
+}
+
+impl From<Biscuit8Quirks> for Quirks {
+    fn from(quirks: Biscuit8Quirks) -> Self {
+        Self {
+            vf_reset: quirks.vf_reset,
+            memory: quirks.memory,
+            shifting: quirks.shifting,
+            jumping: quirks.jumping,
+            wrapping: quirks.wrapping,
+        }
+    }
+}
+
+/// Records the error for [`biscuit8_last_error`], returning `false` for
+/// convenience.
+fn set_error(error: impl ToString) -> bool {
+    let error = CString::new(error.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(error));
+    false
+}
+
+/// Returns the byte slice passed from C, which may be `NULL` when it's empty.
+///
+/// # Safety
+///
+/// `bytes` has to point to `len` readable bytes unless `len` is 0.
+unsafe fn bytes<'a>(bytes: *const u8, len: usize) -> &'a [u8] {
+    match len {
+        0 => &[],
+        _ => slice::from_raw_parts(bytes, len),
+    }
+}
+
+/// Returns a description of the last error on this thread (valid until the
+/// next error), or `NULL` if there hasn't been one.
+#[no_mangle]
+pub extern "C" fn biscuit8_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| match &*last.borrow() {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// Creates an emulator running the ROM, returning `NULL` if it can't be loaded.
+/// It has to be freed with [`biscuit8_free`].
+///
+/// # Safety
+///
+/// `rom` has to point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_new(rom: *const u8, len: usize) -> *mut Biscuit8 {
+    if rom.is_null() && len > 0 {
+        set_error("ROM is NULL.");
+        return ptr::null_mut();
+    }
+    match Chip8::new(bytes(rom, len)) {
+        Ok(chip8) => Box::into_raw(Box::new(Biscuit8 {
+            chip8,
+            keys: Keys::new(),
+            speed: BISCUIT8_DEFAULT_SPEED,
+        })),
+        Err(err) => {
+            set_error(err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees an emulator created by [`biscuit8_new`] (doing nothing if it's
+/// `NULL`).
+///
+/// # Safety
+///
+/// `emulator` has to be `NULL` or created by [`biscuit8_new`], and can't be
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_free(emulator: *mut Biscuit8) {
+    if !emulator.is_null() {
+        drop(Box::from_raw(emulator));
+    }
+}
+
+/// Returns the quirks of the platform.
+#[no_mangle]
+pub extern "C" fn biscuit8_platform_quirks(platform: Biscuit8Platform) -> Biscuit8Quirks {
+    let platform = match platform {
+        Biscuit8Platform::Chip8 => Platform::Chip8,
+        Biscuit8Platform::Schip => Platform::Schip,
+        Biscuit8Platform::XoChip => Platform::XoChip,
+    };
+    platform.quirks().into()
+}
+
+/// Returns the quirks the emulator is running with.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_quirks(emulator: *const Biscuit8) -> Biscuit8Quirks {
+    (*emulator).chip8.quirks().into()
+}
+
+/// Changes the quirks the emulator is running with.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_set_quirks(emulator: *mut Biscuit8, quirks: Biscuit8Quirks) {
+    (*emulator).chip8.set_quirks(quirks.into());
+}
+
+/// Sets how many instructions are executed every frame.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_set_speed(emulator: *mut Biscuit8, speed: u32) {
+    (*emulator).speed = speed;
+}
+
+/// Seeds the random number generator, so that the emulator behaves the same
+/// every time it's run with the same input.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_set_seed(emulator: *mut Biscuit8, seed: u64) {
+    (*emulator).chip8.set_seed(seed);
+}
+
+/// Sets the held keys as a bitmask (with bit `n` set for key `n`). Keys that
+/// weren't held before count as pressed.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_set_keys(emulator: *mut Biscuit8, keys: u16) {
+    let emulator = &mut *emulator;
+    let pressed = keys & !emulator.keys.raw();
+    let last_pressed = (pressed != 0).then(|| pressed.trailing_zeros() as u8);
+    emulator.keys = Keys::from_raw(keys, last_pressed.or(emulator.keys.last_pressed()));
+}
+
+/// Runs a frame, executing as many instructions as the speed says, which should
+/// be done 60 times a second. Returns `false` if the emulator fails.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_run_frame(emulator: *mut Biscuit8) -> bool {
+    let emulator = &mut *emulator;
+    let mut cycles = emulator.speed as usize;
+    while cycles > 0 {
+        match emulator.chip8.run(emulator.keys, cycles) {
+            Ok((executed, _)) => cycles -= executed,
+            Err(err) => return set_error(err),
+        }
+        emulator.keys.reset_last_pressed();
+    }
+    emulator.chip8.decrement_timers();
+    true
+}
+
+/// Returns whether the beep should be playing.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_beeping(emulator: *const Biscuit8) -> bool {
+    (*emulator).chip8.beeping()
+}
+
+/// Copies the screen into `framebuffer` a byte per pixel (0 when unlit and
+/// 255 when lit), row by row. Returns `false` if it's shorter than
+/// [`BISCUIT8_WIDTH`] times [`BISCUIT8_HEIGHT`] bytes.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator, and `framebuffer` has to point to
+/// `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_framebuffer(
+    emulator: *const Biscuit8,
+    framebuffer: *mut u8,
+    len: usize,
+) -> bool {
+    if framebuffer.is_null() || len < BISCUIT8_WIDTH * BISCUIT8_HEIGHT {
+        return set_error("Framebuffer is too small.");
+    }
+    let framebuffer = slice::from_raw_parts_mut(framebuffer, len);
+    let screen = (*emulator).chip8.screen();
+    for (i, pixel) in framebuffer
+        .iter_mut()
+        .take(BISCUIT8_WIDTH * BISCUIT8_HEIGHT)
+        .enumerate()
+    {
+        *pixel = match screen.pixel(i % BISCUIT8_WIDTH, i / BISCUIT8_WIDTH) {
+            true => 0xFF,
+            false => 0,
+        };
+    }
+    true
+}
+
+/// Saves the emulator's state into `state`. Returns `false` if it's shorter
+/// than [`BISCUIT8_STATE_SIZE`] bytes.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator, and `state` has to point to `len`
+/// writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_save_state(
+    emulator: *const Biscuit8,
+    state: *mut u8,
+    len: usize,
+) -> bool {
+    if state.is_null() || len < BISCUIT8_STATE_SIZE {
+        return set_error("State buffer is too small.");
+    }
+    let state = slice::from_raw_parts_mut(state, BISCUIT8_STATE_SIZE);
+    state.copy_from_slice(&(*emulator).chip8.save_state());
+    true
+}
+
+/// Restores a state saved by [`biscuit8_save_state`]. Returns `false` (leaving
+/// the emulator as it was) if it isn't a valid state.
+///
+/// # Safety
+///
+/// `emulator` has to be a valid emulator, and `state` has to point to `len`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn biscuit8_load_state(
+    emulator: *mut Biscuit8,
+    state: *const u8,
+    len: usize,
+) -> bool {
+    if state.is_null() && len > 0 {
+        return set_error("State is NULL.");
+    }
+    match (*emulator).chip8.load_state(bytes(state, len)) {
+        Ok(()) => true,
+        Err(err) => set_error(err),
+    }
+}
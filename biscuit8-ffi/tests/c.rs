@@ -0,0 +1,32 @@
+//! Compiles the C test program against the static library and the header, and
+//! runs it (using the C compiler in `CC`, or `cc`).
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+fn c_program() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // The library is built next to the tests (in `target/<profile>/deps`).
+    let test = env::current_exe().expect("Test executable should have a path.");
+    let library = test.with_file_name("libbiscuit8_ffi.a");
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("biscuit8-ffi-test");
+
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(compiler)
+        .arg(manifest_dir.join("tests").join("test.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-o")
+        .arg(&program)
+        .arg(library)
+        .args(["-lpthread", "-ldl", "-lm"])
+        .status()
+        .expect("C compiler should run.");
+    assert!(status.success(), "C test program should compile.");
+
+    let status = Command::new(&program)
+        .arg(manifest_dir.join("..").join("roms"))
+        .status()
+        .expect("C test program should run.");
+    assert!(status.success(), "C test program should pass.");
+}
@@ -0,0 +1,144 @@
+/* Exercises the C bindings; run by tests/c.rs with the path of the roms
+ * directory as its argument. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "biscuit8.h"
+
+#define CHECK(condition)                                                  \
+    do {                                                                  \
+        if (!(condition)) {                                               \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,        \
+                    __LINE__, #condition);                                \
+            return 1;                                                     \
+        }                                                                 \
+    } while (0)
+
+static uint8_t rom[0x1000];
+
+/* Reads the ROM with the given name from the roms directory, returning its
+ * length (or 0 on failure). */
+static size_t read_rom(const char *roms, const char *name) {
+    char path[1024];
+    snprintf(path, sizeof path, "%s/%s", roms, name);
+    FILE *file = fopen(path, "rb");
+    if (!file) {
+        return 0;
+    }
+    size_t len = fread(rom, 1, sizeof rom, file);
+    fclose(file);
+    return len;
+}
+
+/* Returns how many pixels are lit in the framebuffer. */
+static int lit(const uint8_t *framebuffer) {
+    int count = 0;
+    for (int i = 0; i < BISCUIT8_WIDTH * BISCUIT8_HEIGHT; i++) {
+        count += framebuffer[i] == 255;
+    }
+    return count;
+}
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+    const char *roms = argv[1];
+    uint8_t framebuffer[BISCUIT8_WIDTH * BISCUIT8_HEIGHT];
+    uint8_t other[BISCUIT8_WIDTH * BISCUIT8_HEIGHT];
+    static uint8_t state[BISCUIT8_STATE_SIZE];
+
+    /* ROMs too big for RAM are rejected with an error. */
+    static uint8_t huge[0x1000];
+    CHECK(biscuit8_new(huge, sizeof huge) == NULL);
+    CHECK(biscuit8_last_error() != NULL);
+    CHECK(strstr(biscuit8_last_error(), "ROM size") != NULL);
+
+    /* The IBM logo is drawn. */
+    size_t len = read_rom(roms, "ibm.ch8");
+    CHECK(len > 0);
+    Biscuit8 *emulator = biscuit8_new(rom, len);
+    CHECK(emulator != NULL);
+    biscuit8_set_seed(emulator, 8);
+    CHECK(biscuit8_framebuffer(emulator, framebuffer, sizeof framebuffer));
+    CHECK(lit(framebuffer) == 0);
+    CHECK(!biscuit8_framebuffer(emulator, framebuffer, sizeof framebuffer - 1));
+    for (int i = 0; i < 30; i++) {
+        CHECK(biscuit8_run_frame(emulator));
+    }
+    CHECK(biscuit8_framebuffer(emulator, framebuffer, sizeof framebuffer));
+    CHECK(lit(framebuffer) > 0);
+
+    /* Quirks can be configured. */
+    Biscuit8Quirks schip = biscuit8_platform_quirks(BISCUIT8_PLATFORM_SCHIP);
+    CHECK(schip.shifting && schip.jumping && !schip.vf_reset);
+    biscuit8_set_quirks(emulator, schip);
+    Biscuit8Quirks quirks = biscuit8_quirks(emulator);
+    CHECK(memcmp(&quirks, &schip, sizeof quirks) == 0);
+
+    /* States are exactly BISCUIT8_STATE_SIZE bytes, and restoring one replays
+     * the same frames. */
+    CHECK(!biscuit8_save_state(emulator, state, sizeof state - 1));
+    CHECK(biscuit8_save_state(emulator, state, sizeof state));
+    biscuit8_free(emulator);
+
+    len = read_rom(roms, "corax.ch8");
+    CHECK(len > 0);
+    emulator = biscuit8_new(rom, len);
+    CHECK(emulator != NULL);
+    biscuit8_set_keys(emulator, 0x0021);
+    CHECK(biscuit8_save_state(emulator, state, sizeof state));
+    for (int i = 0; i < 20; i++) {
+        CHECK(biscuit8_run_frame(emulator));
+    }
+    CHECK(biscuit8_framebuffer(emulator, framebuffer, sizeof framebuffer));
+    CHECK(biscuit8_load_state(emulator, state, sizeof state));
+    for (int i = 0; i < 20; i++) {
+        CHECK(biscuit8_run_frame(emulator));
+    }
+    CHECK(biscuit8_framebuffer(emulator, other, sizeof other));
+    CHECK(memcmp(framebuffer, other, sizeof framebuffer) == 0);
+    CHECK(lit(framebuffer) > 0);
+
+    /* Invalid states are rejected, leaving the emulator as it was. */
+    state[0] = 'X';
+    CHECK(!biscuit8_load_state(emulator, state, sizeof state));
+    CHECK(strstr(biscuit8_last_error(), "state") != NULL);
+    CHECK(!biscuit8_load_state(emulator, state, 3));
+    CHECK(biscuit8_framebuffer(emulator, other, sizeof other));
+    CHECK(memcmp(framebuffer, other, sizeof framebuffer) == 0);
+    biscuit8_free(emulator);
+
+    /* Sprites, digits, and registers reaching past the end of RAM through the
+     * index register fail the frame instead of crashing. */
+    static const uint8_t past_end[][8] = {
+        {0xAF, 0xFF, 0xD0, 0x0F},
+        {0xAF, 0xFE, 0xF0, 0x33},
+        {0xAF, 0xFF, 0xF0, 0x1E, 0xF0, 0x1E, 0xF5, 0x55},
+    };
+    static const size_t past_end_len[] = {4, 4, 8};
+    for (size_t i = 0; i < sizeof past_end_len / sizeof *past_end_len; i++) {
+        emulator = biscuit8_new(past_end[i], past_end_len[i]);
+        CHECK(emulator != NULL);
+        CHECK(!biscuit8_run_frame(emulator));
+        CHECK(strstr(biscuit8_last_error(), "outside the emulator's RAM") != NULL);
+        biscuit8_free(emulator);
+    }
+
+    /* The beep ROM beeps. */
+    len = read_rom(roms, "beep.ch8");
+    CHECK(len > 0);
+    emulator = biscuit8_new(rom, len);
+    CHECK(emulator != NULL);
+    CHECK(!biscuit8_beeping(emulator));
+    bool beeped = false;
+    for (int i = 0; i < 60 && !beeped; i++) {
+        CHECK(biscuit8_run_frame(emulator));
+        beeped = biscuit8_beeping(emulator);
+    }
+    CHECK(beeped);
+    biscuit8_free(emulator);
+    biscuit8_free(NULL);
+
+    puts("All checks passed.");
+    return 0;
+}
@@ -0,0 +1,87 @@
+//! A [`biscuit8`] frontend without a window or audio device, primarily provided
+//! by [`HeadlessFrontend`]. It runs a ROM for a given number of frames as fast
+//! as possible, which is useful for testing ROMs (and the emulator) in CI, and
+//! can record the beep to a WAV file and gameplay to a GIF or PNG sequence.
+//! Input movies can be recorded too, though without any input they're only
+//! useful to continue a replayed movie. On x86-64, ROMs run with the JIT (see
+//! [`biscuit8::chip8::jit`]) unless the `jit` feature is turned off. With
+//! `--analyze`, the ROM is statically analyzed instead of run. Errors are also
+//! represented by [`HeadlessFrontendError`].
+
+use biscuit8::{
+    analyzer::Analysis,
+    args::{self, argh, Args},
+    color::Palette,
+    filter::Filter,
+    frontend::{Frontend, Input},
+    runner::{Runner, RunnerError},
+};
+use std::{process::ExitCode, time::Instant};
+use thiserror::Error;
+
+/// A frontend running the emulator without any input, drawing, or playback;
+/// everything else is done by the [`Runner`].
+pub struct HeadlessFrontend;
+
+impl Frontend for HeadlessFrontend {
+    type Error = HeadlessFrontendError;
+    const HEADLESS: bool = true;
+
+    fn poll_input(&mut self, _input: &mut Input, _deadline: Instant) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn present(&mut self, _filter: &Filter, _palette: &Palette) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Used to describe possible errors caused by the runner (the arguments, the
+/// emulator, or writing files).
+#[derive(Debug, Error)]
+pub enum HeadlessFrontendError {
+    #[error("{0}")]
+    Runner(#[from] RunnerError),
+    #[error("The number of frames to run for is required (use --frames or --play-movie).")]
+    NoFrames,
+    #[error("The analysis found problems with the ROM.")]
+    Problems,
+}
+
+/// Same old "exciting" entry point.
+fn main() -> ExitCode {
+    if let Err(err) = main_loop() {
+        eprintln!("{}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Gets the ROM from the given path and runs it for the given number of frames
+/// with some options/settings.
+fn main_loop() -> Result<(), HeadlessFrontendError> {
+    let args = argh::from_env::<Args>();
+    if args.analyze {
+        return analyze(&args);
+    }
+    let mut runner = Runner::new(&args, |_| Ok(HeadlessFrontend))?;
+    runner.set_pacing(false);
+    let frames = args
+        .frames
+        .map(|frames| frames as usize)
+        .or(runner.playback_len())
+        .ok_or(HeadlessFrontendError::NoFrames)?;
+    runner.run_frames(frames)
+}
+
+/// Statically analyzes the ROM from the given path and prints the analysis,
+/// failing if it found problems.
+fn analyze(args: &Args) -> Result<(), HeadlessFrontendError> {
+    let rom = args.path.as_deref().ok_or(RunnerError::NoRom)?;
+    let analysis = Analysis::new(&args::read_rom(rom).map_err(RunnerError::from)?);
+    print!("{}", analysis);
+    if analysis.has_problems() {
+        return Err(HeadlessFrontendError::Problems);
+    }
+    Ok(())
+}
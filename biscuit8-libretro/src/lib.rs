@@ -0,0 +1,611 @@
+//! A libretro core for [`biscuit8`], so that it can run in RetroArch and other
+//! libretro frontends. The frontend drives the [`Core`] through the `retro_*`
+//! functions: video is an XRGB8888 framebuffer, audio is a batch of samples
+//! every frame, and input comes from the RetroPad (mapped to CHIP-8 keys like
+//! the other frontends' gamepads). Core options select the quirks preset, the
+//! speed, and the palette. The parts of the libretro API used are declared in
+//! [`libretro`].
+
+pub mod libretro;
+
+use biscuit8::{
+    audio::{self, Synth},
+    chip8::{self, Chip8, Platform},
+    color::{Palette, PalettePreset},
+    config,
+    filter::Filter,
+    gamepad::{self, Gamepads},
+    keys::Keys,
+    screen::{HEIGHT, WIDTH},
+};
+use libretro::*;
+use std::{
+    ffi::{c_char, c_uint, c_void, CStr, CString},
+    ptr, slice,
+    sync::{Mutex, OnceLock},
+};
+
+/// The key of the quirks preset option.
+const PLATFORM_KEY: &CStr = c"biscuit8_platform";
+
+/// The key of the speed option.
+const SPEED_KEY: &CStr = c"biscuit8_speed";
+
+/// The key of the palette option.
+const PALETTE_KEY: &CStr = c"biscuit8_palette";
+
+/// The speeds that can be chosen, starting with the default.
+const SPEEDS: [u32; 10] = [10, 5, 15, 20, 30, 50, 100, 200, 500, 1000];
+
+/// The RetroPad buttons that are mapped, named after the gamepad buttons used
+/// by the default gamepad keymap (see [`biscuit8::gamepad`]).
+const BUTTONS: [(c_uint, &str); 10] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, "DPadUp"),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, "DPadDown"),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, "DPadLeft"),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, "DPadRight"),
+    (RETRO_DEVICE_ID_JOYPAD_B, "South"),
+    (RETRO_DEVICE_ID_JOYPAD_A, "East"),
+    (RETRO_DEVICE_ID_JOYPAD_Y, "West"),
+    (RETRO_DEVICE_ID_JOYPAD_X, "North"),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, "Select"),
+    (RETRO_DEVICE_ID_JOYPAD_START, "Start"),
+];
+
+/// The callbacks given by the frontend.
+#[derive(Clone, Copy, Debug, Default)]
+struct Callbacks {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_sample_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+}
+
+/// The callbacks given by the frontend.
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+/// The core, once a game is loaded.
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+/// Returns the callbacks given by the frontend.
+fn callbacks() -> Callbacks {
+    *CALLBACKS.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Runs the closure with the core, if a game is loaded.
+fn with_core<T>(f: impl FnOnce(&mut Core) -> T) -> Option<T> {
+    let mut core = CORE.lock().unwrap_or_else(|err| err.into_inner());
+    core.as_mut().map(f)
+}
+
+/// Sends an environment command to the frontend, returning whether it was
+/// handled.
+fn environment<T>(cmd: c_uint, data: *mut T) -> bool {
+    match callbacks().environment {
+        Some(environment) => unsafe { environment(cmd, data.cast()) },
+        None => false,
+    }
+}
+
+/// Logs an error through the frontend, if it has a logging interface.
+fn log_error(err: impl std::fmt::Display) {
+    let mut callback = RetroLogCallback { log: None };
+    environment(RETRO_ENVIRONMENT_GET_LOG_INTERFACE, &mut callback);
+    if let (Some(log), Ok(message)) = (callback.log, CString::new(format!("biscuit8: {}", err))) {
+        unsafe { log(RETRO_LOG_ERROR, c"%s\n".as_ptr(), message.as_ptr()) };
+    }
+}
+
+/// Returns the value of the core option with the given key, if the frontend
+/// has one.
+fn variable(key: &CStr) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr(),
+        value: ptr::null(),
+    };
+    if !environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut variable) || variable.value.is_null() {
+        return None;
+    }
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+/// Returns the core options declared to the frontend, as descriptions followed
+/// by the possible values (the first being the default).
+fn variables() -> &'static [CString; 3] {
+    static VARIABLES: OnceLock<[CString; 3]> = OnceLock::new();
+    VARIABLES.get_or_init(|| {
+        let join = |values: Vec<String>| values.join("|");
+        let platforms = [Platform::Chip8, Platform::Schip, Platform::XoChip]
+            .map(|platform| format!("{:?}", platform).to_lowercase());
+        [
+            format!("Quirks preset; {}", join(platforms.to_vec())),
+            format!(
+                "Speed (instructions per frame); {}",
+                join(SPEEDS.map(|speed| speed.to_string()).to_vec())
+            ),
+            format!(
+                "Palette; {}",
+                join(PalettePreset::ALL.map(|preset| preset.to_string()).to_vec())
+            ),
+        ]
+        .map(|variable| CString::new(variable).expect("Options shouldn't contain NUL."))
+    })
+}
+
+/// Returns the descriptions of the RetroPad buttons, naming the CHIP-8 keys
+/// they're bound to.
+fn descriptions() -> &'static [CString; BUTTONS.len()] {
+    static DESCRIPTIONS: OnceLock<[CString; BUTTONS.len()]> = OnceLock::new();
+    DESCRIPTIONS.get_or_init(|| {
+        let keymap = gamepad::default_keymap();
+        BUTTONS.map(|(_, name)| {
+            let key = keymap.key(name).unwrap_or_default();
+            CString::new(format!("Key {:X}", key)).expect("Descriptions shouldn't contain NUL.")
+        })
+    })
+}
+
+/// The emulator along with everything needed to present it to the frontend.
+pub struct Core {
+    chip8: Chip8,
+    rom: Vec<u8>,
+    speed: u32,
+    keys: Keys,
+    gamepads: Gamepads,
+    held: [bool; BUTTONS.len()],
+    filter: Filter,
+    palette: Palette,
+    synth: Synth,
+    framebuffer: Vec<u32>,
+    halted: bool,
+    exposed: Option<[u8; chip8::RAM_SIZE]>,
+}
+
+impl Core {
+    /// Attempts to construct a core running the given ROM, configured by the
+    /// core options.
+    pub fn new(rom: &[u8]) -> Result<Self, chip8::Chip8Error> {
+        let mut core = Self {
+            chip8: Chip8::new(rom)?,
+            rom: rom.to_vec(),
+            speed: config::DEFAULT_SPEED,
+            keys: Keys::new(),
+            gamepads: Gamepads::new(gamepad::default_keymap()),
+            held: [false; BUTTONS.len()],
+            filter: Filter::default(),
+            palette: Palette::default(),
+            synth: Synth::default(),
+            framebuffer: vec![0; WIDTH * HEIGHT],
+            halted: false,
+            exposed: None,
+        };
+        core.apply_variables();
+        Ok(core)
+    }
+
+    /// Applies the core options set by the frontend.
+    fn apply_variables(&mut self) {
+        if let Some(platform) = variable(PLATFORM_KEY).and_then(|value| value.parse().ok()) {
+            let platform: Platform = platform;
+            self.chip8.set_quirks(platform.quirks());
+        }
+        if let Some(speed) = variable(SPEED_KEY).and_then(|value| value.parse().ok()) {
+            self.speed = speed;
+        }
+        if let Some(preset) = variable(PALETTE_KEY).and_then(|value| value.parse().ok()) {
+            let preset: PalettePreset = preset;
+            self.palette = preset.palette();
+        }
+    }
+
+    /// Restarts the ROM, keeping the quirks.
+    fn reset(&mut self) {
+        let quirks = self.chip8.quirks();
+        if let Ok(chip8) = Chip8::new(&self.rom) {
+            self.chip8 = chip8;
+            self.chip8.set_quirks(quirks);
+        }
+        self.filter = Filter::default();
+        self.halted = false;
+    }
+
+    /// Makes the emulator forget what it cached about the code the frontend
+    /// wrote (like for cheats) through the RAM given by
+    /// [`retro_get_memory_data`] since the last frame, found by comparing it
+    /// with how it was left.
+    fn invalidate_exposed(&mut self) {
+        let Some(exposed) = &mut self.exposed else {
+            return;
+        };
+        let memory = self.chip8.memory();
+        let changed = |(before, after): (&u8, &u8)| before != after;
+        let start = exposed.iter().zip(memory).position(changed);
+        let end = exposed.iter().zip(memory).rposition(changed);
+        if let (Some(start), Some(end)) = (start, end) {
+            self.chip8.invalidate_decoded(start..end + 1);
+        }
+    }
+
+    /// Polls the RetroPad, pressing and releasing the CHIP-8 keys bound to the
+    /// buttons that changed.
+    fn poll_input(&mut self, callbacks: &Callbacks) {
+        let Some(input_state) = callbacks.input_state else {
+            return;
+        };
+        if let Some(input_poll) = callbacks.input_poll {
+            unsafe { input_poll() };
+        }
+        for (i, (id, name)) in BUTTONS.iter().enumerate() {
+            let held = unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, *id) } != 0;
+            match (self.held[i], held) {
+                (false, true) => self.gamepads.press(0, name, &mut self.keys),
+                (true, false) => self.gamepads.release(0, name, Keys::new(), &mut self.keys),
+                _ => (),
+            }
+            self.held[i] = held;
+        }
+    }
+
+    /// Runs a frame: polls input, runs the emulator for as many instructions as
+    /// the speed says (unless it failed), and sends the video and audio to the
+    /// frontend.
+    fn run(&mut self) {
+        self.invalidate_exposed();
+        let callbacks = callbacks();
+        let mut updated = false;
+        if environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut updated) && updated {
+            self.apply_variables();
+        }
+        self.poll_input(&callbacks);
+        if !self.halted {
+            let mut cycles = self.speed as usize;
+            while cycles > 0 {
+                match self.chip8.run(self.keys, cycles) {
+                    Ok((executed, drew)) => {
+                        if drew {
+                            self.filter.draw(self.chip8.screen());
+                        }
+                        cycles -= executed;
+                    }
+                    Err(err) => {
+                        log_error(err);
+                        self.halted = true;
+                        break;
+                    }
+                }
+                self.keys.reset_last_pressed();
+            }
+        }
+        self.synth.set_playing(!self.halted && self.chip8.beeping());
+        self.chip8.decrement_timers();
+        if let Some(exposed) = &mut self.exposed {
+            *exposed = *self.chip8.memory();
+        }
+        self.filter.frame();
+
+        for (i, pixel) in self.framebuffer.iter_mut().enumerate() {
+            let [r, g, b] = self
+                .palette
+                .blend(self.filter.intensity(i % WIDTH, i / WIDTH));
+            *pixel = u32::from_be_bytes([0, r, g, b]);
+        }
+        if let Some(video_refresh) = callbacks.video_refresh {
+            let pitch = WIDTH * 4;
+            unsafe {
+                video_refresh(
+                    self.framebuffer.as_ptr().cast(),
+                    WIDTH as c_uint,
+                    HEIGHT as c_uint,
+                    pitch,
+                )
+            };
+        }
+        let samples: Vec<i16> = self
+            .synth
+            .frame()
+            .into_iter()
+            .flat_map(|sample| {
+                let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                [sample, sample]
+            })
+            .collect();
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            unsafe { audio_sample_batch(samples.as_ptr(), samples.len() / 2) };
+        }
+    }
+}
+
+/// Returns the version of the libretro API implemented.
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// Describes the core.
+///
+/// # Safety
+///
+/// `info` has to be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"biscuit8".as_ptr(),
+        library_version: c"0.1.0".as_ptr(),
+        valid_extensions: c"ch8".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// Describes the video and audio produced.
+///
+/// # Safety
+///
+/// `info` has to be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: WIDTH as c_uint,
+            base_height: HEIGHT as c_uint,
+            max_width: WIDTH as c_uint,
+            max_height: HEIGHT as c_uint,
+            aspect_ratio: WIDTH as f32 / HEIGHT as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: audio::FRAME_RATE as f64,
+            sample_rate: audio::SAMPLE_RATE as f64,
+        },
+    };
+}
+
+/// Stores the environment callback, declaring the core options.
+#[no_mangle]
+pub extern "C" fn retro_set_environment(environment: RetroEnvironment) {
+    CALLBACKS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .environment = Some(environment);
+    let variables = variables();
+    let mut declared = [
+        (PLATFORM_KEY, &variables[0]),
+        (SPEED_KEY, &variables[1]),
+        (PALETTE_KEY, &variables[2]),
+    ]
+    .map(|(key, value)| RetroVariable {
+        key: key.as_ptr(),
+        value: value.as_ptr(),
+    })
+    .into_iter()
+    .chain([RetroVariable {
+        key: ptr::null(),
+        value: ptr::null(),
+    }])
+    .collect::<Vec<_>>();
+    self::environment(RETRO_ENVIRONMENT_SET_VARIABLES, declared.as_mut_ptr());
+}
+
+/// Stores the video callback.
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: RetroVideoRefresh) {
+    CALLBACKS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .video_refresh = Some(video_refresh);
+}
+
+/// Ignores the single sample audio callback, since samples are sent in batches.
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: RetroAudioSample) {}
+
+/// Stores the batch audio callback.
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: RetroAudioSampleBatch) {
+    CALLBACKS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .audio_sample_batch = Some(audio_sample_batch);
+}
+
+/// Stores the input polling callback.
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: RetroInputPoll) {
+    CALLBACKS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .input_poll = Some(input_poll);
+}
+
+/// Stores the input state callback.
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: RetroInputState) {
+    CALLBACKS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .input_state = Some(input_state);
+}
+
+/// Initializes the core (there's nothing to do until a game is loaded).
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+/// Deinitializes the core, unloading the game.
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Ignores the device plugged into a port, since only the RetroPad is used.
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+/// Restarts the game.
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_core(Core::reset);
+}
+
+/// Runs a frame.
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    with_core(Core::run);
+}
+
+/// Returns how many bytes a saved state takes.
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    chip8::STATE_SIZE
+}
+
+/// Saves the emulator's state, returning whether it fit.
+///
+/// # Safety
+///
+/// `data` has to point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    if data.is_null() || size < chip8::STATE_SIZE {
+        return false;
+    }
+    let data = slice::from_raw_parts_mut(data.cast::<u8>(), chip8::STATE_SIZE);
+    with_core(|core| data.copy_from_slice(&core.chip8.save_state())).is_some()
+}
+
+/// Restores a saved state, returning whether it was valid.
+///
+/// # Safety
+///
+/// `data` has to point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    let data = slice::from_raw_parts(data.cast::<u8>(), size);
+    with_core(|core| {
+        let loaded = core.chip8.load_state(data).is_ok();
+        core.halted &= !loaded;
+        loaded
+    })
+    .unwrap_or(false)
+}
+
+/// Ignores cheats, which aren't supported.
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+/// Ignores cheats, which aren't supported.
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// Loads a ROM, returning whether it could be loaded.
+///
+/// # Safety
+///
+/// `game` has to be `NULL` or valid, with `data` pointing to `size` readable
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    let Some(game) = game.as_ref() else {
+        return false;
+    };
+    let rom = match (game.data.is_null(), game.path.is_null()) {
+        (false, _) => slice::from_raw_parts(game.data.cast::<u8>(), game.size).to_vec(),
+        (true, false) => match std::fs::read(&*CStr::from_ptr(game.path).to_string_lossy()) {
+            Ok(rom) => rom,
+            Err(_) => return false,
+        },
+        (true, true) => return false,
+    };
+    let mut format = RetroPixelFormat::Xrgb8888;
+    if !environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format) {
+        return false;
+    }
+    let mut descriptors = BUTTONS
+        .iter()
+        .zip(descriptions())
+        .map(|((id, _), description)| RetroInputDescriptor {
+            port: 0,
+            device: RETRO_DEVICE_JOYPAD,
+            index: 0,
+            id: *id,
+            description: description.as_ptr(),
+        })
+        .chain([RetroInputDescriptor {
+            port: 0,
+            device: 0,
+            index: 0,
+            id: 0,
+            description: ptr::null(),
+        }])
+        .collect::<Vec<_>>();
+    environment(
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+        descriptors.as_mut_ptr(),
+    );
+    match Core::new(&rom) {
+        Ok(core) => {
+            *CORE.lock().unwrap_or_else(|err| err.into_inner()) = Some(core);
+            true
+        }
+        Err(err) => {
+            log_error(err);
+            false
+        }
+    }
+}
+
+/// Doesn't load special games, which aren't supported.
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+/// Unloads the game.
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Returns the region of the game (always NTSC, since it runs at 60 frames a
+/// second).
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// Returns the emulator's RAM as the system RAM (no other memory is exposed).
+/// The frontend can write it at any time, which [`retro_run`] accounts for.
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(id: c_uint) -> *mut c_void {
+    match id {
+        RETRO_MEMORY_SYSTEM_RAM => with_core(|core| {
+            core.exposed = Some(*core.chip8.memory());
+            core.chip8.memory_mut().as_mut_ptr().cast()
+        })
+        .unwrap_or(ptr::null_mut()),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Returns the size of the emulator's RAM as the system RAM (no other memory is
+/// exposed).
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(id: c_uint) -> usize {
+    match id {
+        RETRO_MEMORY_SYSTEM_RAM if with_core(|_| ()).is_some() => chip8::RAM_SIZE,
+        _ => 0,
+    }
+}
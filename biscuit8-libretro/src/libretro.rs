@@ -0,0 +1,192 @@
+//! The parts of the libretro API (from `libretro.h`) used by the core: the
+//! structs passed between the core and the frontend, the callbacks the
+//! frontend provides, and the constants for environment commands, devices, and
+//! pixel formats.
+
+use std::ffi::{c_char, c_uint, c_void};
+
+/// The version of the libretro API implemented.
+pub const RETRO_API_VERSION: c_uint = 1;
+
+/// The RetroPad, a joypad modelled after the SNES controller with extra
+/// buttons.
+pub const RETRO_DEVICE_JOYPAD: c_uint = 1;
+
+/// The B button of the RetroPad (the bottom face button).
+pub const RETRO_DEVICE_ID_JOYPAD_B: c_uint = 0;
+
+/// The Y button of the RetroPad (the left face button).
+pub const RETRO_DEVICE_ID_JOYPAD_Y: c_uint = 1;
+
+/// The select button of the RetroPad.
+pub const RETRO_DEVICE_ID_JOYPAD_SELECT: c_uint = 2;
+
+/// The start button of the RetroPad.
+pub const RETRO_DEVICE_ID_JOYPAD_START: c_uint = 3;
+
+/// Up on the RetroPad's D-pad.
+pub const RETRO_DEVICE_ID_JOYPAD_UP: c_uint = 4;
+
+/// Down on the RetroPad's D-pad.
+pub const RETRO_DEVICE_ID_JOYPAD_DOWN: c_uint = 5;
+
+/// Left on the RetroPad's D-pad.
+pub const RETRO_DEVICE_ID_JOYPAD_LEFT: c_uint = 6;
+
+/// Right on the RetroPad's D-pad.
+pub const RETRO_DEVICE_ID_JOYPAD_RIGHT: c_uint = 7;
+
+/// The A button of the RetroPad (the right face button).
+pub const RETRO_DEVICE_ID_JOYPAD_A: c_uint = 8;
+
+/// The X button of the RetroPad (the top face button).
+pub const RETRO_DEVICE_ID_JOYPAD_X: c_uint = 9;
+
+/// The left shoulder button of the RetroPad.
+pub const RETRO_DEVICE_ID_JOYPAD_L: c_uint = 10;
+
+/// The right shoulder button of the RetroPad.
+pub const RETRO_DEVICE_ID_JOYPAD_R: c_uint = 11;
+
+/// The memory of the system (its RAM), for cheats and achievements.
+pub const RETRO_MEMORY_SYSTEM_RAM: c_uint = 2;
+
+/// The NTSC region (60 frames a second).
+pub const RETRO_REGION_NTSC: c_uint = 0;
+
+/// Sets the pixel format of the video callback (takes a
+/// `*const RetroPixelFormat`).
+pub const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+
+/// Describes the buttons used (takes a `*const RetroInputDescriptor` array
+/// ending with a zeroed descriptor).
+pub const RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+
+/// Gets the value of a core option (takes a `*mut RetroVariable`).
+pub const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+
+/// Declares the core options (takes a `*const RetroVariable` array ending with
+/// a zeroed variable).
+pub const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+
+/// Gets whether any core option changed since it was last gotten (takes a
+/// `*mut bool`).
+pub const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+
+/// Gets the frontend's logging callback (takes a `*mut RetroLogCallback`).
+pub const RETRO_ENVIRONMENT_GET_LOG_INTERFACE: c_uint = 27;
+
+/// The log level of errors.
+pub const RETRO_LOG_ERROR: c_uint = 3;
+
+/// The pixel formats of the video callback.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RetroPixelFormat {
+    /// 15 bit color with an unused top bit.
+    Zrgb1555 = 0,
+    /// 24 bit color with an unused top byte.
+    Xrgb8888 = 1,
+    /// 16 bit color.
+    Rgb565 = 2,
+}
+
+/// The frontend's callback for environment commands.
+pub type RetroEnvironment = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+
+/// The frontend's callback for a frame of video.
+pub type RetroVideoRefresh =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+
+/// The frontend's callback for a single stereo audio sample.
+pub type RetroAudioSample = unsafe extern "C" fn(left: i16, right: i16);
+
+/// The frontend's callback for a batch of interleaved stereo audio samples,
+/// returning how many frames it took.
+pub type RetroAudioSampleBatch = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+
+/// The frontend's callback for polling input.
+pub type RetroInputPoll = unsafe extern "C" fn();
+
+/// The frontend's callback for the state of an input.
+pub type RetroInputState =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+/// The frontend's callback for logging, taking a level and a `printf` format
+/// string with its arguments.
+pub type RetroLogPrintf = unsafe extern "C" fn(level: c_uint, fmt: *const c_char, ...);
+
+/// Describes the core.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroSystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+/// The size of the video.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroGameGeometry {
+    pub base_width: c_uint,
+    pub base_height: c_uint,
+    pub max_width: c_uint,
+    pub max_height: c_uint,
+    pub aspect_ratio: f32,
+}
+
+/// The rates of video and audio.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroSystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+/// The video and audio the core produces.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroSystemAvInfo {
+    pub geometry: RetroGameGeometry,
+    pub timing: RetroSystemTiming,
+}
+
+/// A loaded game (a ROM).
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroGameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+/// A core option: its key, and either its description and possible values (as
+/// `Description; first|second|...`) or its current value.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroVariable {
+    pub key: *const c_char,
+    pub value: *const c_char,
+}
+
+/// Describes what an input does.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroInputDescriptor {
+    pub port: c_uint,
+    pub device: c_uint,
+    pub index: c_uint,
+    pub id: c_uint,
+    pub description: *const c_char,
+}
+
+/// The frontend's logging interface.
+#[repr(C)]
+#[derive(Debug)]
+pub struct RetroLogCallback {
+    pub log: Option<RetroLogPrintf>,
+}
@@ -0,0 +1,311 @@
+//! A minimal libretro frontend that loads the core's shared library like
+//! RetroArch would, runs ROMs through it, and checks the video, audio, input,
+//! core options, and saved states it sees.
+
+use biscuit8_libretro::libretro::*;
+use libloading::{Library, Symbol};
+use std::{
+    env,
+    ffi::{c_uint, c_void, CStr},
+    fs,
+    path::PathBuf,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, AtomicU16, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// The last frame of video sent by the core.
+static VIDEO: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// How many frames of audio the core sent since the last check.
+static AUDIO_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// The loudest audio sample sent by the core since the last check.
+static AUDIO_PEAK: AtomicU16 = AtomicU16::new(0);
+
+/// The RetroPad buttons held, as a bit per ID.
+static BUTTONS: AtomicU16 = AtomicU16::new(0);
+
+/// Whether the core declared its options.
+static DECLARED: AtomicBool = AtomicBool::new(false);
+
+/// Answers the environment commands used by the core.
+unsafe extern "C" fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    match cmd {
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT => {
+            *data.cast::<RetroPixelFormat>() == RetroPixelFormat::Xrgb8888
+        }
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS => true,
+        RETRO_ENVIRONMENT_SET_VARIABLES => {
+            let variables = data.cast::<RetroVariable>();
+            let keys: Vec<_> = (0..)
+                .map(|i| &*variables.add(i))
+                .take_while(|variable| !variable.key.is_null())
+                .map(|variable| CStr::from_ptr(variable.key).to_str().unwrap())
+                .collect();
+            assert_eq!(
+                keys,
+                ["biscuit8_platform", "biscuit8_speed", "biscuit8_palette"]
+            );
+            DECLARED.store(true, Ordering::SeqCst);
+            true
+        }
+        RETRO_ENVIRONMENT_GET_VARIABLE => {
+            let variable = &mut *data.cast::<RetroVariable>();
+            variable.value = match CStr::from_ptr(variable.key).to_bytes() {
+                b"biscuit8_platform" => c"schip".as_ptr(),
+                b"biscuit8_speed" => c"20".as_ptr(),
+                _ => return false,
+            };
+            true
+        }
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE => {
+            *data.cast::<bool>() = false;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Stores a frame of video.
+unsafe extern "C" fn video_refresh(
+    data: *const c_void,
+    width: c_uint,
+    height: c_uint,
+    pitch: usize,
+) {
+    assert_eq!((width, height, pitch), (64, 32, 64 * 4));
+    let frame = std::slice::from_raw_parts(data.cast::<u32>(), (width * height) as usize);
+    *VIDEO.lock().unwrap() = frame.to_vec();
+}
+
+/// Fails, since the core should only send audio in batches.
+unsafe extern "C" fn audio_sample(_left: i16, _right: i16) {
+    panic!("Core should send audio in batches.");
+}
+
+/// Counts frames of audio, and keeps track of the loudest sample.
+unsafe extern "C" fn audio_sample_batch(data: *const i16, frames: usize) -> usize {
+    let samples = std::slice::from_raw_parts(data, frames * 2);
+    let peak = samples.iter().map(|sample| sample.unsigned_abs()).max();
+    AUDIO_PEAK.fetch_max(peak.unwrap_or_default(), Ordering::SeqCst);
+    AUDIO_FRAMES.fetch_add(frames, Ordering::SeqCst);
+    frames
+}
+
+/// Does nothing, since the buttons are set directly by the tests.
+unsafe extern "C" fn input_poll() {}
+
+/// Returns whether a RetroPad button is held.
+unsafe extern "C" fn input_state(port: c_uint, device: c_uint, _index: c_uint, id: c_uint) -> i16 {
+    let held =
+        port == 0 && device == RETRO_DEVICE_JOYPAD && BUTTONS.load(Ordering::SeqCst) & 1 << id != 0;
+    held as i16
+}
+
+/// The core, loaded from its shared library.
+struct Core {
+    library: &'static Library,
+}
+
+impl Core {
+    /// Loads the core's shared library, built next to the tests (in
+    /// `target/<profile>/deps`), and hands it the callbacks. The library is
+    /// never unloaded: destructors of the core's thread-locals can still run
+    /// after the test returns (when the harness's thread exits), and unloading
+    /// it before then would crash them.
+    fn load() -> Self {
+        let test = env::current_exe().expect("Test executable should have a path.");
+        let path = test.with_file_name(libloading::library_filename("biscuit8_libretro"));
+        let library = unsafe { Library::new(path) }.expect("Core should load.");
+        let core = Self {
+            library: Box::leak(Box::new(library)),
+        };
+        unsafe {
+            assert_eq!(
+                core.call::<unsafe extern "C" fn() -> c_uint>(b"retro_api_version")(),
+                1
+            );
+            core.call::<unsafe extern "C" fn(RetroEnvironment)>(b"retro_set_environment")(
+                environment,
+            );
+            core.call::<unsafe extern "C" fn(RetroVideoRefresh)>(b"retro_set_video_refresh")(
+                video_refresh,
+            );
+            core.call::<unsafe extern "C" fn(RetroAudioSample)>(b"retro_set_audio_sample")(
+                audio_sample,
+            );
+            core.call::<unsafe extern "C" fn(RetroAudioSampleBatch)>(
+                b"retro_set_audio_sample_batch",
+            )(audio_sample_batch);
+            core.call::<unsafe extern "C" fn(RetroInputPoll)>(b"retro_set_input_poll")(input_poll);
+            core.call::<unsafe extern "C" fn(RetroInputState)>(b"retro_set_input_state")(
+                input_state,
+            );
+            core.call::<unsafe extern "C" fn()>(b"retro_init")();
+        }
+        assert!(
+            DECLARED.load(Ordering::SeqCst),
+            "Core should declare options."
+        );
+        core
+    }
+
+    /// Returns one of the core's functions, given its function pointer type.
+    unsafe fn call<F>(&self, name: &[u8]) -> Symbol<'_, F> {
+        self.library
+            .get(name)
+            .expect("Core should export the function.")
+    }
+
+    /// Loads a ROM from the `roms` directory, returning whether the core took it.
+    fn load_game(&self, name: &str) -> bool {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join("roms")
+            .join(name);
+        self.load_rom(&fs::read(path).expect("ROM should be readable."))
+    }
+
+    /// Loads a ROM, returning whether the core took it.
+    fn load_rom(&self, rom: &[u8]) -> bool {
+        let game = RetroGameInfo {
+            path: ptr::null(),
+            data: rom.as_ptr().cast(),
+            size: rom.len(),
+            meta: ptr::null(),
+        };
+        unsafe {
+            self.call::<unsafe extern "C" fn(*const RetroGameInfo) -> bool>(b"retro_load_game")(
+                &game,
+            )
+        }
+    }
+
+    /// Runs some frames, returning the last frame of video.
+    fn run(&self, frames: usize) -> Vec<u32> {
+        for _ in 0..frames {
+            unsafe { self.call::<unsafe extern "C" fn()>(b"retro_run")() };
+        }
+        VIDEO.lock().unwrap().clone()
+    }
+
+    /// Returns the system RAM the core exposes, which stays valid (and
+    /// writable) while the game is loaded.
+    fn memory(&self) -> *mut u8 {
+        unsafe {
+            let data = self
+                .call::<unsafe extern "C" fn(c_uint) -> *mut c_void>(b"retro_get_memory_data")(
+                RETRO_MEMORY_SYSTEM_RAM,
+            );
+            let size = self.call::<unsafe extern "C" fn(c_uint) -> usize>(b"retro_get_memory_size")(
+                RETRO_MEMORY_SYSTEM_RAM,
+            );
+            assert!(!data.is_null());
+            assert_eq!(size, 0x1000);
+            data.cast()
+        }
+    }
+
+    /// Saves the core's state.
+    fn serialize(&self) -> Vec<u8> {
+        unsafe {
+            let size = self.call::<unsafe extern "C" fn() -> usize>(b"retro_serialize_size")();
+            let mut state = vec![0; size];
+            let serialize =
+                self.call::<unsafe extern "C" fn(*mut c_void, usize) -> bool>(b"retro_serialize");
+            assert!(serialize(state.as_mut_ptr().cast(), size));
+            state
+        }
+    }
+
+    /// Restores a saved state, returning whether the core took it.
+    fn unserialize(&self, state: &[u8]) -> bool {
+        unsafe {
+            self.call::<unsafe extern "C" fn(*const c_void, usize) -> bool>(b"retro_unserialize")(
+                state.as_ptr().cast(),
+                state.len(),
+            )
+        }
+    }
+}
+
+impl Drop for Core {
+    fn drop(&mut self) {
+        unsafe {
+            self.call::<unsafe extern "C" fn()>(b"retro_unload_game")();
+            self.call::<unsafe extern "C" fn()>(b"retro_deinit")();
+        }
+    }
+}
+
+// The core's state is global, so everything is checked in a single test.
+#[test]
+fn stub_host() {
+    let core = Core::load();
+    // Video: the IBM logo gets drawn.
+    assert!(core.load_game("ibm.ch8"));
+    let frame = core.run(60);
+    assert!(frame.iter().any(|&pixel| pixel != frame[0]));
+
+    // Audio: a frame's worth of samples is sent every frame, silent until
+    // something beeps.
+    AUDIO_FRAMES.store(0, Ordering::SeqCst);
+    AUDIO_PEAK.store(0, Ordering::SeqCst);
+    core.run(10);
+    assert_eq!(AUDIO_FRAMES.load(Ordering::SeqCst), 10 * 44100 / 60);
+    assert_eq!(AUDIO_PEAK.load(Ordering::SeqCst), 0);
+    assert!(core.load_game("beep.ch8"));
+    core.run(10);
+    assert_ne!(AUDIO_PEAK.load(Ordering::SeqCst), 0);
+
+    // Saved states: restoring one and running again ends up in the same state,
+    // and a garbage state is refused.
+    let state = core.serialize();
+    let idle = core.run(30);
+    let idle_state = core.serialize();
+    assert!(core.unserialize(&state));
+    assert_eq!(core.run(30), idle);
+    assert_eq!(core.serialize(), idle_state);
+    assert!(!core.unserialize(&[0; 16]));
+
+    // Input: holding a RetroPad button presses its CHIP-8 key (X is B, which
+    // the ROM waits for).
+    assert!(core.unserialize(&state));
+    BUTTONS.store(1 << RETRO_DEVICE_ID_JOYPAD_X, Ordering::SeqCst);
+    core.run(30);
+    BUTTONS.store(0, Ordering::SeqCst);
+    assert_ne!(core.serialize(), idle_state);
+
+    // ROMs reaching past the end of RAM through the index register (from
+    // 0xFFF or 0xFFE) halt the core instead of crashing the frontend.
+    let past_end: [&[u8]; 3] = [
+        &[0xAF, 0xFF, 0xD0, 0x0F],
+        &[0xAF, 0xFE, 0xF0, 0x33],
+        &[0xAF, 0xFF, 0xF0, 0x1E, 0xF0, 0x1E, 0xF5, 0x55],
+    ];
+    for rom in past_end {
+        assert!(core.load_rom(rom));
+        core.run(2);
+        let halted = core.serialize();
+        core.run(2);
+        assert_eq!(core.serialize(), halted);
+    }
+
+    // Writing the RAM exposed to the frontend (the way cheats do) between
+    // frames changes the code that runs, even code that already ran: the ROM
+    // spins until it's patched to draw a digit.
+    assert!(core.load_rom(&[0x12, 0x00, 0x00, 0x00, 0x00, 0x00]));
+    let ram = core.memory();
+    let blank = core.run(1);
+    assert!(blank.iter().all(|&pixel| pixel == blank[0]));
+    let patch = [0xA0, 0x00, 0xD0, 0x15, 0x12, 0x04];
+    unsafe { ptr::copy_nonoverlapping(patch.as_ptr(), ram.add(0x200), patch.len()) };
+    let frame = core.run(1);
+    assert!(frame.iter().any(|&pixel| pixel != frame[0]));
+
+    // Bad ROMs are refused.
+    assert!(!core.load_rom(&[0; 0x1000]));
+}
@@ -1,34 +1,86 @@
 //! A [`biscuit8`] frontend using [`pixels`] for rendering, [`winit`] for window
 //! management and input, and [`rodio`] for audio, primarily provided by
 //! [`PixelsFrontend`]. Errors are also represented by [`PixelsFrontendError`].
+//! ROMs (or Octo source code) can be dropped onto the window to load them, and
+//! recently loaded ROMs are remembered through [`RecentRoms`]. Gamepads are
+//! supported using [`gilrs`], and cheats can be toggled in a minimal overlay.
+
+mod recent;
 
 use biscuit8::{
-    args::{self, argh, Args, ArgsError, Layout},
-    chip8::{Chip8, Chip8Error},
-    keys::Keys,
-    screen::{self, Screen},
+    args::{self, argh, Args, Mapping},
+    color::Palette,
+    config::Settings,
+    filter::Filter,
+    frontend::{Frontend, Input, Status},
+    hotkeys::{Hotkey, Hotkeys},
+    runner::{Runner, RunnerError},
+    screen,
 };
+use gilrs::{EventType, Gilrs};
 use pixels::{wgpu::Color, Error, Pixels, PixelsBuilder, SurfaceTexture, TextureError};
-use rodio::{source::SineWave, OutputStream, PlayError, Sink, StreamError};
-use std::process::ExitCode;
+use recent::RecentRoms;
+use rodio::{buffer::SamplesBuffer, OutputStream, PlayError, Sink, StreamError};
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
 use thiserror::Error;
 use winit::{
     dpi::PhysicalSize,
     error::{EventLoopError, OsError},
     event::{Event, KeyEvent, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    keyboard::Key,
+    event_loop::EventLoop,
+    keyboard::{Key, NamedKey, PhysicalKey},
+    platform::{
+        modifier_supplement::KeyEventExtModifierSupplement,
+        pump_events::{EventLoopExtPumpEvents, PumpStatus},
+    },
     window::{Window, WindowBuilder},
 };
 
+/// A tiny ROM shown while waiting for a real one to be loaded; it draws "B8" in
+/// the middle of the screen and loops forever.
+const PLACEHOLDER_ROM: [u8; 22] = [
+    0x00, 0xE0, // Clear the screen
+    0x60, 0x0B, // V0 = 0xB
+    0xF0, 0x29, // I = font character in V0
+    0x61, 0x1B, // V1 = 27
+    0x62, 0x0D, // V2 = 13
+    0xD1, 0x25, // Draw at (V1, V2)
+    0x60, 0x08, // V0 = 0x8
+    0xF0, 0x29, // I = font character in V0
+    0x61, 0x20, // V1 = 32
+    0xD1, 0x25, // Draw at (V1, V2)
+    0x12, 0x14, // Jump here forever
+];
+
+/// The file extensions of ROMs that can be loaded (along with Octo source
+/// code, see [`args::SOURCE_EXTENSIONS`]).
+const ROM_EXTENSIONS: [&str; 1] = ["ch8"];
+
+/// How long messages and errors are shown in the window title.
+const NOTICE_DURATION: Duration = Duration::from_secs(4);
+
+/// How many frames of audio are kept queued for playback.
+const QUEUED_AUDIO_FRAMES: usize = 3;
+
+/// How many cheats the overlay shows at once, each in a row 4 pixels tall.
+const OVERLAY_ROWS: usize = 8;
+
 /// A frontend that uses [`pixels`] for rendering, [`winit`] for window
 /// managemenet and input, and [`rodio`] for audio.
 pub struct PixelsFrontend {
-    chip8: Chip8,
-    keys: Keys,
-    layout: Layout,
-    bg: [u8; 3],
-    fg: [u8; 3],
+    recent: RecentRoms,
+    hotkeys: Hotkeys,
+    status: Status,
+    cheats_open: bool,
+    cheat: usize,
+    notice: Option<(String, Instant)>,
+    quit: bool,
+    error: Option<PixelsFrontendError>,
+    gilrs: Option<Gilrs>,
     event_loop: Option<EventLoop<()>>,
     window: Window,
     pixels: Pixels,
@@ -37,48 +89,45 @@ pub struct PixelsFrontend {
 }
 
 impl PixelsFrontend {
-    /// Constructs a new [`pixels`] frontend using the provided emulator instance,
-    /// keyboard layout, colors, and ROM name.
-    pub fn new(
-        chip8: Chip8,
-        layout: Layout,
-        bg: [u8; 3],
-        fg: [u8; 3],
-        rom: &str,
-    ) -> Result<Self, PixelsFrontendError> {
+    /// Constructs a new [`pixels`] frontend with a window sized according to the
+    /// given settings. If there isn't a ROM, a placeholder screen is shown until
+    /// one is dropped onto the window.
+    pub fn new(settings: &Settings) -> Result<Self, PixelsFrontendError> {
+        let gilrs = Gilrs::new()
+            .inspect_err(|err| eprintln!("Gamepads are unavailable: {}.", err))
+            .ok();
         let event_loop = EventLoop::new()?;
         let window = {
-            let size = PhysicalSize::new(screen::WIDTH as u32, screen::HEIGHT as u32);
+            let min_size = PhysicalSize::new(screen::WIDTH as u32, screen::HEIGHT as u32);
+            let size = PhysicalSize::new(
+                min_size.width * settings.scale(),
+                min_size.height * settings.scale(),
+            );
             WindowBuilder::new()
-                .with_title(format!("{} - biscuit8-pixels", rom))
-                .with_min_inner_size(size)
+                .with_title("biscuit8-pixels")
+                .with_inner_size(size)
+                .with_min_inner_size(min_size)
                 .build(&event_loop)?
         };
         let pixels = {
             let size = window.inner_size();
             let surface_texture = SurfaceTexture::new(size.width, size.height, &window);
-            let clear_color = Color {
-                r: (fg[0] as f64) / 255.0,
-                g: (fg[1] as f64) / 255.0,
-                b: (fg[2] as f64) / 255.0,
-                a: 1.0,
-            };
             PixelsBuilder::new(screen::WIDTH as u32, screen::HEIGHT as u32, surface_texture)
-                .clear_color(clear_color)
                 .build()?
         };
         let (_stream, stream_handle) = OutputStream::try_default()?;
         let sink = Sink::try_new(&stream_handle)?;
-        let source = SineWave::new(700.0);
-        sink.append(source);
-        sink.pause();
 
         Ok(Self {
-            chip8,
-            fg,
-            bg,
-            layout,
-            keys: Keys::new(),
+            recent: RecentRoms::load(),
+            hotkeys: settings.hotkeys(),
+            status: Status::default(),
+            cheats_open: false,
+            cheat: 0,
+            notice: None,
+            quit: false,
+            error: None,
+            gilrs,
             event_loop: Some(event_loop),
             window,
             pixels,
@@ -87,37 +136,29 @@ impl PixelsFrontend {
         })
     }
 
-    /// The main loop managed by [`winit`]; almost everything happens here.
-    pub fn main_loop(mut self) -> Result<(), PixelsFrontendError> {
-        let event_loop = self
-            .event_loop
-            .take()
-            .expect("Event loop should've been initialized.");
-        event_loop.run(move |event, elwt| {
-            elwt.set_control_flow(ControlFlow::Poll);
-            if let Err(err) = self.event_handler(event) {
-                eprintln!("{}", err);
-                elwt.exit();
-            }
-        })?;
-        Ok(())
-    }
-
-    /// Handles [`winit`] events (window management, logic, rendering).
-    fn event_handler(&mut self, event: Event<()>) -> Result<(), PixelsFrontendError> {
+    /// Handles [`winit`] events (window management, input).
+    fn event_handler(
+        &mut self,
+        event: Event<()>,
+        input: &mut Input,
+    ) -> Result<(), PixelsFrontendError> {
         match event {
-            Event::WindowEvent { event, .. } => self.window_event_handler(event),
-            Event::AboutToWait => self.instruction_cycle(),
+            Event::WindowEvent { event, .. } => self.window_event_handler(event, input),
             _ => Ok(()),
         }
     }
 
     /// Handles [`winit`] window events (scale, window state, input).
-    fn window_event_handler(&mut self, event: WindowEvent) -> Result<(), PixelsFrontendError> {
+    fn window_event_handler(
+        &mut self,
+        event: WindowEvent,
+        input: &mut Input,
+    ) -> Result<(), PixelsFrontendError> {
         match event {
             WindowEvent::Resized(size) => self.pixels.resize_surface(size.width, size.height)?,
-            WindowEvent::CloseRequested => return Err(PixelsFrontendError::WindowClose),
-            WindowEvent::KeyboardInput { event, .. } => self.key_handler(event),
+            WindowEvent::CloseRequested => self.quit = true,
+            WindowEvent::KeyboardInput { event, .. } => self.key_handler(event, input),
+            WindowEvent::DroppedFile(path) => self.drop_handler(path, input),
             WindowEvent::ScaleFactorChanged { .. } => {
                 let size = self.window.inner_size();
                 self.pixels.resize_surface(size.width, size.height)?
@@ -129,115 +170,351 @@ impl PixelsFrontend {
     }
 
     /// Handles keyboard input.
-    fn key_handler(&mut self, key_event: KeyEvent) {
-        let Key::Character(character) = key_event.logical_key else {
+    fn key_handler(&mut self, key_event: KeyEvent, input: &mut Input) {
+        if self.cheats_open && self.cheat_key_handler(&key_event, input) {
             return;
-        };
-        let Some(key) = (match self.layout {
-            Layout::Qwerty => Self::qwerty_character_to_key(&character),
-            Layout::Colemak => Self::colemak_character_to_key(&character),
-        }) else {
+        }
+        let pressed = key_event.state.is_pressed();
+        if let Some(name) = Self::hotkey_name(&key_event) {
+            if input.hotkey(&name, pressed && !key_event.repeat) {
+                return;
+            }
+        }
+        let Some(name) = Self::key_name(&key_event, input.mapping()) else {
             return;
         };
-        if key_event.state.is_pressed() {
-            self.keys.press_key(key);
+        if pressed {
+            input.press(&name);
         } else {
-            self.keys.release_key(key);
+            input.release(&name);
         }
     }
 
-    /// Converts [`winit`]'s string character representation into a numeric
-    /// CHIP-8 key using QWERTY.
-    fn qwerty_character_to_key(character: &str) -> Option<u8> {
-        Some(match character {
-            "1" => 0x1,
-            "2" => 0x2,
-            "3" => 0x3,
-            "4" => 0xC,
-            "q" => 0x4,
-            "w" => 0x5,
-            "e" => 0x6,
-            "r" => 0xD,
-            "a" => 0x7,
-            "s" => 0x8,
-            "d" => 0x9,
-            "f" => 0xE,
-            "z" => 0xA,
-            "x" => 0x0,
-            "c" => 0xB,
-            "v" => 0xF,
-            _ => return None,
-        })
+    /// Handles the keys used by the cheat overlay while it's shown: the up and
+    /// down arrows select a cheat, space toggles it, and escape hides the
+    /// overlay. Returns whether the key was one of them.
+    fn cheat_key_handler(&mut self, key_event: &KeyEvent, input: &mut Input) -> bool {
+        let Key::Named(named) = key_event.key_without_modifiers() else {
+            return false;
+        };
+        let pressed = key_event.state.is_pressed();
+        let len = self.status.cheats.cheats().len();
+        match named {
+            NamedKey::ArrowUp if pressed => self.cheat = self.cheat.saturating_sub(1),
+            NamedKey::ArrowDown if pressed => {
+                self.cheat = (self.cheat + 1).min(len.saturating_sub(1))
+            }
+            NamedKey::Space if pressed && !key_event.repeat => input.toggle_cheat(self.cheat),
+            NamedKey::Escape if pressed => self.cheats_open = false,
+            NamedKey::ArrowUp | NamedKey::ArrowDown | NamedKey::Space | NamedKey::Escape => (),
+            _ => return false,
+        }
+        self.notice = None;
+        self.update_title();
+        true
     }
 
-    /// Converts [`winit`]'s string character representation into a numeric
-    /// CHIP-8 key using Colemak.
-    fn colemak_character_to_key(character: &str) -> Option<u8> {
-        Some(match character {
-            "1" => 0x1,
-            "2" => 0x2,
-            "3" => 0x3,
-            "4" => 0xC,
-            "q" => 0x4,
-            "w" => 0x5,
-            "f" => 0x6,
-            "p" => 0xD,
-            "a" => 0x7,
-            "r" => 0x8,
-            "s" => 0x9,
-            "t" => 0xE,
-            "z" => 0xA,
-            "x" => 0x0,
-            "c" => 0xB,
-            "v" => 0xF,
-            _ => return None,
-        })
+    /// Draws the cheat overlay over the screen, which is dimmed: a box for
+    /// every cheat (filled when it's enabled) next to a bar (highlighted for the
+    /// selected cheat, whose name is in the window title).
+    fn draw_cheats(&mut self, palette: &Palette) {
+        let frame = self.pixels.frame_mut();
+        for pixel in frame.chunks_exact_mut(4) {
+            for component in &mut pixel[0..3] {
+                *component /= 4;
+            }
+        }
+        let mut set_pixel = |x: usize, y: usize, intensity: f32| {
+            let i = (y * screen::WIDTH + x) * 4;
+            frame[i..i + 3].copy_from_slice(&palette.blend(intensity));
+        };
+        let first = self.cheat.saturating_sub(OVERLAY_ROWS - 1);
+        let cheats = self.status.cheats.cheats().iter().enumerate();
+        for (row, (i, cheat)) in cheats.skip(first).take(OVERLAY_ROWS).enumerate() {
+            let top = row * 4 + 1;
+            for y in top..top + 3 {
+                for x in 1..4 {
+                    let edge = x != 2 || y != top + 1;
+                    if edge || cheat.enabled {
+                        set_pixel(x, y, 1.0);
+                    }
+                }
+            }
+            let intensity = if i == self.cheat { 1.0 } else { 0.4 };
+            for x in 6..screen::WIDTH - 1 {
+                set_pixel(x, top + 1, intensity);
+            }
+        }
+    }
+
+    /// Returns the name of the key used by the keymap according to the mapping:
+    /// the character it types when logical (ignoring modifiers like shift and
+    /// caps lock), or its position when physical.
+    fn key_name(key_event: &KeyEvent, mapping: Mapping) -> Option<String> {
+        match mapping {
+            Mapping::Logical => match key_event.key_without_modifiers() {
+                Key::Character(character) => Some(character.to_lowercase()),
+                _ => None,
+            },
+            Mapping::Physical => match key_event.physical_key {
+                PhysicalKey::Code(code) => Some(format!("{:?}", code)),
+                PhysicalKey::Unidentified(_) => None,
+            },
+        }
+    }
+
+    /// Returns the name of the key used by the hotkeys: the name of a named key,
+    /// or the character typed by any other key.
+    fn hotkey_name(key_event: &KeyEvent) -> Option<String> {
+        match key_event.key_without_modifiers() {
+            Key::Named(named) => Some(format!("{:?}", named)),
+            Key::Character(character) => Some(character.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Requests the dropped file to be loaded, if it's a ROM or Octo source
+    /// code (which is assembled when loaded). Dropping anything else isn't
+    /// fatal; the error is shown and the current ROM keeps running.
+    fn drop_handler(&mut self, path: PathBuf, input: &mut Input) {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let extension = extension.as_str();
+        if ROM_EXTENSIONS.contains(&extension) || args::SOURCE_EXTENSIONS.contains(&extension) {
+            input.load_rom(path);
+        } else {
+            self.message(&PixelsFrontendError::NotRom(path).to_string());
+        }
+    }
+
+    /// Handles gamepad input (including gamepads being connected or
+    /// disconnected) using [`gilrs`].
+    fn gamepad_handler(&mut self, input: &mut Input) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        while let Some(event) = gilrs.next_event() {
+            let id = usize::from(event.id);
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    input.gamepad_press(id, &format!("{:?}", button))
+                }
+                EventType::ButtonReleased(button, _) => {
+                    input.gamepad_release(id, &format!("{:?}", button))
+                }
+                EventType::AxisChanged(axis, value, _) => {
+                    input.gamepad_axis(id, &format!("{:?}", axis), value)
+                }
+                EventType::Disconnected => input.gamepad_disconnect(id),
+                _ => (),
+            }
+        }
+    }
+
+    /// Saves the recent ROMs, reporting (but otherwise ignoring) any failure.
+    fn save_recent(recent: &RecentRoms) {
+        if let Err(err) = recent.save() {
+            eprintln!("Couldn't save recent ROMs: {}.", err);
+        }
+    }
+
+    /// Shows a message in the window title for a few seconds.
+    fn notify(&mut self, notice: String) {
+        self.notice = Some((notice, Instant::now() + NOTICE_DURATION));
+        self.update_title();
     }
 
-    /// Updates the emulator and gets the frontend to act accordingly.
-    fn instruction_cycle(&mut self) -> Result<(), PixelsFrontendError> {
-        let output = self.chip8.instruction_cycle(self.keys)?;
-        if let Some(screen) = output.0 {
-            self.draw_screen(screen);
+    /// Updates the window title to show the latest message, the current ROM,
+    /// the selected recent ROM, or the selected cheat.
+    fn update_title(&self) {
+        let cheat = self
+            .status
+            .cheats
+            .cheats()
+            .get(self.cheat)
+            .filter(|_| self.cheats_open);
+        let recent = (self.recent.selected(), self.recent.position());
+        let title = match (&self.notice, cheat, recent) {
+            (Some((notice, _)), _, _) => format!("{} - biscuit8-pixels", notice),
+            (None, Some(cheat), _) => format!(
+                "[CHEAT {}/{}] {} is {} (arrows to select, space to toggle, {} to close) - biscuit8-pixels",
+                self.cheat + 1,
+                self.status.cheats.cheats().len(),
+                cheat.name,
+                if cheat.enabled { "on" } else { "off" },
+                self.hotkeys.key(Hotkey::Cheats).unwrap_or("escape"),
+            ),
+            (None, None, (Some(rom), Some((position, len)))) => format!(
+                "[{}/{}] {} ({} to load, {} to cancel) - biscuit8-pixels",
+                position,
+                len,
+                rom.to_string_lossy(),
+                self.hotkeys.key(Hotkey::RecentLoad).unwrap_or("unbound"),
+                self.hotkeys.key(Hotkey::RecentCancel).unwrap_or("unbound"),
+            ),
+            _ => Self::title(self.status.rom.as_deref(), &self.hotkeys),
+        };
+        let paused = if self.status.paused { "[PAUSED] " } else { "" };
+        let recording = if self.status.recording { "[REC] " } else { "" };
+        self.window
+            .set_title(&format!("{}{}{}", recording, paused, title));
+    }
+
+    /// Returns the window title used for the given ROM.
+    fn title(rom: Option<&Path>, hotkeys: &Hotkeys) -> String {
+        match rom {
+            Some(rom) => format!("{} - biscuit8-pixels", rom.to_string_lossy()),
+            None => format!(
+                "Drop a ROM to start ({} for recent) - biscuit8-pixels",
+                hotkeys.key(Hotkey::RecentNext).unwrap_or("unbound")
+            ),
         }
-        self.beep(output.1);
-        self.keys.reset_last_pressed();
+    }
+}
+
+impl Frontend for PixelsFrontend {
+    type Error = PixelsFrontendError;
+
+    const PLACEHOLDER_ROM: Option<&'static [u8]> = Some(&PLACEHOLDER_ROM);
+
+    /// Pumps [`winit`] events until the deadline (or until there are some), and
+    /// then handles gamepad input.
+    fn poll_input(&mut self, input: &mut Input, deadline: Instant) -> Result<(), Self::Error> {
+        if self.hotkeys != *input.hotkeys() {
+            self.hotkeys = input.hotkeys().clone();
+            self.update_title();
+        }
+        let now = Instant::now();
+        if self.notice.as_ref().is_some_and(|(_, until)| *until <= now) {
+            self.notice = None;
+            self.update_title();
+        }
+        let mut event_loop = self
+            .event_loop
+            .take()
+            .expect("Event loop should've been initialized.");
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let status = event_loop.pump_events(Some(timeout), |event, _| {
+            if self.error.is_none() {
+                self.error = self.event_handler(event, input).err();
+            }
+        });
+        self.event_loop = Some(event_loop);
+        if let PumpStatus::Exit(_) = status {
+            self.quit = true;
+        }
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.gamepad_handler(input);
         Ok(())
     }
 
-    /// Draws the provided screen to the pixels buffer.
-    fn draw_screen(&mut self, screen: Screen) {
+    /// Draws the screen shown by the display filter to the pixels buffer and
+    /// renders it.
+    fn present(&mut self, filter: &Filter, palette: &Palette) -> Result<(), Self::Error> {
+        let [r, g, b] = palette.fg().map(|component| component as f64 / 255.0);
+        self.pixels.clear_color(Color { r, g, b, a: 1.0 });
         let frame = self.pixels.frame_mut();
         for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
-            let x = i % screen::WIDTH;
-            let y = i / screen::WIDTH;
-            if screen.pixel(x, y) {
-                pixel[0..3].copy_from_slice(&self.fg);
-                pixel[3] = 255;
-            } else {
-                pixel[0..3].copy_from_slice(&self.bg);
-                pixel[3] = 255;
+            let intensity = filter.intensity(i % screen::WIDTH, i / screen::WIDTH);
+            pixel[0..3].copy_from_slice(&palette.blend(intensity));
+            pixel[3] = 255;
+        }
+        if self.cheats_open {
+            self.draw_cheats(palette);
+        }
+        self.pixels.render()?;
+        Ok(())
+    }
+
+    /// Pushes a frame of the synthesizer's samples to [`rodio`]. Frames are
+    /// dropped once enough are queued, and silence is queued if playback runs
+    /// dry, which keeps the frontend in step with the audio device without
+    /// building up latency.
+    fn set_sound(&mut self, _beeping: bool, samples: &[f32]) -> Result<(), Self::Error> {
+        let sample_rate = self.sample_rate();
+        if self.sink.empty() {
+            let silence = vec![0.0; samples.len()];
+            self.sink
+                .append(SamplesBuffer::new(1, sample_rate, silence));
+        }
+        if self.sink.len() < QUEUED_AUDIO_FRAMES {
+            self.sink
+                .append(SamplesBuffer::new(1, sample_rate, samples));
+        }
+        Ok(())
+    }
+
+    fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Performs the action of the pressed hotkey (those for recent ROMs and the
+    /// cheat overlay).
+    fn hotkey(&mut self, hotkey: Hotkey, input: &mut Input) -> Result<(), Self::Error> {
+        self.notice = None;
+        match hotkey {
+            Hotkey::RecentNext => {
+                self.recent.select_next();
             }
+            Hotkey::RecentLoad => {
+                if let Some(rom) = self.recent.selected() {
+                    input.load_rom(rom.to_path_buf());
+                }
+                self.recent.deselect();
+            }
+            Hotkey::RecentCancel => self.recent.deselect(),
+            Hotkey::Cheats if self.status.cheats.is_empty() => {
+                self.message("There aren't any cheats for this ROM.")
+            }
+            Hotkey::Cheats => self.cheats_open = !self.cheats_open,
+            _ => (),
         }
-        self.window.request_redraw();
+        self.update_title();
+        Ok(())
     }
 
-    /// Makes a beeping noise using [`rodio`].
-    fn beep(&self, beep: bool) {
-        if beep {
-            self.sink.play();
-        } else {
-            self.sink.pause();
+    /// Shows the message in the window title for a few seconds, also printing
+    /// it.
+    fn message(&mut self, message: &str) {
+        println!("{}", message);
+        self.notify(message.to_string());
+    }
+
+    /// Shows the error in the window title for a few seconds, also printing it.
+    fn report(&mut self, error: &RunnerError) {
+        eprintln!("{}", error);
+        self.notify(error.to_string());
+    }
+
+    /// Shows the status in the window title, remembering newly loaded ROMs.
+    fn status(&mut self, status: &Status) -> Result<(), Self::Error> {
+        if let Some(rom) = status
+            .rom
+            .as_deref()
+            .filter(|_| status.rom != self.status.rom)
+        {
+            self.recent.push(rom);
+            Self::save_recent(&self.recent);
         }
+        self.status = status.clone();
+        let len = self.status.cheats.cheats().len();
+        self.cheats_open &= len > 0;
+        self.cheat = self.cheat.min(len.saturating_sub(1));
+        self.update_title();
+        Ok(())
     }
 }
 
 /// Used to describe possible errors caused by pixels, winit, rodio, or the
-/// emulator.
+/// runner (the arguments, the emulator, or writing files).
 #[derive(Debug, Error)]
 pub enum PixelsFrontendError {
     #[error("{0}")]
-    Args(#[from] ArgsError),
+    Runner(#[from] RunnerError),
     #[error("{0}")]
     EventLoop(#[from] EventLoopError),
     #[error("{0}")]
@@ -246,14 +523,12 @@ pub enum PixelsFrontendError {
     Pixels(#[from] Error),
     #[error("{0}")]
     Stream(#[from] StreamError),
-    #[error("Window close requested.")]
-    WindowClose,
     #[error("{0}")]
     Texture(#[from] TextureError),
     #[error("{0}")]
-    Chip8(#[from] Chip8Error),
-    #[error("{0}")]
     PlayError(#[from] PlayError),
+    #[error("{} isn't a .ch8 ROM or .8o Octo source code.", .0.display())]
+    NotRom(PathBuf),
 }
 
 /// Same old "exciting" entry point.
@@ -265,18 +540,9 @@ fn main() -> ExitCode {
     ExitCode::SUCCESS
 }
 
-/// Gets the ROM from the given path and starts the main instruction loop with
-/// some options/settings.
+/// Gets the ROM from the given path (if there is one) and runs it with some
+/// options/settings until the window is closed.
 fn main_loop() -> Result<(), PixelsFrontendError> {
     let args = argh::from_env::<Args>();
-    let chip8 = args.chip8()?;
-    let frontend = PixelsFrontend::new(
-        chip8,
-        args.layout,
-        args::hex_to_rgb(args.bg)?,
-        args::hex_to_rgb(args.fg)?,
-        &args.path.to_string_lossy(),
-    )?;
-    frontend.main_loop()?;
-    Ok(())
+    Runner::new(&args, PixelsFrontend::new)?.run()
 }
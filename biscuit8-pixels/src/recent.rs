@@ -0,0 +1,98 @@
+//! Keeps track of recently loaded ROMs through [`RecentRoms`], which is saved
+//! to the user's data directory so that it persists between sessions.
+
+use std::{
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+
+/// The maximum amount of ROMs remembered.
+const MAX_RECENT: usize = 10;
+
+/// A list of recently loaded ROMs (most recent first), along with the entry
+/// currently selected by the user.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RecentRoms {
+    roms: Vec<PathBuf>,
+    selected: Option<usize>,
+}
+
+impl RecentRoms {
+    /// Loads the list of recent ROMs from the data directory, ignoring any
+    /// entries that no longer exist. An empty list is returned if there isn't
+    /// one saved yet.
+    pub fn load() -> Self {
+        let roms = Self::file()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|list| {
+                list.lines()
+                    .map(PathBuf::from)
+                    .filter(|path| path.is_file())
+                    .take(MAX_RECENT)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            roms,
+            selected: None,
+        }
+    }
+
+    /// Saves the list of recent ROMs to the data directory.
+    pub fn save(&self) -> Result<(), IoError> {
+        let Some(file) = Self::file() else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let list = self
+            .roms
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(file, list)
+    }
+
+    /// Moves the given ROM to the front of the list, forgetting the oldest one
+    /// if there are too many.
+    pub fn push(&mut self, rom: &Path) {
+        let rom = rom.canonicalize().unwrap_or_else(|_| rom.to_path_buf());
+        self.roms.retain(|path| *path != rom);
+        self.roms.insert(0, rom);
+        self.roms.truncate(MAX_RECENT);
+        self.selected = None;
+    }
+
+    /// Selects the next ROM in the list (wrapping around) and returns it.
+    pub fn select_next(&mut self) -> Option<&Path> {
+        if self.roms.is_empty() {
+            return None;
+        }
+        let next = self.selected.map_or(0, |i| (i + 1) % self.roms.len());
+        self.selected = Some(next);
+        self.selected()
+    }
+
+    /// Returns the currently selected ROM, if there is one.
+    pub fn selected(&self) -> Option<&Path> {
+        self.roms.get(self.selected?).map(PathBuf::as_path)
+    }
+
+    /// Returns the position of the selection and the length of the list.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        Some((self.selected? + 1, self.roms.len()))
+    }
+
+    /// Stops selecting any ROM.
+    pub fn deselect(&mut self) {
+        self.selected = None;
+    }
+
+    /// The file the list is saved in.
+    fn file() -> Option<PathBuf> {
+        Some(dirs::data_dir()?.join("biscuit8").join("recent"))
+    }
+}
@@ -0,0 +1,21 @@
+"""Plays a ROM by pressing random keys, Gym style, and counts lit pixels."""
+
+import random
+import sys
+
+import numpy as np
+
+import biscuit8
+
+with open(sys.argv[1], "rb") as f:
+    env = biscuit8.Chip8(f.read(), platform="chip8", speed=10)
+
+observation = env.reset(seed=0)
+for _ in range(600):
+    action = 1 << random.randrange(16)
+    observation, reward, terminated, truncated, info = env.step(action)
+    if terminated or truncated:
+        print("Stopped:", info.get("error"))
+        break
+
+print("Lit pixels:", int(np.asarray(observation).sum()))
@@ -0,0 +1,344 @@
+//! Python bindings for [`biscuit8`] using [`pyo3`], so that ROMs can be
+//! scripted from tests and reinforcement learning experiments. [`Chip8`]
+//! follows the Gym pattern for game environments: [`reset`](Chip8::reset)
+//! restarts the ROM and returns an observation, and [`step`](Chip8::step)
+//! takes an action (the keys to hold), runs a frame, and returns the next
+//! observation. Observations are [`Screen`]s, which support Python's buffer
+//! protocol, so `numpy.asarray(screen)` gives a `(32, 64)` array of `uint8`
+//! without copying. Built with `maturin develop` (or `maturin build`).
+
+use biscuit8::{
+    chip8::{self, Chip8Error, Platform},
+    keys::Keys,
+    screen::{HEIGHT, WIDTH},
+};
+use pyo3::{
+    exceptions::{PyBufferError, PyIndexError, PyRuntimeError, PyValueError},
+    ffi,
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
+use std::{
+    ffi::{c_int, c_void},
+    ptr,
+};
+
+/// How many instructions are executed every frame by default.
+const DEFAULT_SPEED: u32 = 10;
+
+/// Converts an emulator error into a Python exception.
+fn runtime_error(err: Chip8Error) -> PyErr {
+    match err {
+        Chip8Error::InvalidAddress(_) | Chip8Error::InvalidRegister(_) => {
+            PyIndexError::new_err(err.to_string())
+        }
+        _ => PyRuntimeError::new_err(err.to_string()),
+    }
+}
+
+/// A CHIP-8 emulator running a ROM, as a Gym-style environment.
+#[pyclass(module = "biscuit8")]
+pub struct Chip8 {
+    chip8: chip8::Chip8,
+    rom: Vec<u8>,
+    platform: Platform,
+    keys: Keys,
+    speed: u32,
+    frames: u64,
+    error: Option<String>,
+}
+
+#[pymethods]
+impl Chip8 {
+    /// The width of the screen in pixels.
+    #[classattr]
+    const WIDTH: usize = WIDTH;
+
+    /// The height of the screen in pixels.
+    #[classattr]
+    const HEIGHT: usize = HEIGHT;
+
+    /// Constructs an emulator running the given ROM with the quirks of the
+    /// given platform (`chip8`, `schip`, or `xochip`), executing `speed`
+    /// instructions every frame, and optionally seeding its random number
+    /// generator.
+    #[new]
+    #[pyo3(signature = (rom, platform = "chip8", speed = DEFAULT_SPEED, seed = None))]
+    fn new(rom: &[u8], platform: &str, speed: u32, seed: Option<u64>) -> PyResult<Self> {
+        let platform = platform
+            .parse::<Platform>()
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        let mut chip8 = Self {
+            chip8: chip8::Chip8::new(rom).map_err(|err| PyValueError::new_err(err.to_string()))?,
+            rom: rom.to_vec(),
+            platform,
+            keys: Keys::new(),
+            speed,
+            frames: 0,
+            error: None,
+        };
+        chip8.restart(seed);
+        Ok(chip8)
+    }
+
+    /// Restarts the ROM (optionally seeding the random number generator) and
+    /// returns the first observation.
+    #[pyo3(signature = (seed = None))]
+    fn reset(&mut self, seed: Option<u64>) -> Screen {
+        self.restart(seed);
+        self.screen()
+    }
+
+    /// Holds the keys in the action (a bitmask with bit `n` set for key `n`),
+    /// runs a frame, and returns `(observation, reward, terminated, truncated,
+    /// info)`. The reward is always `0.0`, since it depends on the game (it can
+    /// be worked out from RAM with [`peek`](Self::peek)); the episode is
+    /// terminated if the emulator fails, with the error in `info["error"]`.
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: u16,
+    ) -> PyResult<(Screen, f64, bool, bool, Bound<'py, PyDict>)> {
+        self.set_keys(action);
+        if self.error.is_none() {
+            if let Err(err) = self.frame() {
+                self.error = Some(err.to_string());
+            }
+        }
+        let info = PyDict::new_bound(py);
+        info.set_item("frame", self.frames)?;
+        info.set_item("beeping", self.chip8.beeping())?;
+        if let Some(error) = &self.error {
+            info.set_item("error", error)?;
+        }
+        Ok((self.screen(), 0.0, self.error.is_some(), false, info))
+    }
+
+    /// Runs a frame, executing as many instructions as the speed says. A
+    /// `RuntimeError` is raised if the emulator fails.
+    fn run_frame(&mut self) -> PyResult<()> {
+        self.frame().map_err(runtime_error)
+    }
+
+    /// Holds the given keys (a bitmask with bit `n` set for key `n`), releasing
+    /// the others.
+    fn set_keys(&mut self, keys: u16) {
+        let pressed = keys & !self.keys.raw();
+        let last_pressed = (pressed != 0).then(|| pressed.trailing_zeros() as u8);
+        self.keys = Keys::from_raw(keys, last_pressed.or(self.keys.last_pressed()));
+    }
+
+    /// The keys held, as a bitmask with bit `n` set for key `n`.
+    #[getter]
+    fn keys(&self) -> u16 {
+        self.keys.raw()
+    }
+
+    /// How many instructions are executed every frame.
+    #[getter]
+    fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    #[setter]
+    fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+    }
+
+    /// How many frames have been run since the ROM was (re)started.
+    #[getter]
+    fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Whether the beep should be playing.
+    #[getter]
+    fn beeping(&self) -> bool {
+        self.chip8.beeping()
+    }
+
+    /// Returns the screen.
+    fn screen(&self) -> Screen {
+        let screen = self.chip8.screen();
+        let pixels = (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| screen.pixel(x, y) as u8))
+            .collect();
+        Screen {
+            pixels,
+            shape: [HEIGHT as isize, WIDTH as isize],
+            strides: [WIDTH as isize, 1],
+        }
+    }
+
+    /// Returns the byte of RAM at the address.
+    fn peek(&self, address: usize) -> PyResult<u8> {
+        self.chip8.peek(address).map_err(runtime_error)
+    }
+
+    /// Writes a byte of RAM at the address.
+    fn poke(&mut self, address: usize, value: u8) -> PyResult<()> {
+        self.chip8.poke(address, value).map_err(runtime_error)
+    }
+
+    /// Returns the value of the register `V[x]`.
+    fn register(&self, x: usize) -> PyResult<u8> {
+        let registers = self.chip8.registers();
+        registers
+            .get(x)
+            .copied()
+            .ok_or_else(|| runtime_error(Chip8Error::InvalidRegister(x)))
+    }
+
+    /// Sets the value of the register `V[x]`.
+    fn set_register(&mut self, x: usize, value: u8) -> PyResult<()> {
+        self.chip8.set_register(x, value).map_err(runtime_error)
+    }
+
+    /// The program counter.
+    #[getter]
+    fn pc(&self) -> usize {
+        self.chip8.pc()
+    }
+
+    /// The index register.
+    #[getter]
+    fn index(&self) -> usize {
+        self.chip8.index()
+    }
+
+    /// The delay and sound timers.
+    #[getter]
+    fn timers(&self) -> (u8, u8) {
+        self.chip8.timers()
+    }
+
+    /// The return addresses on the stack, innermost last.
+    #[getter]
+    fn stack(&self) -> Vec<usize> {
+        self.chip8.stack().to_vec()
+    }
+
+    /// Returns a snapshot of the emulator's state as `bytes`.
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.chip8.save_state())
+    }
+
+    /// Restores a snapshot taken with [`save_state`](Self::save_state). A
+    /// `ValueError` is raised (and nothing is restored) if it isn't valid.
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.chip8
+            .load_state(state)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        self.error = None;
+        Ok(())
+    }
+}
+
+impl Chip8 {
+    /// Restarts the ROM with the platform's quirks, optionally seeding the
+    /// random number generator.
+    fn restart(&mut self, seed: Option<u64>) {
+        if let Ok(chip8) = chip8::Chip8::new(&self.rom) {
+            self.chip8 = chip8;
+        }
+        self.chip8.set_quirks(self.platform.quirks());
+        if let Some(seed) = seed {
+            self.chip8.set_seed(seed);
+        }
+        self.keys = Keys::new();
+        self.frames = 0;
+        self.error = None;
+    }
+
+    /// Runs a frame, executing as many instructions as the speed says.
+    fn frame(&mut self) -> Result<(), Chip8Error> {
+        let mut cycles = self.speed as usize;
+        while cycles > 0 {
+            let (executed, _) = self.chip8.run(self.keys, cycles)?;
+            self.keys.reset_last_pressed();
+            cycles -= executed;
+        }
+        self.chip8.decrement_timers();
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+/// A snapshot of the screen, a pixel per byte (`1` if lit), row by row. It
+/// exposes a read-only `(32, 64)` buffer of `uint8`, for `numpy.asarray` and
+/// `memoryview`.
+#[pyclass(module = "biscuit8", frozen)]
+pub struct Screen {
+    pixels: Vec<u8>,
+    shape: [isize; 2],
+    strides: [isize; 2],
+}
+
+#[pymethods]
+impl Screen {
+    /// Returns whether the pixel at the coordinates is lit.
+    fn pixel(&self, x: usize, y: usize) -> PyResult<bool> {
+        match x < WIDTH && y < HEIGHT {
+            true => Ok(self.pixels[y * WIDTH + x] != 0),
+            false => Err(PyIndexError::new_err("Pixel is off the screen.")),
+        }
+    }
+
+    /// Returns the pixels as `bytes`.
+    fn tobytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new_bound(py, &self.pixels)
+    }
+
+    /// Returns how many rows there are.
+    fn __len__(&self) -> usize {
+        HEIGHT
+    }
+
+    /// Fills in a read-only view of the pixels.
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null."));
+        }
+        if flags & ffi::PyBUF_WRITABLE == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("Screen is read-only."));
+        }
+        let screen = slf.get();
+        let view = &mut *view;
+        view.buf = screen.pixels.as_ptr() as *mut c_void;
+        view.len = screen.pixels.len() as isize;
+        view.readonly = 1;
+        view.itemsize = 1;
+        view.format = match flags & ffi::PyBUF_FORMAT == ffi::PyBUF_FORMAT {
+            true => c"B".as_ptr() as *mut _,
+            false => ptr::null_mut(),
+        };
+        (view.ndim, view.shape) = match flags & ffi::PyBUF_ND == ffi::PyBUF_ND {
+            true => (2, screen.shape.as_ptr() as *mut _),
+            false => (1, ptr::null_mut()),
+        };
+        view.strides = match flags & ffi::PyBUF_STRIDES == ffi::PyBUF_STRIDES {
+            true => screen.strides.as_ptr() as *mut _,
+            false => ptr::null_mut(),
+        };
+        view.suboffsets = ptr::null_mut();
+        view.internal = ptr::null_mut();
+        view.obj = slf.into_any().into_ptr();
+        Ok(())
+    }
+
+    /// Nothing has to be released, since the pixels never change.
+    unsafe fn __releasebuffer__(&self, _view: *mut ffi::Py_buffer) {}
+}
+
+/// The `biscuit8` Python module.
+#[pymodule]
+#[pyo3(name = "biscuit8")]
+fn biscuit8_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Chip8>()?;
+    m.add_class::<Screen>()?;
+    Ok(())
+}
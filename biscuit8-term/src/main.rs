@@ -0,0 +1,305 @@
+//! A [`biscuit8`] frontend drawing in the terminal using [`crossterm`], which
+//! doesn't need a GPU, primarily provided by [`TermFrontend`]. Errors are also
+//! represented by [`TermFrontendError`]. Every character cell shows two pixels
+//! using the upper half block character in truecolor. Terminals don't report
+//! key releases (unless they support the kitty keyboard protocol), so keys are
+//! released once they haven't been pressed or repeated for a while.
+
+use biscuit8::{
+    args::{argh, Args, Mapping},
+    color::{Palette, Rgb},
+    filter::Filter,
+    frontend::{Frontend, Input, Status},
+    runner::{Runner, RunnerError},
+    screen,
+};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Error as IoError, Stdout, Write},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+/// The character used to draw two pixels in a cell: the top one as the
+/// foreground and the bottom one as the background.
+const HALF_BLOCK: char = '▀';
+
+/// The frontend for the terminal.
+pub struct TermFrontend {
+    held: BTreeMap<String, Instant>,
+    releases: bool,
+    key_release: Duration,
+    drawn: Vec<Option<(Rgb, Rgb)>>,
+    beeping: bool,
+    status: Status,
+    message: String,
+    quit: bool,
+    stdout: Stdout,
+}
+
+impl TermFrontend {
+    /// Constructs a new terminal frontend, setting up the terminal (raw mode and
+    /// the alternate screen) until it's dropped. If the terminal doesn't report
+    /// key releases, keys are released once they haven't been pressed or
+    /// repeated for the given time.
+    pub fn new(key_release: Duration) -> Result<Self, TermFrontendError> {
+        let mut stdout = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        let releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if releases {
+            execute!(
+                stdout,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Self {
+            held: BTreeMap::new(),
+            releases,
+            key_release,
+            drawn: Vec::new(),
+            beeping: false,
+            status: Status::default(),
+            message: String::new(),
+            quit: false,
+            stdout,
+        })
+    }
+
+    /// Handles terminal events.
+    fn event_handler(&mut self, event: Event, input: &mut Input) -> Result<(), TermFrontendError> {
+        match event {
+            Event::Key(key_event) => self.key_handler(key_event, input),
+            Event::Resize(..) => {
+                self.drawn.clear();
+                execute!(self.stdout, Clear(ClearType::All))?;
+                self.draw_status()?;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Handles keyboard input (Ctrl+C and Escape quit).
+    fn key_handler(&mut self, key_event: KeyEvent, input: &mut Input) {
+        match key_event.code {
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.quit = true;
+                return;
+            }
+            KeyCode::Esc => {
+                self.quit = true;
+                return;
+            }
+            _ => (),
+        }
+        let Some(name) = Self::key_name(key_event.code) else {
+            return;
+        };
+        if input.hotkey(&name, key_event.kind == KeyEventKind::Press) {
+            return;
+        }
+        if key_event.kind != KeyEventKind::Release {
+            if !self.held.contains_key(&name) {
+                input.press(&name);
+            }
+            self.held.insert(name, Instant::now());
+        } else {
+            self.held.remove(&name);
+            input.release(&name);
+        }
+    }
+
+    /// Returns the name of the key used by the keymap and hotkeys: the
+    /// character it types, or the name of a function key.
+    fn key_name(code: KeyCode) -> Option<String> {
+        match code {
+            KeyCode::Char(character) => Some(character.to_lowercase().collect()),
+            KeyCode::F(number) => Some(format!("F{}", number)),
+            KeyCode::Tab => Some("Tab".to_string()),
+            KeyCode::Enter => Some("Enter".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Releases keys that haven't been pressed or repeated for long enough, if
+    /// the terminal doesn't report key releases.
+    fn release_timed_out(&mut self, input: &mut Input) {
+        if self.releases {
+            return;
+        }
+        let now = Instant::now();
+        self.held.retain(|name, pressed| {
+            let held = now - *pressed < self.key_release;
+            if !held {
+                input.release(name);
+            }
+            held
+        });
+    }
+
+    /// Draws the status line below the screen.
+    fn draw_status(&mut self) -> Result<(), TermFrontendError> {
+        let rom = self
+            .status
+            .rom
+            .as_deref()
+            .map(|rom| rom.to_string_lossy())
+            .unwrap_or_default();
+        queue!(
+            self.stdout,
+            MoveTo(0, (screen::HEIGHT / 2) as u16),
+            Clear(ClearType::CurrentLine),
+            Print(format!(
+                "{}{}{}{} - biscuit8-term (Esc to quit)",
+                rom,
+                if self.status.paused { " [paused]" } else { "" },
+                if self.status.muted { " [muted]" } else { "" },
+                if self.status.recording { " [REC]" } else { "" },
+            )),
+            MoveTo(0, (screen::HEIGHT / 2 + 1) as u16),
+            Clear(ClearType::CurrentLine),
+            Print(&self.message),
+        )?;
+        self.stdout.flush()?;
+        Ok(())
+    }
+}
+
+impl Frontend for TermFrontend {
+    type Error = TermFrontendError;
+
+    /// Handles terminal events until the deadline.
+    fn poll_input(&mut self, input: &mut Input, deadline: Instant) -> Result<(), Self::Error> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            self.event_handler(event::read()?, input)?;
+        }
+        self.release_timed_out(input);
+        Ok(())
+    }
+
+    /// Draws the screen shown by the display filter, only redrawing the cells
+    /// that changed.
+    fn present(&mut self, filter: &Filter, palette: &Palette) -> Result<(), Self::Error> {
+        let color = |rgb: Rgb| Color::Rgb {
+            r: rgb[0],
+            g: rgb[1],
+            b: rgb[2],
+        };
+        self.drawn.resize(screen::WIDTH * screen::HEIGHT / 2, None);
+        let mut colors = None;
+        for row in 0..screen::HEIGHT / 2 {
+            let mut moved = false;
+            for x in 0..screen::WIDTH {
+                let cell = (
+                    palette.blend(filter.intensity(x, row * 2)),
+                    palette.blend(filter.intensity(x, row * 2 + 1)),
+                );
+                let drawn = &mut self.drawn[row * screen::WIDTH + x];
+                if *drawn == Some(cell) {
+                    moved = false;
+                    continue;
+                }
+                *drawn = Some(cell);
+                if !moved {
+                    queue!(self.stdout, MoveTo(x as u16, row as u16))?;
+                    moved = true;
+                }
+                if colors != Some(cell) {
+                    queue!(
+                        self.stdout,
+                        SetForegroundColor(color(cell.0)),
+                        SetBackgroundColor(color(cell.1))
+                    )?;
+                    colors = Some(cell);
+                }
+                queue!(self.stdout, Print(HALF_BLOCK))?;
+            }
+        }
+        queue!(self.stdout, ResetColor)?;
+        self.stdout.flush()?;
+        Ok(())
+    }
+
+    /// Rings the terminal's bell when the beep starts.
+    fn set_sound(&mut self, beeping: bool, _samples: &[f32]) -> Result<(), Self::Error> {
+        if beeping && !self.beeping {
+            execute!(self.stdout, Print('\x07'))?;
+        }
+        self.beeping = beeping;
+        Ok(())
+    }
+
+    fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    fn status(&mut self, status: &Status) -> Result<(), Self::Error> {
+        self.status = status.clone();
+        self.draw_status()
+    }
+
+    /// Shows the message on the line below the status line, since printing
+    /// would mess up the screen.
+    fn message(&mut self, message: &str) {
+        self.message = message.to_string();
+        let _ = self.draw_status();
+    }
+
+    fn report(&mut self, error: &RunnerError) {
+        self.message(&error.to_string());
+    }
+}
+
+impl Drop for TermFrontend {
+    /// Restores the terminal to how it was.
+    fn drop(&mut self) {
+        if self.releases {
+            let _ = execute!(self.stdout, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.stdout, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Used to describe possible errors caused by the terminal or the runner (the
+/// arguments, the emulator, or writing files).
+#[derive(Debug, Error)]
+pub enum TermFrontendError {
+    #[error("{0}")]
+    Runner(#[from] RunnerError),
+    #[error("{0}.")]
+    Io(#[from] IoError),
+}
+
+/// Same old "exciting" entry point.
+fn main() -> ExitCode {
+    if let Err(err) = main_loop() {
+        eprintln!("{}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Gets the ROM from the given path and starts the main instruction loop with
+/// some options/settings.
+fn main_loop() -> Result<(), TermFrontendError> {
+    let mut args = argh::from_env::<Args>();
+    // Terminals only report the characters typed, so physical mapping falls
+    // back to the keyboard layout.
+    args.mapping = Some(Mapping::Logical);
+    Runner::new(&args, |settings| TermFrontend::new(settings.key_release()))?.run()
+}
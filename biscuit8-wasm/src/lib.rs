@@ -0,0 +1,165 @@
+//! WebAssembly bindings for [`biscuit8`] using [`wasm_bindgen`], primarily
+//! provided by [`Emulator`], so that ROMs can be played in the browser (like
+//! the harness in `www`). Only the `no_std` core is used: JavaScript draws the
+//! framebuffer, feeds input, plays the beep, and calls [`Emulator::run_frame`]
+//! 60 times a second.
+
+use biscuit8::{
+    chip8::Chip8,
+    keys::Keys,
+    screen::{Screen, HEIGHT, WIDTH},
+};
+use wasm_bindgen::prelude::*;
+
+/// How many instructions are executed every frame by default.
+const DEFAULT_SPEED: u32 = 10;
+
+/// The default background and foreground colors, as `0xRRGGBB`.
+const DEFAULT_COLORS: (u32, u32) = (0x000000, 0xFFFFFF);
+
+/// An emulator running a ROM, driven from JavaScript.
+#[wasm_bindgen]
+pub struct Emulator {
+    chip8: Chip8,
+    rom: Vec<u8>,
+    keys: Keys,
+    screen: Screen,
+    speed: u32,
+    colors: (u32, u32),
+    beeping: bool,
+}
+
+#[wasm_bindgen]
+impl Emulator {
+    /// Constructs an emulator running the given ROM.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rom: &[u8]) -> Result<Emulator, JsError> {
+        Ok(Self {
+            chip8: Self::chip8(rom)?,
+            rom: rom.to_vec(),
+            keys: Keys::new(),
+            screen: Screen::new(),
+            speed: DEFAULT_SPEED,
+            colors: DEFAULT_COLORS,
+            beeping: false,
+        })
+    }
+
+    /// Replaces the running ROM with the given one, starting it from scratch.
+    #[wasm_bindgen(js_name = loadRom)]
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), JsError> {
+        self.chip8 = Self::chip8(rom)?;
+        self.rom = rom.to_vec();
+        self.keys = Keys::new();
+        self.screen = Screen::new();
+        self.beeping = false;
+        Ok(())
+    }
+
+    /// Restarts the running ROM.
+    pub fn reset(&mut self) -> Result<(), JsError> {
+        let rom = std::mem::take(&mut self.rom);
+        self.load_rom(&rom)
+    }
+
+    /// Seeds the random number generator (with something like
+    /// `Math.random() * 2 ** 32`), since there isn't a source of randomness
+    /// without one. The seed is a plain number rather than a `BigInt`; its
+    /// fractional part is dropped and negative seeds count as 0.
+    #[wasm_bindgen(js_name = setSeed)]
+    pub fn set_seed(&mut self, seed: f64) {
+        self.chip8.set_seed(seed as u64);
+    }
+
+    /// Sets how many instructions are executed every frame.
+    #[wasm_bindgen(js_name = setSpeed)]
+    pub fn set_speed(&mut self, speed: u32) {
+        self.speed = speed;
+    }
+
+    /// Sets the background and foreground colors of the framebuffer, as
+    /// `0xRRGGBB`.
+    #[wasm_bindgen(js_name = setColors)]
+    pub fn set_colors(&mut self, bg: u32, fg: u32) {
+        self.colors = (bg, fg);
+    }
+
+    /// Runs a frame, executing as many instructions as the speed says, which
+    /// should be done 60 times a second.
+    #[wasm_bindgen(js_name = runFrame)]
+    pub fn run_frame(&mut self) -> Result<(), JsError> {
+        let mut cycles = self.speed as usize;
+        let mut drew = false;
+        while cycles > 0 {
+            let (executed, updated) = self
+                .chip8
+                .run(self.keys, cycles)
+                .map_err(|err| JsError::new(&err.to_string()))?;
+            drew |= updated;
+            self.keys.reset_last_pressed();
+            cycles -= executed;
+        }
+        if drew {
+            self.screen = self.chip8.screen().clone();
+        }
+        self.beeping = self.chip8.beeping();
+        self.chip8.decrement_timers();
+        Ok(())
+    }
+
+    /// Presses the CHIP-8 key (from `0x0` to `0xF`; others are ignored).
+    #[wasm_bindgen(js_name = pressKey)]
+    pub fn press_key(&mut self, key: u8) {
+        if key <= 0xF {
+            self.keys.press_key(key);
+        }
+    }
+
+    /// Releases the CHIP-8 key (from `0x0` to `0xF`; others are ignored).
+    #[wasm_bindgen(js_name = releaseKey)]
+    pub fn release_key(&mut self, key: u8) {
+        if key <= 0xF {
+            self.keys.release_key(key);
+        }
+    }
+
+    /// Returns the screen as RGBA pixels (a `Uint8Array` in JavaScript), row by
+    /// row, which can be put straight into an `ImageData`.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        let (bg, fg) = self.colors;
+        let mut rgba = Vec::with_capacity(WIDTH * HEIGHT * 4);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let [_, r, g, b] = match self.screen.pixel(x, y) {
+                    true => fg,
+                    false => bg,
+                }
+                .to_be_bytes();
+                rgba.extend([r, g, b, 0xFF]);
+            }
+        }
+        rgba
+    }
+
+    /// Returns whether the beep should be playing after the last frame.
+    pub fn sound(&self) -> bool {
+        self.beeping
+    }
+
+    /// Returns the width of the framebuffer in pixels.
+    pub fn width() -> usize {
+        WIDTH
+    }
+
+    /// Returns the height of the framebuffer in pixels.
+    pub fn height() -> usize {
+        HEIGHT
+    }
+}
+
+impl Emulator {
+    /// Attempts to construct an emulator core running the given ROM.
+    fn chip8(rom: &[u8]) -> Result<Chip8, JsError> {
+        Chip8::new(rom).map_err(|err| JsError::new(&err.to_string()))
+    }
+}
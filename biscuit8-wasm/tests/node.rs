@@ -0,0 +1,68 @@
+//! Tests the bindings under Node with [`wasm_bindgen_test`] (through
+//! `wasm-pack test --node`); they only run on `wasm32`.
+
+#![cfg(target_arch = "wasm32")]
+
+use biscuit8_wasm::Emulator;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// The IBM logo ROM, which draws a logo and loops forever.
+const IBM: &[u8] = include_bytes!("../../roms/ibm.ch8");
+
+/// The beep ROM, which beeps as soon as it starts.
+const BEEP: &[u8] = include_bytes!("../../roms/beep.ch8");
+
+#[wasm_bindgen_test]
+fn framebuffer_is_rgba() {
+    let emulator = Emulator::new(IBM).unwrap();
+    let framebuffer = emulator.framebuffer();
+    assert_eq!(
+        framebuffer.len(),
+        Emulator::width() * Emulator::height() * 4
+    );
+    assert!(framebuffer.chunks(4).all(|pixel| pixel == [0, 0, 0, 0xFF]));
+}
+
+#[wasm_bindgen_test]
+fn frames_draw() {
+    let mut emulator = Emulator::new(IBM).unwrap();
+    emulator.set_colors(0x000000, 0x102030);
+    for _ in 0..30 {
+        emulator.run_frame().unwrap();
+    }
+    let framebuffer = emulator.framebuffer();
+    assert!(framebuffer
+        .chunks(4)
+        .any(|pixel| pixel == [0x10, 0x20, 0x30, 0xFF]));
+}
+
+#[wasm_bindgen_test]
+fn sound_follows_the_timer() {
+    let mut emulator = Emulator::new(BEEP).unwrap();
+    assert!(!emulator.sound());
+    let beeped = (0..60).any(|_| {
+        emulator.run_frame().unwrap();
+        emulator.sound()
+    });
+    assert!(beeped);
+}
+
+#[wasm_bindgen_test]
+fn keys_outside_the_keypad_are_ignored() {
+    let mut emulator = Emulator::new(IBM).unwrap();
+    emulator.press_key(0x10);
+    emulator.release_key(0xFF);
+    emulator.press_key(0x5);
+    emulator.run_frame().unwrap();
+}
+
+#[wasm_bindgen_test]
+fn reset_restarts_the_rom() {
+    let mut emulator = Emulator::new(IBM).unwrap();
+    let blank = emulator.framebuffer();
+    for _ in 0..30 {
+        emulator.run_frame().unwrap();
+    }
+    emulator.reset().unwrap();
+    assert_eq!(emulator.framebuffer(), blank);
+}
@@ -0,0 +1,100 @@
+// A tiny harness for the biscuit8 WebAssembly bindings; build them with
+// `wasm-pack build --target web` (from biscuit8-wasm) and serve this directory's
+// parent, e.g. `python3 -m http.server`, then open `/www/`.
+
+import init, { Emulator } from "../pkg/biscuit8_wasm.js";
+
+// How long a frame lasts in milliseconds (the emulator runs at 60 frames a
+// second).
+const FRAME = 1000 / 60;
+
+// The CHIP-8 key bound to every key, laid out like the COSMAC VIP's keypad.
+const KEYMAP = {
+    "1": 0x1, "2": 0x2, "3": 0x3, "4": 0xC,
+    "q": 0x4, "w": 0x5, "e": 0x6, "r": 0xD,
+    "a": 0x7, "s": 0x8, "d": 0x9, "f": 0xE,
+    "z": 0xA, "x": 0x0, "c": 0xB, "v": 0xF,
+};
+
+await init();
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const image = context.createImageData(Emulator.width(), Emulator.height());
+const mute = document.getElementById("mute");
+
+let emulator = null;
+let beep = null;
+let last = performance.now();
+let lag = 0;
+
+// Starts or stops the beep, creating the audio context on first use (browsers
+// only allow it after user input).
+function setBeep(playing) {
+    if (playing && !beep) {
+        const audio = new AudioContext();
+        const oscillator = audio.createOscillator();
+        oscillator.type = "square";
+        oscillator.frequency.value = 440;
+        const gain = audio.createGain();
+        gain.gain.value = 0.1;
+        oscillator.connect(gain).connect(audio.destination);
+        oscillator.start();
+        beep = audio;
+    } else if (!playing && beep) {
+        beep.close();
+        beep = null;
+    }
+}
+
+// Runs as many frames as have passed since the last animation frame, and draws
+// the last one.
+function animationFrame(now) {
+    requestAnimationFrame(animationFrame);
+    if (!emulator) {
+        return;
+    }
+    lag = Math.min(lag + now - last, FRAME * 4);
+    last = now;
+    try {
+        while (lag >= FRAME) {
+            emulator.runFrame();
+            lag -= FRAME;
+        }
+    } catch (error) {
+        console.error(error);
+        emulator = null;
+        setBeep(false);
+        return;
+    }
+    image.data.set(emulator.framebuffer());
+    context.putImageData(image, 0, 0);
+    setBeep(emulator.sound() && !mute.checked);
+}
+
+document.getElementById("rom").addEventListener("change", async (event) => {
+    const file = event.target.files[0];
+    if (!file) {
+        return;
+    }
+    const rom = new Uint8Array(await file.arrayBuffer());
+    try {
+        emulator = new Emulator(rom);
+        emulator.setSeed(Math.random() * 2 ** 32);
+        last = performance.now();
+        lag = 0;
+    } catch (error) {
+        alert(error);
+    }
+});
+
+for (const [type, pressed] of [["keydown", true], ["keyup", false]]) {
+    document.addEventListener(type, (event) => {
+        const key = KEYMAP[event.key.toLowerCase()];
+        if (emulator && key !== undefined) {
+            pressed ? emulator.pressKey(key) : emulator.releaseKey(key);
+        }
+    });
+}
+
+requestAnimationFrame(animationFrame);
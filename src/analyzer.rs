@@ -0,0 +1,428 @@
+//! Provides a static analyzer for ROMs, [`Analysis`], which walks the control
+//! flow reachable from the start of a ROM (without running it) and reports
+//! [`Issue`]s: unknown opcodes, jumps outside the ROM or to odd addresses,
+//! self-modifying writes into code, subroutine calls nested deeper than the
+//! stack, and SUPER-CHIP or XO-CHIP opcodes. It also recommends a platform,
+//! and points out the instructions whose behavior depends on the quirks.
+
+use crate::{
+    chip8::{Platform, RAM_SIZE, ROM_LOC, STACK_SIZE},
+    instruction::Instruction,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{Display, Error as FmtError, Formatter},
+};
+
+/// Something suspicious about a ROM, found at the address of an instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Issue {
+    /// An opcode the emulator rejects as unknown.
+    UnknownOpcode(usize, Instruction),
+    /// A jump or call to an address outside the ROM.
+    OutsideRom(usize, usize),
+    /// A computed jump (`BNNN`) that can go past the end of RAM.
+    OutsideRam(usize),
+    /// Execution running past the end of the ROM.
+    PastRom(usize),
+    /// A jump or call to an odd address.
+    OddJump(usize, usize),
+    /// A write (`FX33` or `FX55`) into the code at the address.
+    SelfModifying(usize, usize),
+    /// A call nesting subroutines deeper than the stack allows.
+    StackOverflow(usize),
+    /// An opcode specific to a platform.
+    PlatformOpcode(usize, Instruction, Platform),
+    /// A computed jump (`BNNN`) whose targets can't be followed.
+    ComputedJump(usize),
+}
+
+impl Issue {
+    /// Returns the address of the instruction the issue was found at.
+    pub const fn address(&self) -> usize {
+        match self {
+            Self::UnknownOpcode(address, _)
+            | Self::OutsideRom(address, _)
+            | Self::OutsideRam(address)
+            | Self::PastRom(address)
+            | Self::OddJump(address, _)
+            | Self::SelfModifying(address, _)
+            | Self::StackOverflow(address)
+            | Self::PlatformOpcode(address, ..)
+            | Self::ComputedJump(address) => *address,
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        write!(f, "{:#05X}: ", self.address())?;
+        match self {
+            Self::UnknownOpcode(_, instruction) => {
+                write!(f, "{} is an unknown opcode", instruction)
+            }
+            Self::OutsideRom(_, target) => write!(f, "jumps to {:#05X}, outside the ROM", target),
+            Self::OutsideRam(_) => write!(f, "computed jump can go past the end of RAM"),
+            Self::PastRom(_) => write!(f, "execution runs past the end of the ROM"),
+            Self::OddJump(_, target) => write!(f, "jumps to odd address {:#05X}", target),
+            Self::SelfModifying(_, target) => write!(f, "writes into code at {:#05X}", target),
+            Self::StackOverflow(_) => write!(
+                f,
+                "calls nest deeper than the stack ({} return addresses)",
+                STACK_SIZE
+            ),
+            Self::PlatformOpcode(_, instruction, platform) => {
+                write!(f, "{} is a {} opcode", instruction, platform)?;
+                if instruction.class().is_none() {
+                    write!(f, " (rejected as unknown)")?;
+                }
+                Ok(())
+            }
+            Self::ComputedJump(_) => write!(f, "computed jump can't be followed"),
+        }
+    }
+}
+
+/// Where the analysis is in the control flow: the address of an instruction,
+/// how many subroutines deep it is, and the index register if it's known.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Path {
+    pc: usize,
+    depth: usize,
+    i: Option<usize>,
+}
+
+/// The results of statically analyzing a ROM (see the [module](self) docs).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Analysis {
+    rom_len: usize,
+    code: BTreeSet<usize>,
+    issues: BTreeSet<Issue>,
+    quirks: BTreeMap<&'static str, usize>,
+    platform: Platform,
+}
+
+impl Analysis {
+    /// Analyzes the ROM, following every path through it from the start.
+    pub fn new(rom: &[u8]) -> Self {
+        let mut analysis = Self {
+            rom_len: rom.len(),
+            code: BTreeSet::new(),
+            issues: BTreeSet::new(),
+            quirks: BTreeMap::new(),
+            platform: Platform::Chip8,
+        };
+        let fetch = |pc: usize| {
+            let offset = pc.checked_sub(ROM_LOC)?;
+            let bytes = rom.get(offset..offset + 2)?;
+            Some(Instruction::new(u16::from_be_bytes([bytes[0], bytes[1]])))
+        };
+        let mut writes = Vec::new();
+        let mut visited: BTreeMap<(usize, Option<usize>), usize> = BTreeMap::new();
+        let mut paths = vec![Path {
+            pc: ROM_LOC,
+            depth: 0,
+            i: None,
+        }];
+        while let Some(path) = paths.pop() {
+            // Paths are only followed again if they're deeper (to find stack
+            // overflows).
+            match visited.get(&(path.pc, path.i)) {
+                Some(depth) if *depth >= path.depth => continue,
+                _ => visited.insert((path.pc, path.i), path.depth),
+            };
+            let Some(instruction) = fetch(path.pc) else {
+                analysis.issues.insert(Issue::PastRom(path.pc));
+                continue;
+            };
+            // An XO-CHIP long index (F000 NNNN) is followed by its address.
+            let size = |pc| match fetch(pc).map(|instruction| instruction.nibbles()) {
+                Some((0xF, 0x0, 0x0, 0x0)) => 4,
+                _ => 2,
+            };
+            let next = path.pc + size(path.pc);
+            analysis.code.extend(path.pc..next);
+            let skip = next + size(next);
+            let to = |pc| Path { pc, ..path };
+            let (pc, x, y) = (path.pc, instruction.x(), instruction.y());
+            if let Some(platform) = Self::platform_of(instruction) {
+                analysis
+                    .issues
+                    .insert(Issue::PlatformOpcode(pc, instruction, platform));
+                analysis.platform = analysis.platform.max(platform);
+            }
+            match instruction.nibbles() {
+                (0x0, 0x0, 0xE, 0xE) | (0x0, 0x0, 0xF, 0xD) => (),
+                (0x1, ..) => {
+                    if analysis.jump(pc, instruction.nnn()) {
+                        paths.push(to(instruction.nnn()));
+                    }
+                }
+                (0x2, ..) => {
+                    let follow = analysis.jump(pc, instruction.nnn());
+                    if follow && path.depth >= STACK_SIZE {
+                        analysis.issues.insert(Issue::StackOverflow(pc));
+                    } else if follow {
+                        paths.push(Path {
+                            pc: instruction.nnn(),
+                            depth: path.depth + 1,
+                            i: path.i,
+                        });
+                    }
+                    // Subroutines are assumed to return, and to keep the index
+                    // register as it was.
+                    paths.push(to(next));
+                }
+                (0x3 | 0x4, ..)
+                | (0x5 | 0x9, _, _, 0x0)
+                | (0xE, _, 0x9, 0xE)
+                | (0xE, _, 0xA, 0x1) => {
+                    paths.extend([to(next), to(skip)]);
+                }
+                (0x8, _, _, 0x1..=0x3) => {
+                    *analysis.quirks.entry("vf-reset").or_default() += 1;
+                    paths.push(to(next));
+                }
+                (0x8, _, _, 0x6 | 0xE) => {
+                    if x != y {
+                        *analysis.quirks.entry("shifting").or_default() += 1;
+                    }
+                    paths.push(to(next));
+                }
+                (0xA, ..) => paths.push(Path {
+                    i: Some(instruction.nnn()),
+                    ..to(next)
+                }),
+                (0xB, ..) => {
+                    *analysis.quirks.entry("jumping").or_default() += 1;
+                    analysis.issues.insert(Issue::ComputedJump(pc));
+                    if instruction.nnn() + 0xFF >= RAM_SIZE - 1 {
+                        analysis.issues.insert(Issue::OutsideRam(pc));
+                    }
+                }
+                (0xF, _, 0x3, 0x3) => {
+                    if let Some(i) = path.i {
+                        writes.push((pc, i..i + 3));
+                    }
+                    paths.push(to(next));
+                }
+                (0xF, _, 0x5 | 0x6, 0x5) => {
+                    *analysis.quirks.entry("memory").or_default() += 1;
+                    if let (Some(i), 0x5) = (path.i, instruction.nibbles().2) {
+                        writes.push((pc, i..i + x + 1));
+                    }
+                    // The memory quirk decides where the index register ends up.
+                    paths.push(Path {
+                        i: None,
+                        ..to(next)
+                    });
+                }
+                (0xF, _, 0x1, 0xE) | (0xF, _, 0x2, 0x9) | (0xF, 0x0, 0x0, 0x0) => {
+                    paths.push(Path {
+                        i: None,
+                        ..to(next)
+                    });
+                }
+                _ if instruction.class().is_none() && Self::platform_of(instruction).is_none() => {
+                    analysis
+                        .issues
+                        .insert(Issue::UnknownOpcode(pc, instruction));
+                }
+                _ => paths.push(to(next)),
+            }
+        }
+        for (pc, range) in writes {
+            if let Some(target) = range
+                .into_iter()
+                .find(|address| analysis.code.contains(address))
+            {
+                analysis.issues.insert(Issue::SelfModifying(pc, target));
+            }
+        }
+        analysis
+    }
+
+    /// Checks the target of a jump or call, returning whether it's in the ROM
+    /// (and can be followed).
+    fn jump(&mut self, pc: usize, target: usize) -> bool {
+        if !target.is_multiple_of(2) {
+            self.issues.insert(Issue::OddJump(pc, target));
+        }
+        if target < ROM_LOC || target >= ROM_LOC + self.rom_len {
+            self.issues.insert(Issue::OutsideRom(pc, target));
+            return false;
+        }
+        true
+    }
+
+    /// Returns the platform the instruction is specific to, if it's a
+    /// SUPER-CHIP or XO-CHIP extension.
+    fn platform_of(instruction: Instruction) -> Option<Platform> {
+        match instruction.nibbles() {
+            (0x0, 0x0, 0xC, _)
+            | (0x0, 0x0, 0xF, 0xB..=0xF)
+            | (0xD, _, _, 0x0)
+            | (0xF, _, 0x3, 0x0)
+            | (0xF, _, 0x7 | 0x8, 0x5) => Some(Platform::Schip),
+            (0x0, 0x0, 0xD, _)
+            | (0x5, _, _, 0x2 | 0x3)
+            | (0xF, 0x0, 0x0, 0x0)
+            | (0xF, _, 0x0, 0x1)
+            | (0xF, 0x0, 0x0, 0x2)
+            | (0xF, _, 0x3, 0xA) => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+
+    /// Returns the issues found, ordered by address.
+    pub fn issues(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter()
+    }
+
+    /// Returns whether anything other than platform-specific opcodes and
+    /// computed jumps (which are only informational) was found.
+    pub fn has_problems(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| !matches!(issue, Issue::PlatformOpcode(..) | Issue::ComputedJump(_)))
+    }
+
+    /// Returns the addresses of the reachable code.
+    pub const fn code(&self) -> &BTreeSet<usize> {
+        &self.code
+    }
+
+    /// Returns the platform the ROM is most likely written for (the newest one
+    /// whose opcodes it uses).
+    pub const fn platform(&self) -> Platform {
+        self.platform
+    }
+
+    /// Returns the quirks (named like in the config file) that affect
+    /// instructions the ROM uses, along with how many of them there are.
+    pub const fn quirks(&self) -> &BTreeMap<&'static str, usize> {
+        &self.quirks
+    }
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        writeln!(
+            f,
+            "{} of {} ROM bytes are reachable code.",
+            self.code.len(),
+            self.rom_len
+        )?;
+        if self.issues.is_empty() {
+            writeln!(f, "No issues found.")?;
+        }
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+
+        let quirks = self.platform.quirks();
+        writeln!(f, "\nRecommended settings:")?;
+        writeln!(f, "platform = \"{}\"", self.platform)?;
+        writeln!(f, "\n[quirks]")?;
+        for (quirk, enabled) in [
+            ("vf-reset", quirks.vf_reset),
+            ("memory", quirks.memory),
+            ("shifting", quirks.shifting),
+            ("jumping", quirks.jumping),
+            ("wrapping", quirks.wrapping),
+        ] {
+            write!(f, "{} = {}", quirk, enabled)?;
+            match self.quirks.get(quirk) {
+                Some(1) => writeln!(f, "  # affects 1 instruction")?,
+                Some(count) => writeln!(f, "  # affects {} instructions", count)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the issues found in the ROM.
+    fn issues(rom: &[u8]) -> Vec<Issue> {
+        Analysis::new(rom).issues().copied().collect()
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let unknown = Issue::UnknownOpcode(0x200, Instruction::new(0x8008));
+        assert_eq!(issues(&[0x80, 0x08]), [unknown]);
+    }
+
+    #[test]
+    fn outside_rom() {
+        assert_eq!(issues(&[0x13, 0x00]), [Issue::OutsideRom(0x200, 0x300)]);
+    }
+
+    #[test]
+    fn outside_ram() {
+        let issues = issues(&[0xBF, 0x80]);
+        assert_eq!(
+            issues,
+            [Issue::OutsideRam(0x200), Issue::ComputedJump(0x200)]
+        );
+        assert!(Analysis::new(&[0xBF, 0x80]).has_problems());
+    }
+
+    #[test]
+    fn past_rom() {
+        assert_eq!(issues(&[0x60, 0x00]), [Issue::PastRom(0x202)]);
+    }
+
+    #[test]
+    fn odd_jump() {
+        assert!(issues(&[0x12, 0x03, 0x12, 0x00]).contains(&Issue::OddJump(0x200, 0x203)));
+    }
+
+    #[test]
+    fn self_modifying() {
+        let rom = [0xA2, 0x00, 0xF0, 0x33, 0x12, 0x02];
+        assert_eq!(issues(&rom), [Issue::SelfModifying(0x202, 0x200)]);
+        let data = [0xA2, 0x06, 0xF0, 0x33, 0x12, 0x02, 0x00, 0x00, 0x00];
+        assert_eq!(issues(&data), []);
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let rom = [0x22, 0x00, 0x12, 0x02];
+        assert_eq!(issues(&rom), [Issue::StackOverflow(0x200)]);
+    }
+
+    #[test]
+    fn platform_opcode() {
+        let hires = Issue::PlatformOpcode(0x200, Instruction::new(0x00FF), Platform::Schip);
+        assert_eq!(issues(&[0x00, 0xFF, 0x12, 0x02]), [hires]);
+        assert!(!Analysis::new(&[0x00, 0xFF, 0x12, 0x02]).has_problems());
+    }
+
+    #[test]
+    fn computed_jump() {
+        let analysis = Analysis::new(&[0xB2, 0x00]);
+        assert_eq!(
+            analysis.issues().collect::<Vec<_>>(),
+            [&Issue::ComputedJump(0x200)]
+        );
+        assert!(!analysis.has_problems());
+        assert_eq!(analysis.quirks().get("jumping"), Some(&1));
+    }
+
+    #[test]
+    fn recommends_the_newest_platform_used() {
+        let plain = [0x00, 0xE0, 0x12, 0x02];
+        assert_eq!(Analysis::new(&plain).platform(), Platform::Chip8);
+        let schip = [0x00, 0xFF, 0x12, 0x02];
+        assert_eq!(Analysis::new(&schip).platform(), Platform::Schip);
+        let xo_chip = [0x00, 0xFF, 0xF0, 0x00, 0x02, 0x00, 0x12, 0x06];
+        assert_eq!(Analysis::new(&xo_chip).platform(), Platform::XoChip);
+        let report = Analysis::new(&xo_chip).to_string();
+        assert!(report.contains("platform = \"XO-CHIP\""));
+    }
+}
@@ -1,54 +1,254 @@
 //! CLI argument parsing is done here; you can use [`argh`] to get a struct
-//! containing things like the path to the ROM and other options/settings.
+//! containing things like the path to the ROM and other options/settings. The
+//! arguments are the topmost layer of [`Settings`], overriding the [`Config`]
+//! file. Parsing needs the `args` feature; the settings it parses (and loading
+//! ROMs with them) don't.
 
-use crate::chip8::{Chip8, Chip8Error};
+use crate::{
+    audio::Waveform,
+    chip8::{Chip8, Chip8Error, Platform},
+    color::ColorError,
+    config::{Config, ConfigError, Settings},
+    filter::FilterMode,
+    keymap::{self, Keymap, KeymapError},
+    octo::{self, OctoError},
+    record::RecordFormat,
+};
+#[cfg(feature = "args")]
 pub use argh::{self, FromArgs};
+use serde::{de::Error as DeError, Deserialize, Deserializer};
 use std::{
     fmt::{Display, Error as FmtError, Formatter},
     fs,
     io::Error as IoError,
-    num::ParseIntError,
-    path::PathBuf,
+    path::Path,
     str::FromStr,
 };
 use thiserror::Error;
+#[cfg(feature = "args")]
+use {
+    crate::{color::PalettePreset, config::AudioSettings},
+    std::path::PathBuf,
+};
 
 /// A CHIP-8 emulator with support for multiple frontends and options.
+#[cfg(feature = "args")]
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, FromArgs)]
 pub struct Args {
-    /// the keyboard layout to use (QWERTY and Colemak supported)
-    #[argh(option, short = 'l', default = "Layout::default()")]
-    pub layout: Layout,
-    /// the background color in #RRGGBB hex
-    #[argh(option, default = "\"#000000\".to_string()")]
-    pub bg: String,
-    /// the foreground color in #RRGGBB hex
-    #[argh(option, default = "\"#FFFFFF\".to_string()")]
-    pub fg: String,
-    /// path of the ROM to execute
+    /// path of the TOML config file (biscuit8/config.toml in the user's config
+    /// directory by default)
+    #[argh(option, short = 'c')]
+    pub config: Option<PathBuf>,
+    /// the platform whose quirks are emulated (CHIP-8, SUPER-CHIP, and XO-CHIP
+    /// supported)
+    #[argh(option, short = 'p')]
+    pub platform: Option<Platform>,
+    /// how many instructions are executed every frame (at 60 frames a second)
+    #[argh(option, short = 's')]
+    pub speed: Option<u32>,
+    /// whether keys are mapped by the character they type ("logical") or their
+    /// position on the keyboard ("physical")
+    #[argh(option, short = 'm')]
+    pub mapping: Option<Mapping>,
+    /// the keyboard layout to use with logical mapping (QWERTY, Colemak,
+    /// Dvorak, AZERTY, and QWERTZ supported)
+    #[argh(option, short = 'l')]
+    pub layout: Option<Layout>,
+    /// path of a TOML keymap binding keys to CHIP-8 keys, used instead of the
+    /// preset for the mapping and any configured keymap
+    #[argh(option, short = 'k')]
+    pub keymap: Option<PathBuf>,
+    /// path of a TOML keymap binding gamepad buttons to CHIP-8 keys, used
+    /// instead of the default one and any configured one
+    #[argh(option)]
+    pub gamepad_keymap: Option<PathBuf>,
+    /// the palette preset to use (mono, octo, gameboy, amber, lcd, or cga)
+    #[argh(option)]
+    pub palette: Option<PalettePreset>,
+    /// the background color as #RRGGBB, #RGB, rgb(R, G, B), or a CSS color
+    /// name (overrides the palette's)
+    #[argh(option)]
+    pub bg: Option<String>,
+    /// the foreground color as #RRGGBB, #RGB, rgb(R, G, B), or a CSS color
+    /// name (overrides the palette's)
+    #[argh(option)]
+    pub fg: Option<String>,
+    /// the shape of the beep's wave (square, sine, triangle, or noise)
+    #[argh(option, short = 'w')]
+    pub waveform: Option<Waveform>,
+    /// mute the beep (it can also be toggled with a hotkey)
+    #[argh(switch)]
+    pub mute: bool,
+    /// path of a WAV file the beep is recorded to
+    #[argh(option)]
+    pub wav: Option<PathBuf>,
+    /// path gameplay is recorded to from the start (a file for GIFs, or a
+    /// directory for PNG sequences)
+    #[argh(option, short = 'r')]
+    pub record: Option<PathBuf>,
+    /// the format gameplay is recorded in (gif or png)
+    #[argh(option)]
+    pub record_format: Option<RecordFormat>,
+    /// path an input movie of the ROM is recorded to
+    #[argh(option)]
+    pub record_movie: Option<PathBuf>,
+    /// path of an input movie to replay (using its speed and quirks)
+    #[argh(option)]
+    pub play_movie: Option<PathBuf>,
+    /// path a profiling report (hot addresses, opcode classes, and
+    /// subroutines) is written to, along with a .folded file for flamegraphs
+    #[argh(option)]
+    pub profile: Option<PathBuf>,
+    /// path a coverage report (which ROM bytes were executed, read, and
+    /// written) is written to, as an lcov tracefile if it ends in .info or
+    /// .lcov or an annotated disassembly otherwise
+    #[argh(option)]
+    pub coverage: Option<PathBuf>,
+    /// path of an assembler symbol map labelling the coverage report
+    #[argh(option)]
+    pub symbols: Option<PathBuf>,
+    /// path of a TOML cheat list for the ROM, used instead of the one next to
+    /// it
+    #[argh(option)]
+    pub cheats: Option<PathBuf>,
+    /// statically analyze the ROM instead of running it, reporting issues and
+    /// recommending a platform (headless frontends only)
+    #[argh(switch)]
+    pub analyze: bool,
+    /// how many frames a headless frontend runs the ROM for before exiting
+    #[argh(option)]
+    pub frames: Option<u32>,
+    /// the display filter used to reduce flicker (none, decay, or, or vblank)
+    #[argh(option, short = 'f')]
+    pub filter: Option<FilterMode>,
+    /// the strength of the display filter from 0 to 100: how much of a pixel
+    /// is kept every frame when decaying, or how many frames are blended when
+    /// ORing
+    #[argh(option)]
+    pub filter_strength: Option<u8>,
+    /// how many times larger than the CHIP-8 screen the window is
+    #[argh(option)]
+    pub scale: Option<u32>,
+    /// path of the ROM (or .8o Octo source code) to execute (a frontend may
+    /// wait for one if omitted)
     #[argh(positional)]
-    pub path: PathBuf,
+    pub path: Option<PathBuf>,
 }
 
+#[cfg(feature = "args")]
 impl Args {
-    /// Attempts to return a constructed emulator using the provided arguments.
-    pub fn chip8(&self) -> Result<Chip8, ArgsError> {
-        let rom = fs::read(&self.path)?;
-        Ok(Chip8::new(&rom)?)
+    /// Attempts to load the config file given, or the default one if there is
+    /// one.
+    pub fn config(&self) -> Result<Config, ArgsError> {
+        match &self.config {
+            Some(path) => Ok(Config::load(path)?),
+            None => Ok(Config::load_default()?),
+        }
+    }
+
+    /// Attempts to return the settings given through the arguments.
+    pub fn settings(&self) -> Result<Settings, ArgsError> {
+        Ok(Settings {
+            platform: self.platform,
+            speed: self.speed,
+            palette: self.palette.as_ref().map(PalettePreset::palette),
+            bg: self.bg.clone(),
+            fg: self.fg.clone(),
+            mapping: self.mapping,
+            layout: self.layout,
+            keymap: self.keymap.as_deref().map(Keymap::load).transpose()?,
+            gamepad_keymap: self
+                .gamepad_keymap
+                .as_deref()
+                .map(Keymap::load)
+                .transpose()?,
+            audio: AudioSettings {
+                waveform: self.waveform,
+                mute: self.mute.then_some(true),
+                ..AudioSettings::default()
+            },
+            filter: self.filter,
+            filter_strength: self.filter_strength,
+            record_format: self.record_format,
+            scale: self.scale,
+            ..Settings::default()
+        })
     }
 }
 
+/// The file extensions of Octo source code, which is assembled when loaded.
+pub const SOURCE_EXTENSIONS: [&str; 1] = ["8o"];
+
+/// Attempts to read the ROM at the given path, assembling it first if it's Octo
+/// source code (see [`SOURCE_EXTENSIONS`]).
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, ArgsError> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if SOURCE_EXTENSIONS.contains(&extension.as_str()) {
+        Ok(octo::assemble(&fs::read_to_string(path)?)?)
+    } else {
+        Ok(fs::read(path)?)
+    }
+}
+
+/// Attempts to construct an emulator from the ROM (or Octo source code) at the
+/// given path, along with the settings it should be run with: the config's
+/// settings for it overridden by the given settings (usually from
+/// [`Args::settings`]).
+pub fn load_rom(
+    path: &Path,
+    config: &Config,
+    settings: &Settings,
+) -> Result<(Chip8, Settings), ArgsError> {
+    let rom = read_rom(path)?;
+    let settings = config
+        .settings_for(Some((path, &rom)))?
+        .merge(settings.clone());
+    let mut chip8 = Chip8::new(&rom)?;
+    chip8.set_quirks(settings.quirks());
+    Ok((chip8, settings))
+}
+
+/// Deserializes a value from a string, parsed the same way as on the command
+/// line, so that config files accept the same names.
+fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map_err(DeError::custom)
+}
+
 /// Error type for different ways emulator creation could fail.
 #[derive(Debug, Error)]
 pub enum ArgsError {
     #[error("Layout doesn't exist.")]
     Layout,
-    #[error("Hexadecimal RGB color format is incorrect.")]
-    HexRgb,
+    #[error("Mapping must be logical or physical.")]
+    Mapping,
+    #[error("Waveform must be square, sine, triangle, or noise.")]
+    Waveform,
+    #[error("Filter must be none, decay, or, or vblank.")]
+    Filter,
+    #[error("Record format must be gif or png.")]
+    RecordFormat,
+    #[error("{0}")]
+    Color(#[from] ColorError),
     #[error("{0}.")]
     Io(#[from] IoError),
     #[error("{0}")]
     Chip8(#[from] Chip8Error),
+    #[error("{0}")]
+    Keymap(#[from] KeymapError),
+    #[error("{0}")]
+    Config(#[from] ConfigError),
+    #[error("{0}")]
+    Octo(#[from] OctoError),
 }
 
 /// The supported keyboard layouts.
@@ -57,6 +257,22 @@ pub enum Layout {
     #[default]
     Qwerty,
     Colemak,
+    Dvorak,
+    Azerty,
+    Qwertz,
+}
+
+impl Layout {
+    /// Returns the keymap for this keyboard layout.
+    pub fn keymap(&self) -> Keymap {
+        Keymap::from_grid(match self {
+            Self::Qwerty => keymap::QWERTY,
+            Self::Colemak => keymap::COLEMAK,
+            Self::Dvorak => keymap::DVORAK,
+            Self::Azerty => keymap::AZERTY,
+            Self::Qwertz => keymap::QWERTZ,
+        })
+    }
 }
 
 impl Display for Layout {
@@ -64,6 +280,9 @@ impl Display for Layout {
         match self {
             Self::Qwerty => write!(f, "QWERTY"),
             Self::Colemak => write!(f, "Colemak"),
+            Self::Dvorak => write!(f, "Dvorak"),
+            Self::Azerty => write!(f, "AZERTY"),
+            Self::Qwertz => write!(f, "QWERTZ"),
         }
     }
 }
@@ -75,20 +294,119 @@ impl FromStr for Layout {
         match s.to_lowercase().as_ref() {
             "qwerty" => Ok(Layout::Qwerty),
             "colemak" => Ok(Layout::Colemak),
+            "dvorak" => Ok(Layout::Dvorak),
+            "azerty" => Ok(Layout::Azerty),
+            "qwertz" => Ok(Layout::Qwertz),
             _ => Err(ArgsError::Layout),
         }
     }
 }
 
-/// Converts a given hexadecimal color to a 24-bit RGB color.
-pub fn hex_to_rgb(color: String) -> Result<[u8; 3], ArgsError> {
-    if !color.starts_with('#') || color.len() != 7 {
-        return Err(ArgsError::HexRgb);
+impl<'de> Deserialize<'de> for Layout {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+/// The ways a frontend can map its keys to CHIP-8 keys.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Mapping {
+    /// Keys are named after the characters they type, so they depend on the
+    /// keyboard layout.
+    #[default]
+    Logical,
+    /// Keys are named after their physical position (see
+    /// [`keymap::PHYSICAL`]), so they stay in place on every layout.
+    Physical,
+}
+
+impl Display for Mapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Logical => write!(f, "logical"),
+            Self::Physical => write!(f, "physical"),
+        }
+    }
+}
+
+impl FromStr for Mapping {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "logical" => Ok(Mapping::Logical),
+            "physical" => Ok(Mapping::Physical),
+            _ => Err(ArgsError::Mapping),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Mapping {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Platform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl FromStr for FilterMode {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "none" => Ok(FilterMode::None),
+            "decay" => Ok(FilterMode::Decay),
+            "or" => Ok(FilterMode::Or),
+            "vblank" => Ok(FilterMode::Vblank),
+            _ => Err(ArgsError::Filter),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FilterMode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            "noise" => Ok(Waveform::Noise),
+            _ => Err(ArgsError::Waveform),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Waveform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+impl FromStr for RecordFormat {
+    type Err = ArgsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "gif" => Ok(RecordFormat::Gif),
+            "png" => Ok(RecordFormat::Png),
+            _ => Err(ArgsError::RecordFormat),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RecordFormat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
     }
-    let color = (1..color.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&color[i..i + 2], 16))
-        .collect::<Result<Vec<u8>, ParseIntError>>()
-        .map_err(|_| ArgsError::HexRgb)?;
-    Ok([color[0], color[1], color[2]])
 }
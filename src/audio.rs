@@ -0,0 +1,184 @@
+//! Provides sound generation for the sound timer through [`Synth`], which
+//! produces mono samples of a [`Waveform`] with an attack/release envelope so
+//! that the beep doesn't pop when it starts or stops. A frontend only has to
+//! play the samples it produces every frame.
+
+use crate::config::{DEFAULT_ATTACK, DEFAULT_FREQUENCY, DEFAULT_RELEASE, DEFAULT_VOLUME};
+use fastrand::Rng;
+use std::{
+    f32::consts::TAU,
+    fmt::{Display, Error as FmtError, Formatter},
+    time::Duration,
+};
+
+/// The sample rate used by frontends by default, in hertz.
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// How many frames are emulated every second (which is also the rate the
+/// timers are decremented at).
+pub const FRAME_RATE: u32 = 60;
+
+/// The shapes of wave the synthesizer can produce.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Waveform {
+    Square,
+    #[default]
+    Sine,
+    Triangle,
+    /// Random values, changing as often as the frequency says.
+    Noise,
+}
+
+impl Display for Waveform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Square => write!(f, "square"),
+            Self::Sine => write!(f, "sine"),
+            Self::Triangle => write!(f, "triangle"),
+            Self::Noise => write!(f, "noise"),
+        }
+    }
+}
+
+/// A synthesizer producing the beep made while the sound timer is active.
+#[derive(Clone, Debug)]
+pub struct Synth {
+    waveform: Waveform,
+    frequency: f32,
+    volume: f32,
+    attack: f32,
+    release: f32,
+    sample_rate: u32,
+    phase: f32,
+    gain: f32,
+    noise: f32,
+    playing: bool,
+    muted: bool,
+    rng: Rng,
+}
+
+impl Synth {
+    /// Constructs a synthesizer producing the given waveform at the given
+    /// frequency (in hertz) and volume (from 0 to 1), fading in and out over
+    /// the given attack and release times, with samples produced at the given
+    /// sample rate.
+    pub fn new(
+        waveform: Waveform,
+        frequency: f32,
+        volume: f32,
+        attack: Duration,
+        release: Duration,
+        sample_rate: u32,
+    ) -> Self {
+        Self {
+            waveform,
+            frequency: frequency.max(0.0),
+            volume: volume.clamp(0.0, 1.0),
+            attack: attack.as_secs_f32(),
+            release: release.as_secs_f32(),
+            sample_rate: sample_rate.max(1),
+            phase: 0.0,
+            gain: 0.0,
+            noise: 0.0,
+            playing: false,
+            muted: false,
+            rng: Rng::new(),
+        }
+    }
+
+    /// Seeds the random number generator behind the noise waveform, so that
+    /// it sounds the same every time (see
+    /// [`Chip8::seed`](crate::chip8::Chip8::seed)).
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// Returns the sample rate of the synthesizer in hertz.
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Starts or stops the beep (the envelope still fades it in or out).
+    pub fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    /// Returns whether the synthesizer is muted.
+    pub const fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Mutes or unmutes the synthesizer.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Produces the next sample (from -1 to 1).
+    pub fn sample(&mut self) -> f32 {
+        let target = if self.playing && !self.muted {
+            self.volume
+        } else {
+            0.0
+        };
+        let time = if target > self.gain {
+            self.attack
+        } else {
+            self.release
+        };
+        let step = match time * self.sample_rate as f32 {
+            samples if samples >= 1.0 => self.volume.max(f32::EPSILON) / samples,
+            _ => f32::INFINITY,
+        };
+        self.gain = if target > self.gain {
+            (self.gain + step).min(target)
+        } else {
+            (self.gain - step).max(target)
+        };
+        if self.gain == 0.0 {
+            self.phase = 0.0;
+            self.noise = self.rng.f32() * 2.0 - 1.0;
+            return 0.0;
+        }
+
+        let wave = match self.waveform {
+            Waveform::Square if self.phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Sine => (self.phase * TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Noise => self.noise,
+        };
+        self.phase += self.frequency / self.sample_rate as f32;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            self.noise = self.rng.f32() * 2.0 - 1.0;
+        }
+        wave * self.gain
+    }
+
+    /// Fills the buffer with the next samples.
+    pub fn fill(&mut self, buffer: &mut [f32]) {
+        for sample in buffer {
+            *sample = self.sample();
+        }
+    }
+
+    /// Produces the samples lasting a frame (see [`FRAME_RATE`]).
+    pub fn frame(&mut self) -> Vec<f32> {
+        let mut samples = vec![0.0; (self.sample_rate / FRAME_RATE) as usize];
+        self.fill(&mut samples);
+        samples
+    }
+}
+
+impl Default for Synth {
+    fn default() -> Self {
+        Self::new(
+            Waveform::default(),
+            DEFAULT_FREQUENCY,
+            DEFAULT_VOLUME,
+            Duration::from_secs_f32(DEFAULT_ATTACK / 1000.0),
+            Duration::from_secs_f32(DEFAULT_RELEASE / 1000.0),
+            SAMPLE_RATE,
+        )
+    }
+}
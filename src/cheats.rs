@@ -0,0 +1,426 @@
+//! Provides cheats: searching RAM for the addresses of values (like lives or
+//! the current level) through [`Search`], and freezing addresses or registers
+//! to fixed values every frame through [`Cheats`]. Cheat lists can be loaded
+//! from TOML files, either given explicitly or specific to a ROM.
+
+use crate::chip8::{Chip8, Chip8Error, RAM_SIZE};
+use serde::Deserialize;
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use toml::de::Error as TomlError;
+
+/// The extension of a cheat list specific to a ROM, which replaces the ROM's
+/// own extension (`pong.ch8` uses `pong.cheats.toml`).
+pub const ROM_CHEATS_EXTENSION: &str = "cheats.toml";
+
+/// How a value in RAM is compared to its value in the previous snapshot when
+/// narrowing a search.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Comparison {
+    /// The value is equal to the given one.
+    Equal(u8),
+    /// The value changed.
+    Changed,
+    /// The value didn't change.
+    Unchanged,
+    /// The value increased.
+    Increased,
+    /// The value decreased.
+    Decreased,
+}
+
+impl Comparison {
+    /// Returns whether a value matches, given its previous value.
+    pub const fn matches(&self, previous: u8, value: u8) -> bool {
+        match self {
+            Self::Equal(expected) => value == *expected,
+            Self::Changed => value != previous,
+            Self::Unchanged => value == previous,
+            Self::Increased => value > previous,
+            Self::Decreased => value < previous,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Equal(value) => write!(f, "equal to {:#04X}", value),
+            Self::Changed => write!(f, "changed"),
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Increased => write!(f, "increased"),
+            Self::Decreased => write!(f, "decreased"),
+        }
+    }
+}
+
+/// A search for the addresses of a value in RAM, narrowed down step by step:
+/// every address starts as a candidate, and every comparison against the
+/// previous snapshot (like "the lives decreased" after losing one) drops those
+/// that don't match.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Search {
+    snapshot: [u8; RAM_SIZE],
+    candidates: Vec<usize>,
+}
+
+impl Search {
+    /// Starts a search with every address as a candidate, taking a snapshot of
+    /// the emulator's RAM.
+    pub fn new(chip8: &Chip8) -> Self {
+        Self {
+            snapshot: *chip8.memory(),
+            candidates: (0..RAM_SIZE).collect(),
+        }
+    }
+
+    /// Drops the candidates whose value doesn't match the comparison with the
+    /// previous snapshot, then takes a new one. The remaining candidates are
+    /// returned.
+    pub fn narrow(&mut self, chip8: &Chip8, comparison: Comparison) -> &[usize] {
+        let memory = chip8.memory();
+        self.candidates
+            .retain(|&address| comparison.matches(self.snapshot[address], memory[address]));
+        self.snapshot = *memory;
+        &self.candidates
+    }
+
+    /// Returns the addresses that are still candidates.
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    /// Returns the value at the address in the latest snapshot.
+    pub fn value(&self, address: usize) -> Option<u8> {
+        self.snapshot.get(address).copied()
+    }
+}
+
+/// What a cheat freezes.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Target {
+    /// The byte of RAM at the address.
+    Address(usize),
+    /// The register `V[x]`.
+    Register(usize),
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Address(address) => write!(f, "{:#05X}", address),
+            Self::Register(x) => write!(f, "V{:X}", x),
+        }
+    }
+}
+
+/// A cheat freezing an address or a register to a value.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cheat {
+    /// What the cheat is called (like "Infinite lives").
+    pub name: String,
+    /// What the cheat freezes.
+    pub target: Target,
+    /// The value it's frozen to.
+    pub value: u8,
+    /// Whether the cheat is applied.
+    pub enabled: bool,
+}
+
+impl Cheat {
+    /// Freezes the target to the value, if the cheat is enabled. An error is
+    /// returned if the target isn't in RAM or isn't a register.
+    pub fn apply(&self, chip8: &mut Chip8) -> Result<(), Chip8Error> {
+        if !self.enabled {
+            return Ok(());
+        }
+        match self.target {
+            Target::Address(address) => chip8.poke(address, self.value),
+            Target::Register(x) => chip8.set_register(x, self.value),
+        }
+    }
+}
+
+/// A cheat as written in a cheat list, which has either an address or a
+/// register.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCheat {
+    name: String,
+    address: Option<usize>,
+    register: Option<usize>,
+    value: u8,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+/// Cheats in cheat lists are enabled unless they say otherwise.
+const fn enabled() -> bool {
+    true
+}
+
+/// A cheat list as written in a file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawCheats {
+    #[serde(default, rename = "cheat")]
+    cheats: Vec<RawCheat>,
+}
+
+/// A list of cheats, applied every frame.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    /// Constructs an empty cheat list.
+    pub const fn new() -> Self {
+        Self { cheats: Vec::new() }
+    }
+
+    /// Attempts to load a cheat list from a TOML file, in which every
+    /// `[[cheat]]` has a `name`, either an `address` or a `register`, the
+    /// `value` to freeze it to, and optionally whether it's `enabled` (it is by
+    /// default).
+    pub fn load(path: &Path) -> Result<Self, CheatError> {
+        let file = fs::read_to_string(path)?;
+        let raw = toml::from_str::<RawCheats>(&file)?;
+        let cheats = raw
+            .cheats
+            .into_iter()
+            .map(|raw| {
+                let target = match (raw.address, raw.register) {
+                    (Some(address), None) if address < RAM_SIZE => Target::Address(address),
+                    (None, Some(x)) if x <= 0xF => Target::Register(x),
+                    _ => return Err(CheatError::InvalidTarget(raw.name)),
+                };
+                Ok(Cheat {
+                    name: raw.name,
+                    target,
+                    value: raw.value,
+                    enabled: raw.enabled,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { cheats })
+    }
+
+    /// Attempts to load the cheat list specific to the ROM at the given path,
+    /// which is in a file next to it (see [`ROM_CHEATS_EXTENSION`]). An empty
+    /// list is returned if there isn't one.
+    pub fn load_for_rom(rom: &Path) -> Result<Self, CheatError> {
+        let path = Self::rom_cheats_path(rom);
+        if !path.is_file() {
+            return Ok(Self::new());
+        }
+        Self::load(&path)
+    }
+
+    /// Returns the path of the cheat list specific to the ROM at the given
+    /// path.
+    pub fn rom_cheats_path(rom: &Path) -> PathBuf {
+        rom.with_extension(ROM_CHEATS_EXTENSION)
+    }
+
+    /// Adds a cheat to the end of the list.
+    pub fn push(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    /// Removes the cheat at the index, returning it.
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+
+    /// Enables or disables the cheat at the index, returning whether it's now
+    /// enabled.
+    pub fn toggle(&mut self, index: usize) -> Option<bool> {
+        let cheat = self.cheats.get_mut(index)?;
+        cheat.enabled = !cheat.enabled;
+        Some(cheat.enabled)
+    }
+
+    /// Returns the cheats.
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// Returns whether there aren't any cheats.
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    /// Freezes the target of every enabled cheat to its value, which should be
+    /// done every frame. Cheats whose target isn't in RAM or isn't a register
+    /// are disabled, and an error is returned for each of them.
+    pub fn apply(&mut self, chip8: &mut Chip8) -> Vec<CheatError> {
+        let mut errors = Vec::new();
+        for cheat in &mut self.cheats {
+            if let Err(err) = cheat.apply(chip8) {
+                cheat.enabled = false;
+                errors.push(CheatError::Disabled(cheat.name.clone(), err));
+            }
+        }
+        errors
+    }
+}
+
+/// Used to describe possible errors when loading or applying a cheat list.
+#[derive(Debug, Error)]
+pub enum CheatError {
+    #[error("Cheat {0} needs either an address in RAM or a register from V0 to VF.")]
+    InvalidTarget(String),
+    #[error("Cheat {0} was disabled: {1}")]
+    Disabled(String, Chip8Error),
+    #[error("{0}.")]
+    Io(#[from] IoError),
+    #[error("{0}")]
+    Toml(#[from] TomlError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts a search after setting 0x300 to 0x302 to 5, then sets them to
+    /// the given values and narrows it with the comparison.
+    fn narrowed(values: [u8; 3], comparison: Comparison) -> (Search, Chip8) {
+        let mut chip8 = Chip8::new(&[0x12, 0x00]).unwrap();
+        for address in 0x300..0x303 {
+            chip8.poke(address, 5).unwrap();
+        }
+        let mut search = Search::new(&chip8);
+        for (address, value) in (0x300..).zip(values) {
+            chip8.poke(address, value).unwrap();
+        }
+        search.narrow(&chip8, comparison);
+        (search, chip8)
+    }
+
+    /// Writes a cheat list to a file of its own, returning its path.
+    fn cheat_list(name: &str, toml: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "biscuit8-{}-{}.cheats.toml",
+            name,
+            std::process::id()
+        ));
+        fs::write(&path, toml).unwrap();
+        path
+    }
+
+    #[test]
+    fn searches_narrow_by_comparison() {
+        let (search, _) = narrowed([4, 5, 6], Comparison::Changed);
+        assert_eq!(search.candidates(), [0x300, 0x302]);
+        let (search, _) = narrowed([4, 5, 6], Comparison::Increased);
+        assert_eq!(search.candidates(), [0x302]);
+        let (search, _) = narrowed([4, 5, 6], Comparison::Decreased);
+        assert_eq!(search.candidates(), [0x300]);
+        let (search, _) = narrowed([4, 5, 6], Comparison::Unchanged);
+        assert_eq!(search.candidates().len(), RAM_SIZE - 2);
+        assert!(search.candidates().contains(&0x301));
+        assert!(!search.candidates().contains(&0x300));
+
+        // Later steps compare against the snapshot taken by the last one.
+        let (mut search, mut chip8) = narrowed([4, 5, 6], Comparison::Changed);
+        chip8.poke(0x300, 6).unwrap();
+        assert_eq!(search.narrow(&chip8, Comparison::Equal(6)), [0x300, 0x302]);
+        assert_eq!(search.narrow(&chip8, Comparison::Changed), []);
+        assert_eq!(search.value(0x302), Some(6));
+        assert_eq!(search.value(RAM_SIZE), None);
+    }
+
+    #[test]
+    fn loads_cheat_lists() {
+        let path = cheat_list(
+            "valid",
+            "[[cheat]]\nname = \"Lives\"\naddress = 0x3A0\nvalue = 9\n\n\
+             [[cheat]]\nname = \"Level\"\nregister = 0xE\nvalue = 2\nenabled = false\n",
+        );
+        let cheats = Cheats::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            cheats.cheats(),
+            [
+                Cheat {
+                    name: "Lives".to_string(),
+                    target: Target::Address(0x3A0),
+                    value: 9,
+                    enabled: true,
+                },
+                Cheat {
+                    name: "Level".to_string(),
+                    target: Target::Register(0xE),
+                    value: 2,
+                    enabled: false,
+                },
+            ]
+        );
+
+        for target in [
+            "",
+            "address = 0x1000",
+            "register = 16",
+            "address = 1\nregister = 1",
+        ] {
+            let path = cheat_list(
+                "invalid",
+                &format!("[[cheat]]\nname = \"Bad\"\n{}\nvalue = 1\n", target),
+            );
+            let err = Cheats::load(&path).err();
+            fs::remove_file(path).unwrap();
+            assert!(matches!(err, Some(CheatError::InvalidTarget(name)) if name == "Bad"));
+        }
+        let path = cheat_list(
+            "unknown",
+            "[[cheat]]\nname = \"Bad\"\nregister = 1\nvalue = 1\nfrozen = true\n",
+        );
+        let err = Cheats::load(&path).err();
+        fs::remove_file(path).unwrap();
+        assert!(matches!(err, Some(CheatError::Toml(_))));
+
+        let rom = Path::new("missing.ch8");
+        assert_eq!(
+            Cheats::rom_cheats_path(rom),
+            Path::new("missing.cheats.toml")
+        );
+        assert!(Cheats::load_for_rom(rom).unwrap().is_empty());
+    }
+
+    #[test]
+    fn applies_enabled_cheats() {
+        let mut chip8 = Chip8::new(&[0x12, 0x00]).unwrap();
+        let mut cheats = Cheats::new();
+        for (name, target, enabled) in [
+            ("Lives", Target::Address(0x3A0), true),
+            ("Level", Target::Register(0xE), true),
+            ("Score", Target::Register(0x2), false),
+            ("Broken", Target::Register(0x10), true),
+        ] {
+            cheats.push(Cheat {
+                name: name.to_string(),
+                target,
+                value: 9,
+                enabled,
+            });
+        }
+
+        let errors = cheats.apply(&mut chip8);
+        assert!(matches!(
+            &errors[..],
+            [CheatError::Disabled(name, Chip8Error::InvalidRegister(0x10))] if name == "Broken"
+        ));
+        assert_eq!(chip8.peek(0x3A0), Ok(9));
+        assert_eq!(chip8.state().v[0xE], 9);
+        assert_eq!(chip8.state().v[0x2], 0);
+        let enabled = cheats.cheats().iter().map(|cheat| cheat.enabled);
+        assert_eq!(enabled.collect::<Vec<_>>(), [true, true, false, false]);
+        assert!(cheats.apply(&mut chip8).is_empty());
+    }
+}
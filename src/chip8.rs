@@ -1,16 +1,55 @@
 //! Provides the logic of the emulator itself, primarily through the [`Chip8`]
-//! struct. The error type [`Chip8Error`] is also provided.
+//! struct. The error type [`Chip8Error`] is also provided, along with the
+//! [`Quirks`] that differ between CHIP-8 [`Platform`]s, and [`Chip8State`] and
+//! [`Trace`] for tools (like debuggers, cheats, and profilers) that observe or
+//! patch the machine. With the `jit` feature on x86-64, [`jit`] translates the
+//! code into native code instead of interpreting it.
 
-use crate::{instruction::Instruction, keys::Keys, screen::Screen};
+use crate::{
+    instruction::{Instruction, Opcode},
+    keys::Keys,
+    screen::{Screen, HEIGHT, WIDTH},
+};
+use core::{
+    fmt::{Debug, Display, Error as FmtError, Formatter},
+    ops::Range,
+    str::FromStr,
+};
 use fastrand::Rng;
-use std::time::{Duration, Instant};
-use thiserror::Error;
+use serde::{Deserialize, Serialize};
+
+#[cfg(all(feature = "jit", target_arch = "x86_64"))]
+pub mod jit;
 
 /// How many bytes to allocate for the emulator's RAM.
-const RAM_SIZE: usize = 0x1000;
+pub const RAM_SIZE: usize = 0x1000;
 
 /// Where to put the ROM in the emulator's RAM.
-const ROM_LOC: usize = 0x200;
+pub const ROM_LOC: usize = 0x200;
+
+/// How many return addresses the stack can hold (subroutines can be nested this
+/// deep).
+pub const STACK_SIZE: usize = 0x10;
+
+/// The bytes every saved state starts with, the last one being the version of
+/// the format.
+const STATE_MAGIC: [u8; 4] = *b"B8S\x01";
+
+/// How many bytes a saved state takes (see [`Chip8::save_state`]): the magic
+/// bytes, RAM, registers, the index register and program counter, the timers,
+/// the stack and its pointer, the screen (a bit per pixel), the quirks, and the
+/// random number generator.
+pub const STATE_SIZE: usize = STATE_MAGIC.len()
+    + RAM_SIZE
+    + 0x10
+    + 2
+    + 2
+    + 2
+    + STACK_SIZE * 2
+    + 1
+    + WIDTH * HEIGHT / 8
+    + 1
+    + 8;
 
 /// The sprites for every hexadecimal digit as a font (stored at the beginning
 /// of the emulator's RAM).
@@ -42,19 +81,23 @@ pub struct Chip8 {
     pc: usize,
     dt: u8,
     st: u8,
-    stack: Vec<usize>,
+    stack: [usize; STACK_SIZE],
+    sp: usize,
     instruction: Instruction,
     keys: Keys,
     screen: Screen,
-    last_decrement: Instant,
+    quirks: Quirks,
     rng: Rng,
+    tracing: bool,
+    trace: Option<Trace>,
+    decoded: DecodeCache,
 }
 
 impl Chip8 {
     /// Create an emulator from the given ROM.
     pub fn new(rom: &[u8]) -> Result<Self, Chip8Error> {
         if rom.len() > RAM_SIZE - ROM_LOC {
-            let exceed = rom.len() - RAM_SIZE - ROM_LOC;
+            let exceed = rom.len() - (RAM_SIZE - ROM_LOC);
             return Err(Chip8Error::RomTooBig(exceed));
         }
 
@@ -69,44 +112,371 @@ impl Chip8 {
             pc: ROM_LOC,
             dt: 0,
             st: 0,
-            stack: Vec::new(),
+            stack: [0; STACK_SIZE],
+            sp: 0,
             instruction: Instruction::new(0),
             keys: Keys::new(),
             screen: Screen::new(),
-            last_decrement: Instant::now(),
-            rng: Rng::new(),
+            quirks: Quirks::default(),
+            rng: Self::rng(),
+            tracing: false,
+            trace: None,
+            decoded: DecodeCache::new(),
         })
     }
 
+    /// Returns a random number generator seeded randomly, or with zero without
+    /// `std` (where [`set_seed`](Self::set_seed) should be used instead).
+    fn rng() -> Rng {
+        #[cfg(feature = "std")]
+        return Rng::new();
+        #[cfg(not(feature = "std"))]
+        return Rng::with_seed(0);
+    }
+
+    /// Returns the emulator's screen.
+    pub const fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Returns the quirks the emulator is running with.
+    pub const fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Changes the quirks the emulator is running with.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Seeds the random number generator, so that the emulator behaves the same
+    /// every time it's run with the same input (see
+    /// [`Movie`](crate::movie::Movie)).
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::with_seed(seed);
+    }
+
+    /// Returns the current state of the random number generator as a seed,
+    /// which other sources of randomness (like the noise of
+    /// [`Synth`](crate::audio::Synth)) can be seeded with so that they're as
+    /// reproducible as the emulator.
+    pub fn seed(&self) -> u64 {
+        self.rng.get_seed()
+    }
+
+    /// Returns a snapshot of the emulator's state, which can be restored with
+    /// [`load_state`](Self::load_state) (even by another emulator).
+    pub fn save_state(&self) -> [u8; STATE_SIZE] {
+        let mut state = [0; STATE_SIZE];
+        let mut writer = StateWriter {
+            state: &mut state,
+            pos: 0,
+        };
+        writer.write(&STATE_MAGIC);
+        writer.write(&self.ram);
+        writer.write(&self.v);
+        writer.write(&(self.i as u16).to_be_bytes());
+        writer.write(&(self.pc as u16).to_be_bytes());
+        writer.write(&[self.dt, self.st]);
+        for address in self.stack {
+            writer.write(&(address as u16).to_be_bytes());
+        }
+        writer.write(&[self.sp as u8]);
+        for y in 0..HEIGHT {
+            for x in (0..WIDTH).step_by(8) {
+                let byte = (0..8).fold(0, |byte, bit| {
+                    byte << 1 | self.screen.pixel(x + bit, y) as u8
+                });
+                writer.write(&[byte]);
+            }
+        }
+        let quirks = [
+            self.quirks.vf_reset,
+            self.quirks.memory,
+            self.quirks.shifting,
+            self.quirks.jumping,
+            self.quirks.wrapping,
+        ];
+        writer.write(&[quirks
+            .iter()
+            .fold(0, |byte, quirk| byte << 1 | *quirk as u8)]);
+        writer.write(&self.rng.get_seed().to_be_bytes());
+        state
+    }
+
+    /// Restores a snapshot of an emulator's state taken with
+    /// [`save_state`](Self::save_state). An error is returned (and nothing is
+    /// restored) if it isn't a valid snapshot, including if its index register
+    /// or program counter is outside RAM.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), Chip8Error> {
+        if state.len() != STATE_SIZE || state[..STATE_MAGIC.len()] != STATE_MAGIC {
+            return Err(Chip8Error::InvalidState);
+        }
+        let mut reader = StateReader {
+            state,
+            pos: STATE_MAGIC.len(),
+        };
+        let mut loaded = self.clone();
+        loaded.ram.copy_from_slice(reader.read(RAM_SIZE));
+        loaded.decoded = DecodeCache::new();
+        loaded.v.copy_from_slice(reader.read(0x10));
+        loaded.i = reader.read_u16() as usize;
+        loaded.pc = reader.read_u16() as usize;
+        [loaded.dt, loaded.st] = [reader.read_u8(), reader.read_u8()];
+        for address in &mut loaded.stack {
+            *address = reader.read_u16() as usize;
+        }
+        loaded.sp = reader.read_u8() as usize;
+        if loaded.i >= RAM_SIZE || loaded.pc >= RAM_SIZE || loaded.sp > STACK_SIZE {
+            return Err(Chip8Error::InvalidState);
+        }
+        loaded.screen.clear();
+        for y in 0..HEIGHT {
+            for x in (0..WIDTH).step_by(8) {
+                let byte = reader.read_u8();
+                for bit in 0..8 {
+                    loaded.screen.set_pixel(x + bit, y, byte & 0x80 >> bit != 0);
+                }
+            }
+        }
+        let quirks = reader.read_u8();
+        loaded.quirks = Quirks {
+            vf_reset: quirks & 0b10000 != 0,
+            memory: quirks & 0b01000 != 0,
+            shifting: quirks & 0b00100 != 0,
+            jumping: quirks & 0b00010 != 0,
+            wrapping: quirks & 0b00001 != 0,
+        };
+        let mut seed = [0; 8];
+        seed.copy_from_slice(reader.read(8));
+        loaded.rng = Rng::with_seed(u64::from_be_bytes(seed));
+        *self = loaded;
+        Ok(())
+    }
+
     /// Performs one iteration of the fetch-decode-execute cycle and returns the
     /// screen as well as whether the frontend should beep or not, if it was
-    /// updated.An error is returned if there isn't another [`Instruction`] to be
-    /// decoded and executed or the opcode of the current [`Instruction`] is
+    /// updated. An error is returned if there isn't another [`Instruction`] to
+    /// be decoded and executed or the opcode of the current [`Instruction`] is
     /// unknown.
+    ///
+    /// The timers aren't decremented here (they used to be, every cycle):
+    /// callers must call [`decrement_timers`](Self::decrement_timers) once per
+    /// frame, as [`run`](Self::run) doesn't either.
     pub fn instruction_cycle(&mut self, keys: Keys) -> Result<(Option<Screen>, bool), Chip8Error> {
-        self.decrement_timers();
-        let Some(instruction) = self.fetch_instruction() else {
-            return Err(Chip8Error::NoMoreInstructions);
-        };
         self.keys = keys;
-        self.instruction = instruction;
-        self.pc += 2;
-        if self.decode_execute()? {
+        if self.step()? {
             return Ok((Some(self.screen.clone()), self.st > 0));
         }
         Ok((None, self.st > 0))
     }
 
-    /// Decrements the delay and sound timers at a rate of 60 hertz.
-    fn decrement_timers(&mut self) {
-        if self.last_decrement.elapsed() >= Duration::new(0, 16666666) {
-            if self.dt != 0 {
-                self.dt -= 1;
+    /// Executes up to the given number of instructions (stopping early once one
+    /// updates the screen) and returns how many were executed, along with
+    /// whether the screen was updated. The last key pressed only counts for the
+    /// first instruction. This is much faster than calling
+    /// [`instruction_cycle`](Self::instruction_cycle) repeatedly, since the
+    /// screen isn't copied (get it with [`screen`](Self::screen) instead). An
+    /// error is returned like with [`instruction_cycle`](Self::instruction_cycle).
+    pub fn run(&mut self, keys: Keys, cycles: usize) -> Result<(usize, bool), Chip8Error> {
+        self.keys = keys;
+        self.run_until(cycles, |_| false)
+    }
+
+    /// Does the same as [`run`](Self::run) with the keys already set, but also
+    /// stops once the program counter is at an address `stop` returns true for.
+    #[inline(always)]
+    fn run_until(
+        &mut self,
+        cycles: usize,
+        mut stop: impl FnMut(usize) -> bool,
+    ) -> Result<(usize, bool), Chip8Error> {
+        for cycle in 1..=cycles {
+            if self.step()? {
+                return Ok((cycle, true));
             }
-            if self.st != 0 {
-                self.st -= 1;
+            self.keys.reset_last_pressed();
+            if stop(self.pc) {
+                return Ok((cycle, false));
             }
-            self.last_decrement = Instant::now();
+        }
+        Ok((cycles, false))
+    }
+
+    /// Fetches, decodes (unless it's cached), and executes the next
+    /// instruction, returning whether the screen was updated.
+    #[inline(always)]
+    fn step(&mut self) -> Result<bool, Chip8Error> {
+        let Some(instruction) = self.fetch_instruction() else {
+            return Err(Chip8Error::NoMoreInstructions);
+        };
+        if self.tracing {
+            self.trace = Some(Trace {
+                pc: self.pc,
+                instruction,
+                i: self.i,
+            });
+        }
+        let opcode = self.decoded.opcode(self.pc, instruction);
+        self.instruction = instruction;
+        self.pc += 2;
+        self.execute(opcode)
+    }
+
+    /// Decrements the delay and sound timers, which should be done at the end of
+    /// every frame (60 times a second). Timers are driven by frames rather than
+    /// the clock so that the emulator behaves the same however fast it's run.
+    pub fn decrement_timers(&mut self) {
+        self.dt = self.dt.saturating_sub(1);
+        self.st = self.st.saturating_sub(1);
+    }
+
+    /// Returns whether the sound timer is active (so the frontend should beep).
+    pub const fn beeping(&self) -> bool {
+        self.st > 0
+    }
+
+    /// Returns the registers, from `V0` to `VF`.
+    pub const fn registers(&self) -> &[u8; 0x10] {
+        &self.v
+    }
+
+    /// Sets the register `V[x]`. An error is returned if there isn't such a
+    /// register.
+    pub fn set_register(&mut self, x: usize, value: u8) -> Result<(), Chip8Error> {
+        let register = self.v.get_mut(x).ok_or(Chip8Error::InvalidRegister(x))?;
+        *register = value;
+        Ok(())
+    }
+
+    /// Returns the program counter (the address of the next instruction).
+    pub const fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Sets the program counter. An error is returned if it's outside RAM.
+    pub fn set_pc(&mut self, pc: usize) -> Result<(), Chip8Error> {
+        self.pc = Self::address(pc)?;
+        Ok(())
+    }
+
+    /// Returns the index register.
+    pub const fn index(&self) -> usize {
+        self.i
+    }
+
+    /// Sets the index register. An error is returned if it's outside RAM.
+    pub fn set_index(&mut self, i: usize) -> Result<(), Chip8Error> {
+        self.i = Self::address(i)?;
+        Ok(())
+    }
+
+    /// Returns the delay and sound timers.
+    pub const fn timers(&self) -> (u8, u8) {
+        (self.dt, self.st)
+    }
+
+    /// Sets the delay and sound timers.
+    pub fn set_timers(&mut self, dt: u8, st: u8) {
+        (self.dt, self.st) = (dt, st);
+    }
+
+    /// Returns the return addresses on the stack, from the outermost
+    /// subroutine call to the innermost one.
+    pub fn stack(&self) -> &[usize] {
+        &self.stack[..self.sp]
+    }
+
+    /// Returns the byte of RAM at the address. An error is returned if it's
+    /// outside RAM.
+    pub fn peek(&self, address: usize) -> Result<u8, Chip8Error> {
+        Ok(self.ram[Self::address(address)?])
+    }
+
+    /// Writes a byte of RAM at the address. An error is returned if it's
+    /// outside RAM.
+    pub fn poke(&mut self, address: usize, value: u8) -> Result<(), Chip8Error> {
+        self.ram[Self::address(address)?] = value;
+        self.decoded.invalidate(address..address + 1);
+        Ok(())
+    }
+
+    /// Returns the emulator's RAM (with the font at the start and the ROM at
+    /// [`ROM_LOC`]).
+    pub const fn memory(&self) -> &[u8; RAM_SIZE] {
+        &self.ram
+    }
+
+    /// Returns the emulator's RAM for patching.
+    pub fn memory_mut(&mut self) -> &mut [u8; RAM_SIZE] {
+        self.decoded = DecodeCache::new();
+        &mut self.ram
+    }
+
+    /// Forgets what's cached about the code at the addresses (clamped to RAM),
+    /// for when RAM is written without going through the emulator: like
+    /// through a pointer into [`memory_mut`](Self::memory_mut) held onto after
+    /// it was called.
+    pub fn invalidate_decoded(&mut self, addresses: Range<usize>) {
+        let end = addresses.end.min(RAM_SIZE);
+        self.decoded.invalidate(addresses.start.min(end)..end);
+    }
+
+    /// Returns a copy of the machine's state, which can be changed and given
+    /// back with [`set_state`](Self::set_state).
+    pub fn state(&self) -> Chip8State {
+        Chip8State {
+            ram: self.ram,
+            v: self.v,
+            i: self.i,
+            pc: self.pc,
+            dt: self.dt,
+            st: self.st,
+            stack: self.stack,
+            sp: self.sp,
+            screen: self.screen.clone(),
+        }
+    }
+
+    /// Replaces the machine's state (keeping the quirks and the random number
+    /// generator). An error is returned (and nothing is replaced) if the index
+    /// register or the program counter is outside RAM, or the stack pointer is
+    /// past the end of the stack.
+    pub fn set_state(&mut self, state: &Chip8State) -> Result<(), Chip8Error> {
+        if state.i >= RAM_SIZE || state.pc >= RAM_SIZE || state.sp > STACK_SIZE {
+            return Err(Chip8Error::InvalidState);
+        }
+        self.ram = state.ram;
+        self.decoded = DecodeCache::new();
+        self.v = state.v;
+        self.i = state.i;
+        self.pc = state.pc;
+        self.dt = state.dt;
+        self.st = state.st;
+        self.stack = state.stack;
+        self.sp = state.sp;
+        self.screen = state.screen.clone();
+        Ok(())
+    }
+
+    /// Starts or stops recording a [`Trace`] of every instruction executed,
+    /// which costs a little speed.
+    pub fn set_tracing(&mut self, tracing: bool) {
+        self.tracing = tracing;
+        self.trace = None;
+    }
+
+    /// Returns the trace of the last instruction executed (or attempted), if
+    /// tracing is enabled.
+    pub const fn trace(&self) -> Option<Trace> {
+        self.trace
+    }
+
+    /// Checks that an address is in RAM.
+    const fn address(address: usize) -> Result<usize, Chip8Error> {
+        match address < RAM_SIZE {
+            true => Ok(address),
+            false => Err(Chip8Error::InvalidAddress(address)),
         }
     }
 
@@ -118,53 +488,56 @@ impl Chip8 {
         Some(Instruction::new(u16::from_be_bytes([*first, *second])))
     }
 
-    /// Decodes the current [`Instruction`] and executes the appropriate method. An
-    /// error is returned when the instruction opcode is unknown, and a bool for
-    /// whether the screen was updated.
-    fn decode_execute(&mut self) -> Result<bool, Chip8Error> {
-        match self.instruction.nibbles() {
-            (0, 0, 0, 0) => (),
-            (0x0, 0x0, 0xE, 0x0) => {
+    /// Executes the appropriate method for the decoded opcode of the current
+    /// [`Instruction`]. An error is returned when the opcode is unknown, and a
+    /// bool for whether the screen was updated.
+    #[inline(always)]
+    fn execute(&mut self, opcode: Option<Opcode>) -> Result<bool, Chip8Error> {
+        let Some(opcode) = opcode else {
+            return Err(Chip8Error::UnknownInstruction(self.instruction, self.pc));
+        };
+        match opcode {
+            Opcode::Nop => (),
+            Opcode::ClearScreen => {
                 self.clear_screen();
                 return Ok(true);
             }
-            (0x0, 0x0, 0xE, 0xE) => self.subroutine_return(),
-            (0x1, _, _, _) => self.jump_addr(),
-            (0x2, _, _, _) => self.call_subroutine(),
-            (0x3, _, _, _) => self.skip_eq_byte(),
-            (0x4, _, _, _) => self.skip_not_byte(),
-            (0x5, _, _, 0x0) => self.skip_eq_reg(),
-            (0x6, _, _, _) => self.set_reg_byte(),
-            (0x7, _, _, _) => self.add_byte(),
-            (0x8, _, _, 0x0) => self.set_reg_reg(),
-            (0x8, _, _, 0x1) => self.or_reg(),
-            (0x8, _, _, 0x2) => self.and_reg(),
-            (0x8, _, _, 0x3) => self.xor_reg(),
-            (0x8, _, _, 0x4) => self.add_reg(),
-            (0x8, _, _, 0x5) => self.sub_reg(),
-            (0x8, _, _, 0x6) => self.shr_reg(),
-            (0x8, _, _, 0x7) => self.rev_sub_reg(),
-            (0x8, _, _, 0xE) => self.shl_reg(),
-            (0x9, _, _, 0x0) => self.skip_not_reg(),
-            (0xA, _, _, _) => self.set_index_addr(),
-            (0xB, _, _, _) => self.jump_add_addr(),
-            (0xC, _, _, _) => self.rand_and_byte(),
-            (0xD, _, _, _) => {
-                self.draw_sprite();
+            Opcode::SubroutineReturn => self.subroutine_return()?,
+            Opcode::JumpAddr => self.jump_addr(),
+            Opcode::CallSubroutine => self.call_subroutine()?,
+            Opcode::SkipEqByte => self.skip_eq_byte(),
+            Opcode::SkipNotByte => self.skip_not_byte(),
+            Opcode::SkipEqReg => self.skip_eq_reg(),
+            Opcode::SetRegByte => self.set_reg_byte(),
+            Opcode::AddByte => self.add_byte(),
+            Opcode::SetRegReg => self.set_reg_reg(),
+            Opcode::OrReg => self.or_reg(),
+            Opcode::AndReg => self.and_reg(),
+            Opcode::XorReg => self.xor_reg(),
+            Opcode::AddReg => self.add_reg(),
+            Opcode::SubReg => self.sub_reg(),
+            Opcode::ShrReg => self.shr_reg(),
+            Opcode::RevSubReg => self.rev_sub_reg(),
+            Opcode::ShlReg => self.shl_reg(),
+            Opcode::SkipNotReg => self.skip_not_reg(),
+            Opcode::SetIndexAddr => self.set_index_addr(),
+            Opcode::JumpAddAddr => self.jump_add_addr(),
+            Opcode::RandAndByte => self.rand_and_byte(),
+            Opcode::DrawSprite => {
+                self.draw_sprite()?;
                 return Ok(true);
             }
-            (0xE, _, 0x9, 0xE) => self.skip_eq_key(),
-            (0xE, _, 0xA, 0x1) => self.skip_not_key(),
-            (0xF, _, 0x0, 0x7) => self.set_reg_delay(),
-            (0xF, _, 0x0, 0xA) => self.set_reg_key(),
-            (0xF, _, 0x1, 0x5) => self.set_delay_reg(),
-            (0xF, _, 0x1, 0x8) => self.set_sound_reg(),
-            (0xF, _, 0x1, 0xE) => self.add_index_reg(),
-            (0xF, _, 0x2, 0x9) => self.set_index_char(),
-            (0xF, _, 0x3, 0x3) => self.set_index_bcd(),
-            (0xF, _, 0x5, 0x5) => self.set_index_reg(),
-            (0xF, _, 0x6, 0x5) => self.set_reg_index(),
-            _ => return Err(Chip8Error::UnknownInstruction(self.instruction, self.pc)),
+            Opcode::SkipEqKey => self.skip_eq_key(),
+            Opcode::SkipNotKey => self.skip_not_key(),
+            Opcode::SetRegDelay => self.set_reg_delay(),
+            Opcode::SetRegKey => self.set_reg_key(),
+            Opcode::SetDelayReg => self.set_delay_reg(),
+            Opcode::SetSoundReg => self.set_sound_reg(),
+            Opcode::AddIndexReg => self.add_index_reg(),
+            Opcode::SetIndexChar => self.set_index_char(),
+            Opcode::SetIndexBcd => self.set_index_bcd()?,
+            Opcode::SetIndexReg => self.set_index_reg()?,
+            Opcode::SetRegIndex => self.set_reg_index()?,
         }
         Ok(false)
     }
@@ -174,12 +547,15 @@ impl Chip8 {
         self.screen.clear();
     }
 
-    /// Returns from the current subroutine using the stack.
-    fn subroutine_return(&mut self) {
-        self.pc = self
-            .stack
-            .pop()
-            .expect("Stack should've had something to pop.");
+    /// Returns from the current subroutine using the stack. An error is
+    /// returned if there isn't a subroutine to return from.
+    fn subroutine_return(&mut self) -> Result<(), Chip8Error> {
+        if self.sp == 0 {
+            return Err(Chip8Error::StackUnderflow(self.pc));
+        }
+        self.sp -= 1;
+        self.pc = self.stack[self.sp];
+        Ok(())
     }
 
     /// Jumps to the given address.
@@ -187,10 +563,16 @@ impl Chip8 {
         self.pc = self.instruction.nnn();
     }
 
-    /// Calls a subroutine using the stack.
-    fn call_subroutine(&mut self) {
-        self.stack.push(self.pc);
+    /// Calls a subroutine using the stack. An error is returned if the stack is
+    /// full.
+    fn call_subroutine(&mut self) -> Result<(), Chip8Error> {
+        if self.sp == STACK_SIZE {
+            return Err(Chip8Error::StackOverflow(self.pc));
+        }
+        self.stack[self.sp] = self.pc;
+        self.sp += 1;
         self.pc = self.instruction.nnn();
+        Ok(())
     }
 
     /// Skips the next instruction if the register is equal to the byte.
@@ -233,19 +615,27 @@ impl Chip8 {
     /// Applies a bitwise OR operation onto the register with the register.
     fn or_reg(&mut self) {
         self.v[self.instruction.x()] |= self.v[self.instruction.y()];
-        self.v[0xF] = 0;
+        self.reset_flag();
     }
 
     /// Applies a bitwise AND operation onto the register with the register.
     fn and_reg(&mut self) {
         self.v[self.instruction.x()] &= self.v[self.instruction.y()];
-        self.v[0xF] = 0;
+        self.reset_flag();
     }
 
     /// Applies a bitwise XOR operation onto the register with the register.
     fn xor_reg(&mut self) {
         self.v[self.instruction.x()] ^= self.v[self.instruction.y()];
-        self.v[0xF] = 0;
+        self.reset_flag();
+    }
+
+    /// Resets the flag register after a bitwise operation if the quirk is
+    /// enabled.
+    fn reset_flag(&mut self) {
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
     }
 
     /// Adds the register to the register and sets the flag register in the case of
@@ -270,9 +660,9 @@ impl Chip8 {
     /// register by one.
     fn shr_reg(&mut self) {
         let x = self.instruction.x();
-        let lsb = self.v[x] & 1;
-        self.v[x] = self.v[self.instruction.y()] >> 1;
-        self.v[0xF] = lsb;
+        let source = self.v[self.shift_source()];
+        self.v[x] = source >> 1;
+        self.v[0xF] = source & 1;
     }
 
     /// Sets the register to the register minus it and sets the flag register in the
@@ -288,9 +678,19 @@ impl Chip8 {
     /// register by one.
     fn shl_reg(&mut self) {
         let x = self.instruction.x();
-        let msb = (self.v[x] >> 7) & 1;
-        self.v[x] = self.v[self.instruction.y()] << 1;
-        self.v[0xF] = msb;
+        let source = self.v[self.shift_source()];
+        self.v[x] = source << 1;
+        self.v[0xF] = (source >> 7) & 1;
+    }
+
+    /// Returns the register shifted by the shift instructions: the second one
+    /// normally, or the first one if the quirk is enabled.
+    fn shift_source(&self) -> usize {
+        if self.quirks.shifting {
+            self.instruction.x()
+        } else {
+            self.instruction.y()
+        }
     }
 
     /// Skips the next instruction if the register isn't equal to the register by
@@ -306,9 +706,15 @@ impl Chip8 {
         self.i = self.instruction.nnn();
     }
 
-    /// Sets the program counter to the address plus the first register.
+    /// Sets the program counter to the address plus the first register, or plus
+    /// the register in the address if the quirk is enabled.
     fn jump_add_addr(&mut self) {
-        self.pc = self.instruction.nnn() + self.v[0x0] as usize;
+        let offset = if self.quirks.jumping {
+            self.v[self.instruction.x()]
+        } else {
+            self.v[0x0]
+        };
+        self.pc = self.instruction.nnn() + offset as usize;
     }
 
     /// Sets the register to the result of a bitwise AND operation on a random
@@ -319,12 +725,21 @@ impl Chip8 {
 
     /// Draws the sprite located in the index register onto the screen, and the flag
     /// register is set if a pixel collision occurs; the location of the sprite is
-    /// represented using the registers, and height is defined by the nibble.
-    fn draw_sprite(&mut self) {
-        let sprite = &self.ram[self.i..self.i + self.instruction.n()];
+    /// represented using the registers, and height is defined by the nibble. An
+    /// error is returned if the sprite goes past the end of RAM.
+    fn draw_sprite(&mut self) -> Result<(), Chip8Error> {
+        let Some(sprite) = self.ram.get(self.i..self.i + self.instruction.n()) else {
+            return Err(Chip8Error::InvalidAddress(RAM_SIZE));
+        };
         let x = self.v[self.instruction.x()] as usize;
         let y = self.v[self.instruction.y()] as usize;
-        self.v[0xF] = self.screen.draw_sprite(sprite, x, y) as u8;
+        let erased = if self.quirks.wrapping {
+            self.screen.draw_sprite_wrapped(sprite, x, y)
+        } else {
+            self.screen.draw_sprite(sprite, x, y)
+        };
+        self.v[0xF] = erased as u8;
+        Ok(())
     }
 
     /// Skips the next instruction if the key represented in the register is
@@ -367,9 +782,10 @@ impl Chip8 {
         self.st = self.v[self.instruction.x()];
     }
 
-    /// Adds the register to the index register.
+    /// Adds the register to the index register, wrapping around at the end of
+    /// RAM (the index register only addresses RAM).
     fn add_index_reg(&mut self) {
-        self.i = self.i.wrapping_add(self.v[self.instruction.x()] as usize);
+        self.i = (self.i + self.v[self.instruction.x()] as usize) % RAM_SIZE;
     }
 
     /// Sets the index register to the font character represented by the register.
@@ -379,38 +795,579 @@ impl Chip8 {
 
     /// Sets the location in RAM represented by the index register to the
     /// binary-coded decimal representation of the register (hundreds, tens, and
-    /// ones all in decimal).
-    fn set_index_bcd(&mut self) {
+    /// ones all in decimal). An error is returned if the digits go past the end
+    /// of RAM.
+    fn set_index_bcd(&mut self) -> Result<(), Chip8Error> {
         let vx = self.v[self.instruction.x()];
-        self.ram[self.i] = vx / 100 % 10;
-        self.ram[self.i + 1] = vx / 10 % 10;
-        self.ram[self.i + 2] = vx % 10;
+        let digits = self.index_range(3)?;
+        self.ram[digits.clone()].copy_from_slice(&[vx / 100 % 10, vx / 10 % 10, vx % 10]);
+        self.decoded.invalidate(digits);
+        Ok(())
     }
 
     /// Sets the location in RAM represented by the index register to the range of
-    /// registers from the first to the register.
-    fn set_index_reg(&mut self) {
+    /// registers from the first to the register. An error is returned if the
+    /// registers go past the end of RAM.
+    fn set_index_reg(&mut self) -> Result<(), Chip8Error> {
         let x = self.instruction.x();
-        self.ram[self.i..=self.i + x].copy_from_slice(&self.v[0x0..=x]);
-        self.i += x + 1;
+        let range = self.index_range(x + 1)?;
+        self.ram[range.clone()].copy_from_slice(&self.v[0x0..=x]);
+        self.decoded.invalidate(range);
+        self.increment_index(x);
+        Ok(())
     }
 
     /// Sets the range of registers from the first to the register to the location
-    /// in RAM represented by the index register.
-    fn set_reg_index(&mut self) {
+    /// in RAM represented by the index register. An error is returned if the
+    /// registers go past the end of RAM.
+    fn set_reg_index(&mut self) -> Result<(), Chip8Error> {
         let x = self.instruction.x();
-        self.v[0x0..=x].copy_from_slice(&self.ram[self.i..=self.i + x]);
-        self.i += x + 1;
+        let range = self.index_range(x + 1)?;
+        self.v[0x0..=x].copy_from_slice(&self.ram[range]);
+        self.increment_index(x);
+        Ok(())
+    }
+
+    /// Returns the given number of bytes of RAM from the index register on. An
+    /// error is returned (with the first address outside RAM) if they go past
+    /// the end of RAM.
+    fn index_range(&self, len: usize) -> Result<Range<usize>, Chip8Error> {
+        match self.i + len <= RAM_SIZE {
+            true => Ok(self.i..self.i + len),
+            false => Err(Chip8Error::InvalidAddress(self.i.max(RAM_SIZE))),
+        }
+    }
+
+    /// Moves the index register past the registers stored or loaded if the quirk
+    /// is enabled, wrapping around at the end of RAM.
+    fn increment_index(&mut self, x: usize) {
+        if self.quirks.memory {
+            self.i = (self.i + x + 1) % RAM_SIZE;
+        }
     }
 }
 
-/// Used to describe possibble errors caused by the emulator
-#[derive(Clone, Copy, Debug, Eq, Error, Hash, Ord, PartialEq, PartialOrd)]
+/// The decoded opcodes of the instructions at even addresses (where nearly all
+/// of them are), filled in as they're executed so that they're only decoded
+/// once, and invalidated when the RAM they're in is written. It takes a byte
+/// per instruction, and doesn't change what the emulator does, so it's ignored
+/// when comparing emulators. The RAM written since it was last asked is kept
+/// too, for other caches of the code (like the JIT's).
+#[derive(Clone)]
+struct DecodeCache {
+    opcodes: [Option<Option<Opcode>>; RAM_SIZE / 2],
+    written: Range<usize>,
+}
+
+impl DecodeCache {
+    /// Constructs an empty cache (which counts all of RAM as written).
+    const fn new() -> Self {
+        Self {
+            opcodes: [None; RAM_SIZE / 2],
+            written: 0..RAM_SIZE,
+        }
+    }
+
+    /// Returns the decoded opcode of the instruction at the address, decoding
+    /// (and caching) it if needed.
+    fn opcode(&mut self, address: usize, instruction: Instruction) -> Option<Opcode> {
+        if !address.is_multiple_of(2) {
+            return instruction.opcode();
+        }
+        *self.opcodes[address / 2].get_or_insert_with(|| instruction.opcode())
+    }
+
+    /// Forgets the opcodes of the instructions overlapping the addresses.
+    fn invalidate(&mut self, addresses: Range<usize>) {
+        let end = addresses.end.div_ceil(2).min(self.opcodes.len());
+        self.opcodes[addresses.start / 2..end].fill(None);
+        self.written = match self.written.is_empty() {
+            true => addresses,
+            false => self.written.start.min(addresses.start)..self.written.end.max(addresses.end),
+        };
+    }
+
+    /// Returns the addresses of RAM written since this was last called (as one
+    /// range covering them all), if any were.
+    #[cfg_attr(not(all(feature = "jit", target_arch = "x86_64")), allow(dead_code))]
+    fn take_written(&mut self) -> Option<Range<usize>> {
+        let written = core::mem::replace(&mut self.written, 0..0);
+        (!written.is_empty()).then_some(written)
+    }
+}
+
+impl Debug for DecodeCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        f.write_str("DecodeCache")
+    }
+}
+
+impl PartialEq for DecodeCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DecodeCache {}
+
+/// Writes the fields of a saved state in order.
+struct StateWriter<'a> {
+    state: &'a mut [u8; STATE_SIZE],
+    pos: usize,
+}
+
+impl StateWriter<'_> {
+    /// Writes the bytes after the previous ones.
+    fn write(&mut self, bytes: &[u8]) {
+        self.state[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+}
+
+/// Reads the fields of a saved state in order.
+struct StateReader<'a> {
+    state: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    /// Reads the given number of bytes after the previous ones.
+    fn read(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.state[self.pos..self.pos + len];
+        self.pos += len;
+        bytes
+    }
+
+    /// Reads a byte.
+    fn read_u8(&mut self) -> u8 {
+        self.read(1)[0]
+    }
+
+    /// Reads a big-endian 16-bit number.
+    fn read_u16(&mut self) -> u16 {
+        u16::from_be_bytes([self.read_u8(), self.read_u8()])
+    }
+}
+
+/// A copy of the machine's state (see [`Chip8::state`]), for reading or
+/// changing it all at once.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Chip8State {
+    /// The RAM (see [`RAM_SIZE`]).
+    pub ram: [u8; RAM_SIZE],
+    /// The registers, from `V0` to `VF`.
+    pub v: [u8; 0x10],
+    /// The index register.
+    pub i: usize,
+    /// The program counter.
+    pub pc: usize,
+    /// The delay timer.
+    pub dt: u8,
+    /// The sound timer.
+    pub st: u8,
+    /// The return addresses of the stack, of which the first [`sp`](Self::sp)
+    /// are in use.
+    pub stack: [usize; STACK_SIZE],
+    /// The stack pointer (how many return addresses are on the stack).
+    pub sp: usize,
+    /// The screen.
+    pub screen: Screen,
+}
+
+/// What an instruction did, recorded while tracing (see
+/// [`Chip8::set_tracing`]) for tools like profilers and coverage trackers.
+/// Everything else (like the subroutine it called) follows from the
+/// instruction.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Trace {
+    /// The address of the instruction.
+    pub pc: usize,
+    /// The instruction.
+    pub instruction: Instruction,
+    /// The index register before the instruction was executed.
+    pub i: usize,
+}
+
+impl Trace {
+    /// Returns the address of the subroutine called, if the instruction called
+    /// one (`2NNN`).
+    pub const fn call(&self) -> Option<usize> {
+        match self.instruction.nibbles() {
+            (0x2, _, _, _) => Some(self.instruction.nnn()),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the instruction returned from a subroutine (`00EE`).
+    pub const fn returned(&self) -> bool {
+        matches!(self.instruction.nibbles(), (0x0, 0x0, 0xE, 0xE))
+    }
+
+    /// Returns whether the instruction drew a sprite (`DXYN`).
+    pub const fn drew(&self) -> bool {
+        matches!(self.instruction.nibbles(), (0xD, _, _, _))
+    }
+
+    /// Returns the addresses the instruction read as data: the sprite drawn
+    /// (`DXYN`) or the registers loaded (`FX65`).
+    pub const fn reads(&self) -> Range<usize> {
+        match self.instruction.nibbles() {
+            (0xD, _, _, _) => self.i..self.i + self.instruction.n(),
+            (0xF, _, 0x6, 0x5) => self.i..self.i + self.instruction.x() + 1,
+            _ => self.i..self.i,
+        }
+    }
+
+    /// Returns the addresses the instruction wrote: the binary-coded decimal
+    /// digits (`FX33`) or the registers stored (`FX55`).
+    pub const fn writes(&self) -> Range<usize> {
+        match self.instruction.nibbles() {
+            (0xF, _, 0x3, 0x3) => self.i..self.i + 3,
+            (0xF, _, 0x5, 0x5) => self.i..self.i + self.instruction.x() + 1,
+            _ => self.i..self.i,
+        }
+    }
+}
+
+/// The behaviors that differ between CHIP-8 interpreters, which ROMs may rely
+/// on. The defaults match the original COSMAC VIP interpreter.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Quirks {
+    /// Whether the bitwise operations (AND, OR, and XOR) reset the flag
+    /// register.
+    pub vf_reset: bool,
+    /// Whether storing and loading registers moves the index register past
+    /// them.
+    pub memory: bool,
+    /// Whether the shift instructions shift the first register in place instead
+    /// of the second one.
+    pub shifting: bool,
+    /// Whether `BXNN` jumps to `XNN` plus the register `X` instead of `NNN` plus
+    /// the first register.
+    pub jumping: bool,
+    /// Whether sprites wrap around the edges of the screen instead of being
+    /// clipped.
+    pub wrapping: bool,
+}
+
+impl Quirks {
+    /// The quirks of the original COSMAC VIP interpreter.
+    pub const CHIP8: Self = Self {
+        vf_reset: true,
+        memory: true,
+        shifting: false,
+        jumping: false,
+        wrapping: false,
+    };
+
+    /// The quirks of SUPER-CHIP 1.1.
+    pub const SCHIP: Self = Self {
+        vf_reset: false,
+        memory: false,
+        shifting: true,
+        jumping: true,
+        wrapping: false,
+    };
+
+    /// The quirks of Octo's XO-CHIP.
+    pub const XOCHIP: Self = Self {
+        vf_reset: false,
+        memory: true,
+        shifting: false,
+        jumping: false,
+        wrapping: true,
+    };
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::CHIP8
+    }
+}
+
+/// The CHIP-8 platforms whose quirks are known.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Platform {
+    #[default]
+    Chip8,
+    Schip,
+    XoChip,
+}
+
+impl Platform {
+    /// Returns the quirks of the platform.
+    pub const fn quirks(&self) -> Quirks {
+        match self {
+            Self::Chip8 => Quirks::CHIP8,
+            Self::Schip => Quirks::SCHIP,
+            Self::XoChip => Quirks::XOCHIP,
+        }
+    }
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Chip8 => write!(f, "CHIP-8"),
+            Self::Schip => write!(f, "SUPER-CHIP"),
+            Self::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = Chip8Error;
+
+    /// Parses a platform's name, ignoring case and dashes (so `SUPER-CHIP`,
+    /// `superchip`, and `schip` are all the same).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is = |name: &str| {
+            s.bytes()
+                .filter(|byte| *byte != b'-')
+                .map(|byte| byte.to_ascii_lowercase())
+                .eq(name.bytes())
+        };
+        if is("chip8") {
+            Ok(Self::Chip8)
+        } else if is("schip") || is("superchip") {
+            Ok(Self::Schip)
+        } else if is("xochip") {
+            Ok(Self::XoChip)
+        } else {
+            Err(Chip8Error::UnknownPlatform)
+        }
+    }
+}
+
+/// Used to describe possibble errors caused by the emulator. It's displayed by
+/// hand rather than with [`thiserror`](https://docs.rs/thiserror), which needs
+/// `std`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Chip8Error {
-    #[error("ROM size exceeds the amount of RAM provided by the CHIP-8 emulator by {0} bytes.")]
     RomTooBig(usize),
-    #[error("There aren't any more instructions to run.")]
     NoMoreInstructions,
-    #[error("Instruction opcode {0} at {1} is unknown.")]
     UnknownInstruction(Instruction, usize),
+    StackOverflow(usize),
+    StackUnderflow(usize),
+    InvalidState,
+    InvalidAddress(usize),
+    InvalidRegister(usize),
+    UnknownPlatform,
+}
+
+impl Display for Chip8Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::RomTooBig(exceed) => write!(
+                f,
+                "ROM size exceeds the amount of RAM provided by the CHIP-8 emulator by {} bytes.",
+                exceed
+            ),
+            Self::NoMoreInstructions => write!(f, "There aren't any more instructions to run."),
+            Self::UnknownInstruction(instruction, pc) => {
+                write!(
+                    f,
+                    "Instruction opcode {} at {} is unknown.",
+                    instruction, pc
+                )
+            }
+            Self::StackOverflow(pc) => write!(f, "Subroutine call at {} overflows the stack.", pc),
+            Self::StackUnderflow(pc) => {
+                write!(
+                    f,
+                    "Return at {} doesn't have a subroutine to return from.",
+                    pc
+                )
+            }
+            Self::InvalidState => write!(f, "Saved state is invalid or from another version."),
+            Self::InvalidAddress(address) => {
+                write!(f, "Address {:#05X} is outside the emulator's RAM.", address)
+            }
+            Self::InvalidRegister(x) => write!(f, "There isn't a register V{:X}.", x),
+            Self::UnknownPlatform => write!(f, "Platform doesn't exist."),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Chip8Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the ROM until it fails (or runs a frame's worth of instructions).
+    fn run(rom: &[u8]) -> Result<(usize, bool), Chip8Error> {
+        Chip8::new(rom).unwrap().run(Keys::new(), 10)
+    }
+
+    /// Runs every instruction of the ROM once with the quirks, returning the
+    /// emulator.
+    fn run_quirks(rom: &[u8], quirks: Quirks) -> Chip8 {
+        let mut chip8 = Chip8::new(rom).unwrap();
+        chip8.set_quirks(quirks);
+        let cycles = rom.len() / 2;
+        assert_eq!(chip8.run(Keys::new(), cycles).unwrap().0, cycles);
+        chip8
+    }
+
+    #[test]
+    fn index_accesses_past_the_end_of_ram_fail() {
+        let sprite = [0xAF, 0xFF, 0xD0, 0x0F];
+        assert_eq!(run(&sprite), Err(Chip8Error::InvalidAddress(RAM_SIZE)));
+        let bcd = [0xAF, 0xFE, 0xF0, 0x33];
+        assert_eq!(run(&bcd), Err(Chip8Error::InvalidAddress(RAM_SIZE)));
+        let store = [0xAF, 0xFF, 0xF0, 0x1E, 0xF0, 0x1E, 0xF5, 0x55];
+        assert_eq!(run(&store), Err(Chip8Error::InvalidAddress(RAM_SIZE)));
+        let load = [0xAF, 0xFF, 0xF1, 0x65];
+        assert_eq!(run(&load), Err(Chip8Error::InvalidAddress(RAM_SIZE)));
+        let last = [0xAF, 0xFF, 0xD0, 0x01, 0xF0, 0x65];
+        assert!(run(&last).is_ok());
+    }
+
+    #[test]
+    fn index_wraps_around_ram() {
+        let mut chip8 = Chip8::new(&[0x60, 0xFF, 0xAF, 0xFF, 0xF0, 0x1E]).unwrap();
+        chip8.run(Keys::new(), 3).unwrap();
+        assert_eq!(chip8.index(), 0xFE);
+    }
+
+    #[test]
+    fn shifting_quirk() {
+        // V0 = 2 and V1 = 0x81, then V0 is shifted (8016 and 801E).
+        for (opcode, on, off) in [(0x16, (0x01, 0), (0x40, 1)), (0x1E, (0x04, 0), (0x02, 1))] {
+            let rom = [0x60, 0x02, 0x61, 0x81, 0x80, opcode];
+            for (enabled, (v0, vf)) in [(true, on), (false, off)] {
+                let quirks = Quirks {
+                    shifting: enabled,
+                    ..Quirks::CHIP8
+                };
+                let v = run_quirks(&rom, quirks).state().v;
+                assert_eq!((v[0x0], v[0xF]), (v0, vf), "8XY{:X}", opcode & 0xF);
+            }
+        }
+    }
+
+    #[test]
+    fn vf_reset_quirk() {
+        // VF = 5, V0 = 3, and V1 = 6, then V0 is ORed, ANDed, or XORed.
+        for (opcode, v0) in [(0x11, 0x07), (0x12, 0x02), (0x13, 0x05)] {
+            let rom = [0x6F, 0x05, 0x60, 0x03, 0x61, 0x06, 0x80, opcode];
+            for (enabled, vf) in [(true, 0), (false, 5)] {
+                let quirks = Quirks {
+                    vf_reset: enabled,
+                    ..Quirks::CHIP8
+                };
+                let v = run_quirks(&rom, quirks).state().v;
+                assert_eq!((v[0x0], v[0xF]), (v0, vf), "8XY{:X}", opcode & 0xF);
+            }
+        }
+    }
+
+    #[test]
+    fn memory_quirk() {
+        let store = [0xA3, 0x00, 0x60, 0x01, 0x61, 0x02, 0xF1, 0x55];
+        let load = [0xA3, 0x00, 0xF1, 0x65];
+        for rom in [&store[..], &load[..]] {
+            for (enabled, i) in [(true, 0x302), (false, 0x300)] {
+                let quirks = Quirks {
+                    memory: enabled,
+                    ..Quirks::CHIP8
+                };
+                assert_eq!(run_quirks(rom, quirks).state().i, i);
+            }
+        }
+    }
+
+    #[test]
+    fn jumping_quirk() {
+        // V0 = 4 and V2 = 6, then B210 jumps.
+        let rom = [0x60, 0x04, 0x62, 0x06, 0xB2, 0x10];
+        for (enabled, pc) in [(true, 0x216), (false, 0x214)] {
+            let quirks = Quirks {
+                jumping: enabled,
+                ..Quirks::CHIP8
+            };
+            assert_eq!(run_quirks(&rom, quirks).state().pc, pc);
+        }
+    }
+
+    #[test]
+    fn wrapping_quirk() {
+        // Draws a line 8 pixels wide 4 pixels from the right edge.
+        let rom = [0x60, 0x3C, 0x61, 0x00, 0xA2, 0x08, 0xD0, 0x11, 0xFF];
+        for enabled in [true, false] {
+            let quirks = Quirks {
+                wrapping: enabled,
+                ..Quirks::CHIP8
+            };
+            let screen = run_quirks(&rom, quirks).state().screen;
+            assert!((60..64).all(|x| screen.pixel(x, 0)));
+            assert!((0..4).all(|x| screen.pixel(x, 0) == enabled));
+        }
+    }
+
+    #[test]
+    fn platforms_parse() {
+        assert_eq!("CHIP-8".parse(), Ok(Platform::Chip8));
+        assert_eq!("super-chip".parse(), Ok(Platform::Schip));
+        assert_eq!("schip".parse(), Ok(Platform::Schip));
+        assert_eq!("XO-CHIP".parse(), Ok(Platform::XoChip));
+        assert_eq!("vip".parse::<Platform>(), Err(Chip8Error::UnknownPlatform));
+    }
+
+    #[test]
+    fn pokes_into_executed_code_are_seen() {
+        let mut chip8 = Chip8::new(&[0x60, 0x01, 0x12, 0x00]).unwrap();
+        chip8.run(Keys::new(), 4).unwrap();
+        assert_eq!(chip8.state().v[0], 0x01);
+        chip8.poke(ROM_LOC + 1, 0x07).unwrap();
+        chip8.run(Keys::new(), 2).unwrap();
+        assert_eq!(chip8.state().v[0], 0x07);
+    }
+
+    #[test]
+    fn states_round_trip() {
+        // Sets the timers, draws a digit, and loops in a subroutine.
+        let rom = [
+            0x61, 0x05, 0xF1, 0x15, 0xF1, 0x18, 0xF1, 0x29, 0xD1, 0x15, 0x22, 0x0E, 0x12, 0x0C,
+            0x12, 0x0E,
+        ];
+        let mut chip8 = Chip8::new(&rom).unwrap();
+        // Drawing ends the first run.
+        assert_eq!(chip8.run(Keys::new(), 10), Ok((5, true)));
+        chip8.run(Keys::new(), 10).unwrap();
+        let state = chip8.state();
+        assert_eq!(state.sp, 1);
+
+        let mut other = Chip8::new(&[0x12, 0x00]).unwrap();
+        other.set_state(&state).unwrap();
+        assert_eq!(other.state(), state);
+        // The state doesn't include the seed, which snapshots do.
+        other.set_seed(chip8.seed());
+        assert_eq!(other.save_state(), chip8.save_state());
+        chip8.run(Keys::new(), 10).unwrap();
+        other.run(Keys::new(), 10).unwrap();
+        assert_eq!(other.state(), chip8.state());
+
+        let invalid = Chip8State {
+            pc: RAM_SIZE,
+            ..state
+        };
+        assert_eq!(other.set_state(&invalid), Err(Chip8Error::InvalidState));
+    }
+
+    #[test]
+    fn states_outside_ram_are_rejected() {
+        let mut chip8 = Chip8::new(&[0x12, 0x00]).unwrap();
+        let mut state = chip8.save_state();
+        assert!(chip8.load_state(&state).is_ok());
+        let i = STATE_MAGIC.len() + RAM_SIZE + 0x10;
+        state[i..i + 2].copy_from_slice(&(RAM_SIZE as u16).to_be_bytes());
+        assert_eq!(chip8.load_state(&state), Err(Chip8Error::InvalidState));
+        let mut state = chip8.save_state();
+        state[i + 2..i + 4].copy_from_slice(&0xFFFF_u16.to_be_bytes());
+        assert_eq!(chip8.load_state(&state), Err(Chip8Error::InvalidState));
+    }
 }
@@ -0,0 +1,598 @@
+//! Provides a [`Jit`] that runs a [`Chip8`] by translating its code into native
+//! x86-64 code a block at a time, which is faster than interpreting it for long
+//! runs (like fuzzing and training). The instructions that touch registers,
+//! timers, and keys are translated, along with jumps (which are followed) and
+//! drawing (which calls back into the emulator); the rest (like calling
+//! subroutines and storing registers in RAM) end blocks and are run by the
+//! interpreter. Skips branch within a block, and jumps back into it loop
+//! without leaving the native code (for as long as the cycles allow), so the
+//! loops games wait in cost next to nothing. Blocks are cached by address, and
+//! thrown away when RAM they were translated from is written. Errors are
+//! represented by [`JitError`].
+
+use super::{Chip8, Chip8Error, Quirks, RAM_SIZE};
+use crate::{
+    instruction::{Instruction, Opcode},
+    keys::{self, Keys},
+};
+use core::mem::offset_of;
+use memmap2::{Mmap, MmapMut};
+use std::{io::Error as IoError, ops::Range};
+use thiserror::Error;
+
+/// How many bytes of native code are kept before the cache is flushed.
+const CODE_SIZE: usize = 1 << 20;
+
+/// How many instructions a block holds at most.
+const MAX_BLOCK: usize = 64;
+
+/// Where the registers are in an emulator.
+const V: usize = offset_of!(Chip8, v);
+
+/// Where the index register is in an emulator.
+const I: usize = offset_of!(Chip8, i);
+
+/// Where the program counter is in an emulator.
+const PC: usize = offset_of!(Chip8, pc);
+
+/// Where the delay timer is in an emulator.
+const DT: usize = offset_of!(Chip8, dt);
+
+/// Where the sound timer is in an emulator.
+const ST: usize = offset_of!(Chip8, st);
+
+/// Where the bitmask of held keys is in an emulator.
+const KEYS: usize = offset_of!(Chip8, keys) + keys::RAW_OFFSET;
+
+// The index register wraps around RAM by masking.
+const _: () = assert!(RAM_SIZE.is_power_of_two());
+
+/// The 8-bit registers the native code uses.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Reg {
+    Al = 0,
+    Cl = 1,
+    Dl = 2,
+}
+
+/// A translated block: how many instructions there are in it, and where its
+/// native code is (or nothing if it's left to the interpreter).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Block {
+    instructions: usize,
+    code: Option<usize>,
+}
+
+/// What a block returns: how many instructions it executed, and what to do
+/// next.
+#[repr(C)]
+struct Exit {
+    executed: usize,
+    next: Next,
+}
+
+/// What to do after a block.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(usize)]
+enum Next {
+    /// Carry on at the program counter.
+    Continue = 0,
+    /// Return, since the last instruction drew.
+    Drew = 1,
+    /// Interpret the instruction at the program counter, which the block
+    /// couldn't execute (so that it fails like it would otherwise).
+    Interpret = 2,
+}
+
+/// Runs an emulator with native code (see the [module](self) docs), kept in
+/// memory that's only ever writable or executable (never both). A JIT
+/// should only ever run the one emulator, since its blocks are translated from
+/// that emulator's RAM.
+pub struct Jit {
+    code: Option<Mmap>,
+    len: usize,
+    blocks: Vec<Option<Block>>,
+    covered: Vec<bool>,
+    quirks: Option<Quirks>,
+}
+
+impl Jit {
+    /// Attempts to set aside memory for native code.
+    pub fn new() -> Result<Self, JitError> {
+        Ok(Self {
+            code: Some(MmapMut::map_anon(CODE_SIZE)?.make_exec()?),
+            len: 0,
+            blocks: vec![None; RAM_SIZE],
+            covered: vec![false; RAM_SIZE],
+            quirks: None,
+        })
+    }
+
+    /// Does the same as [`Chip8::run`] (with exactly the same results), but
+    /// with native code wherever it can. While tracing, the emulator is only
+    /// interpreted.
+    pub fn run(
+        &mut self,
+        chip8: &mut Chip8,
+        keys: Keys,
+        cycles: usize,
+    ) -> Result<(usize, bool), Chip8Error> {
+        if chip8.tracing {
+            return chip8.run(keys, cycles);
+        }
+        // The quirks are translated into the code.
+        if self.quirks != Some(chip8.quirks) {
+            self.flush();
+            self.quirks = Some(chip8.quirks);
+        }
+        chip8.keys = keys;
+        let mut executed = 0;
+        while executed < cycles {
+            if let Some(written) = chip8.decoded.take_written() {
+                self.invalidate(written);
+            }
+            // Past the end of RAM, the interpreter fails.
+            let block = match self.blocks.get(chip8.pc) {
+                Some(Some(block)) => Some(*block),
+                Some(None) => Some(self.translate(chip8)),
+                None => None,
+            };
+            let next = match (block, self.code.as_ref()) {
+                (
+                    Some(Block {
+                        code: Some(offset),
+                        instructions,
+                        ..
+                    }),
+                    Some(code),
+                ) if instructions <= cycles - executed => {
+                    // SAFETY: The block was written by `translate` as a function
+                    // taking the emulator, which it only reads and writes within
+                    // (or passes to `draw_sprite`), and how many instructions it
+                    // may execute (at least as many as it holds).
+                    let exit = unsafe {
+                        let block: unsafe extern "sysv64" fn(*mut Chip8, usize) -> Exit =
+                            core::mem::transmute(code.as_ptr().add(offset));
+                        block(chip8, cycles - executed)
+                    };
+                    executed += exit.executed;
+                    exit.next
+                }
+                _ => Next::Interpret,
+            };
+            match next {
+                Next::Continue => chip8.keys.reset_last_pressed(),
+                Next::Drew => return Ok((executed, true)),
+                Next::Interpret => {
+                    // Interpreting is always right, so the blocks left to the
+                    // interpreter are run straight through.
+                    let blocks = &self.blocks;
+                    let (interpreted, drew) = chip8.run_until(cycles - executed, |pc| {
+                        !matches!(blocks.get(pc), Some(Some(Block { code: None, .. })))
+                    })?;
+                    executed += interpreted;
+                    if drew {
+                        return Ok((executed, true));
+                    }
+                }
+            }
+        }
+        Ok((executed, false))
+    }
+
+    /// Throws away every block.
+    fn flush(&mut self) {
+        self.blocks.fill(None);
+        self.covered.fill(false);
+        self.len = 0;
+    }
+
+    /// Throws away every block if any instruction translated was written.
+    /// Programs rarely write over their own code, so it isn't worth keeping
+    /// track of which blocks hold what.
+    fn invalidate(&mut self, written: Range<usize>) {
+        let end = written.end.min(RAM_SIZE);
+        if self.covered[written.start.min(end)..end].contains(&true) {
+            self.flush();
+        }
+    }
+
+    /// Translates the block at the program counter and caches it. If the first
+    /// instruction can't be translated (or the code couldn't be written), the
+    /// block is left to the interpreter.
+    #[cold]
+    fn translate(&mut self, chip8: &Chip8) -> Block {
+        let quirks = chip8.quirks;
+        let mut asm = Assembler::default();
+        // Where the code of every instruction translated starts (by its
+        // address), the skips waiting for the instruction they skip to, and the
+        // loops waiting for how many instructions there are.
+        let mut labels: Vec<(usize, usize)> = Vec::new();
+        let mut skips: Vec<(usize, usize)> = Vec::new();
+        let mut loops = Vec::new();
+        // The address of the instruction to translate next, if the path through
+        // the block goes on.
+        let mut next = Some(chip8.pc);
+        asm.begin();
+        // Once a path ends, translation goes on where the skips left over skip
+        // to, until there are none.
+        while let Some(pc) = next.or(skips.first().map(|&(target, _)| target)) {
+            next = None;
+            skips.retain(|&(target, fixup)| match target == pc {
+                true => {
+                    asm.patch(fixup);
+                    false
+                }
+                false => true,
+            });
+            // Going back to an instruction already translated loops.
+            if let Some(label) = label(&labels, pc) {
+                loops.push(asm.loop_to(label));
+                asm.exit(pc, Next::Continue);
+                continue;
+            }
+            let fetched = chip8.ram.get(pc..pc + 2);
+            let Some(&[high, low]) = fetched.filter(|_| labels.len() < MAX_BLOCK) else {
+                asm.exit(pc, Next::Continue);
+                continue;
+            };
+            let raw = u16::from_be_bytes([high, low]);
+            let instruction = Instruction::new(raw);
+            let Some(opcode) = instruction.opcode().filter(|opcode| translated(*opcode)) else {
+                asm.exit(pc, Next::Continue);
+                continue;
+            };
+            labels.push((pc, asm.code.len()));
+            asm.count();
+            next = Some(pc + 2);
+            let (x, y, nn, nnn) = (
+                instruction.x(),
+                instruction.y(),
+                instruction.nn(),
+                instruction.nnn(),
+            );
+            match opcode {
+                Opcode::Nop => (),
+                Opcode::JumpAddr => next = Some(nnn),
+                Opcode::DrawSprite => {
+                    asm.draw_sprite(raw, pc);
+                    next = None;
+                }
+                Opcode::SkipEqByte | Opcode::SkipNotByte => {
+                    // cmp byte [rdi + V + x], nn
+                    asm.modrm_disp(&[0x80], 7, V + x);
+                    asm.bytes(&[nn]);
+                    skips.push((pc + 4, asm.jump(Cond::equal(opcode == Opcode::SkipEqByte))));
+                }
+                Opcode::SkipEqReg | Opcode::SkipNotReg => {
+                    asm.load(Reg::Al, V + x);
+                    // cmp al, [rdi + V + y]
+                    asm.modrm_disp(&[0x3A], Reg::Al as u8, V + y);
+                    skips.push((pc + 4, asm.jump(Cond::equal(opcode == Opcode::SkipEqReg))));
+                }
+                Opcode::SkipEqKey | Opcode::SkipNotKey => {
+                    // movzx ecx, byte [rdi + V + x]; and ecx, 0xF;
+                    // movzx eax, word [rdi + KEYS]; bt eax, ecx
+                    asm.modrm_disp(&[0x0F, 0xB6], Reg::Cl as u8, V + x);
+                    asm.bytes(&[0x83, 0xE1, 0x0F]);
+                    asm.modrm_disp(&[0x0F, 0xB7], Reg::Al as u8, KEYS);
+                    asm.bytes(&[0x0F, 0xA3, 0xC8]);
+                    let cond = match opcode {
+                        Opcode::SkipEqKey => Cond::Carry,
+                        _ => Cond::NotCarry,
+                    };
+                    skips.push((pc + 4, asm.jump(cond)));
+                }
+                Opcode::SetRegByte => {
+                    // mov byte [rdi + V + x], nn
+                    asm.modrm_disp(&[0xC6], 0, V + x);
+                    asm.bytes(&[nn]);
+                }
+                Opcode::AddByte => {
+                    // add byte [rdi + V + x], nn
+                    asm.modrm_disp(&[0x80], 0, V + x);
+                    asm.bytes(&[nn]);
+                }
+                Opcode::SetRegReg => {
+                    asm.load(Reg::Al, V + y);
+                    asm.store(Reg::Al, V + x);
+                }
+                Opcode::OrReg | Opcode::AndReg | Opcode::XorReg => {
+                    asm.load(Reg::Al, V + x);
+                    asm.load(Reg::Cl, V + y);
+                    // or/and/xor al, cl
+                    let op = match opcode {
+                        Opcode::OrReg => 0x08,
+                        Opcode::AndReg => 0x20,
+                        _ => 0x30,
+                    };
+                    asm.bytes(&[op, 0xC8]);
+                    asm.store(Reg::Al, V + x);
+                    if quirks.vf_reset {
+                        // mov byte [rdi + V + 0xF], 0
+                        asm.modrm_disp(&[0xC6], 0, V + 0xF);
+                        asm.bytes(&[0]);
+                    }
+                }
+                Opcode::AddReg | Opcode::SubReg => {
+                    asm.load(Reg::Al, V + x);
+                    asm.load(Reg::Cl, V + y);
+                    match opcode {
+                        // add al, cl; setc dl
+                        Opcode::AddReg => asm.bytes(&[0x00, 0xC8, 0x0F, 0x92, 0xC2]),
+                        // sub al, cl; setnc dl
+                        _ => asm.bytes(&[0x28, 0xC8, 0x0F, 0x93, 0xC2]),
+                    }
+                    asm.store(Reg::Al, V + x);
+                    asm.store(Reg::Dl, V + 0xF);
+                }
+                Opcode::RevSubReg => {
+                    asm.load(Reg::Al, V + x);
+                    asm.load(Reg::Cl, V + y);
+                    // sub cl, al; setnc dl
+                    asm.bytes(&[0x28, 0xC1, 0x0F, 0x93, 0xC2]);
+                    asm.store(Reg::Cl, V + x);
+                    asm.store(Reg::Dl, V + 0xF);
+                }
+                Opcode::ShrReg | Opcode::ShlReg => {
+                    let source = if quirks.shifting { x } else { y };
+                    asm.load(Reg::Al, V + source);
+                    // mov dl, al
+                    asm.bytes(&[0x88, 0xC2]);
+                    match opcode {
+                        // and dl, 1; shr al, 1
+                        Opcode::ShrReg => asm.bytes(&[0x80, 0xE2, 0x01, 0xD0, 0xE8]),
+                        // shr dl, 7; shl al, 1
+                        _ => asm.bytes(&[0xC0, 0xEA, 0x07, 0xD0, 0xE0]),
+                    }
+                    asm.store(Reg::Al, V + x);
+                    asm.store(Reg::Dl, V + 0xF);
+                }
+                Opcode::SetIndexAddr => {
+                    // mov qword [rdi + I], nnn
+                    asm.modrm_disp(&[0x48, 0xC7], 0, I);
+                    asm.bytes(&(nnn as u32).to_le_bytes());
+                }
+                Opcode::SetRegDelay => {
+                    asm.load(Reg::Al, DT);
+                    asm.store(Reg::Al, V + x);
+                }
+                Opcode::SetDelayReg | Opcode::SetSoundReg => {
+                    asm.load(Reg::Al, V + x);
+                    asm.store(
+                        Reg::Al,
+                        if opcode == Opcode::SetDelayReg {
+                            DT
+                        } else {
+                            ST
+                        },
+                    );
+                }
+                Opcode::AddIndexReg => {
+                    // movzx eax, byte [rdi + V + x]; add [rdi + I], rax;
+                    // and qword [rdi + I], RAM_SIZE - 1 (wrapping around RAM)
+                    asm.modrm_disp(&[0x0F, 0xB6], 0, V + x);
+                    asm.modrm_disp(&[0x48, 0x01], 0, I);
+                    asm.modrm_disp(&[0x48, 0x81], 4, I);
+                    asm.bytes(&(RAM_SIZE as u32 - 1).to_le_bytes());
+                }
+                Opcode::SetIndexChar => {
+                    // movzx eax, byte [rdi + V + x]; lea eax, [rax + rax * 4];
+                    // mov [rdi + I], rax
+                    asm.modrm_disp(&[0x0F, 0xB6], 0, V + x);
+                    asm.bytes(&[0x8D, 0x04, 0x80]);
+                    asm.modrm_disp(&[0x48, 0x89], 0, I);
+                }
+                _ => unreachable!("Only translated opcodes get here."),
+            }
+        }
+        let instructions = labels.len();
+        for &fixup in &loops {
+            asm.code[fixup] = instructions as u8;
+        }
+        // A lone instruction (that doesn't loop) is quicker to interpret than to
+        // call into.
+        let code = match (instructions, loops.is_empty()) {
+            (0, _) | (1, true) => None,
+            _ => self.write(&asm.code),
+        };
+        if code.is_some() {
+            for &(pc, _) in &labels {
+                self.covered[pc..pc + 2].fill(true);
+            }
+        }
+        let block = Block { instructions, code };
+        self.blocks[chip8.pc] = Some(block);
+        block
+    }
+
+    /// Attempts to write the native code of a block, returning where it is.
+    /// Once the memory set aside is full, every block is thrown away first.
+    fn write(&mut self, code: &[u8]) -> Option<usize> {
+        if self.len + code.len() > CODE_SIZE {
+            self.flush();
+        }
+        // The memory is only made writable while the code is written.
+        let mut writable = self.code.take()?.make_mut().ok()?;
+        let offset = self.len;
+        writable[offset..offset + code.len()].copy_from_slice(code);
+        self.len += code.len();
+        self.code = Some(writable.make_exec().ok()?);
+        Some(offset)
+    }
+}
+
+/// Draws the sprite of the instruction for the native code, returning whether
+/// it could be (if not, the interpreter fails on it instead).
+///
+/// # Safety
+///
+/// The emulator has to be valid and not otherwise borrowed.
+unsafe extern "sysv64" fn draw_sprite(chip8: *mut Chip8, instruction: u16) -> bool {
+    // SAFETY: The native code passes on the emulator it was called with.
+    let chip8 = unsafe { &mut *chip8 };
+    chip8.instruction = Instruction::new(instruction);
+    chip8.draw_sprite().is_ok()
+}
+
+/// Returns where the code of the instruction at the address starts, if it's
+/// been translated.
+fn label(labels: &[(usize, usize)], pc: usize) -> Option<usize> {
+    labels
+        .iter()
+        .find(|&&(address, _)| address == pc)
+        .map(|&(_, label)| label)
+}
+
+/// Returns whether the opcode is translated into native code (rather than left
+/// to the interpreter).
+const fn translated(opcode: Opcode) -> bool {
+    !matches!(
+        opcode,
+        Opcode::ClearScreen
+            | Opcode::SubroutineReturn
+            | Opcode::CallSubroutine
+            | Opcode::JumpAddAddr
+            | Opcode::RandAndByte
+            | Opcode::SetRegKey
+            | Opcode::SetIndexBcd
+            | Opcode::SetIndexReg
+            | Opcode::SetRegIndex
+    )
+}
+
+/// The conditions the native code branches on.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Cond {
+    Carry = 0x2,
+    NotCarry = 0x3,
+    Equal = 0x4,
+    NotEqual = 0x5,
+    BelowOrEqual = 0x6,
+}
+
+impl Cond {
+    /// Returns the condition of a comparison being equal (or not).
+    const fn equal(equal: bool) -> Self {
+        match equal {
+            true => Self::Equal,
+            false => Self::NotEqual,
+        }
+    }
+}
+
+/// Writes the native code of a block, which takes the emulator in `rdi` and
+/// how many instructions it may execute in `rsi`, and returns an [`Exit`] with
+/// how many it did (counted in `r8`).
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    /// Writes bytes as they are.
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    /// Writes an opcode whose operand is memory in the emulator (`[rdi +
+    /// offset]`), with the register (or opcode extension) given.
+    fn modrm_disp(&mut self, opcode: &[u8], reg: u8, offset: usize) {
+        self.bytes(opcode);
+        self.bytes(&[0x80 | (reg << 3) | 7]);
+        self.bytes(&(offset as u32).to_le_bytes());
+    }
+
+    /// Loads a byte of the emulator into the register.
+    fn load(&mut self, reg: Reg, offset: usize) {
+        self.modrm_disp(&[0x8A], reg as u8, offset);
+    }
+
+    /// Stores the register into a byte of the emulator.
+    fn store(&mut self, reg: Reg, offset: usize) {
+        self.modrm_disp(&[0x88], reg as u8, offset);
+    }
+
+    /// Starts counting the instructions executed.
+    fn begin(&mut self) {
+        // xor r8d, r8d
+        self.bytes(&[0x45, 0x31, 0xC0]);
+    }
+
+    /// Counts an instruction as executed.
+    fn count(&mut self) {
+        // inc r8
+        self.bytes(&[0x49, 0xFF, 0xC0]);
+    }
+
+    /// Sets the program counter and returns how many instructions were
+    /// executed, along with what to do next.
+    fn exit(&mut self, pc: usize, next: Next) {
+        // mov qword [rdi + PC], pc; mov rax, r8; mov edx, next; ret
+        self.modrm_disp(&[0x48, 0xC7], 0, PC);
+        self.bytes(&(pc as u32).to_le_bytes());
+        self.bytes(&[0x4C, 0x89, 0xC0, 0xBA]);
+        self.bytes(&(next as u32).to_le_bytes());
+        self.bytes(&[0xC3]);
+    }
+
+    /// Draws the sprite of the instruction at the address by calling
+    /// [`draw_sprite`], then returns. If it can't be drawn, it's left
+    /// uncounted for the interpreter.
+    fn draw_sprite(&mut self, instruction: u16, pc: usize) {
+        let draw_sprite: unsafe extern "sysv64" fn(*mut Chip8, u16) -> bool = draw_sprite;
+        // push rdi; push rsi; push r8 (which also aligns the stack)
+        self.bytes(&[0x57, 0x56, 0x41, 0x50]);
+        // mov esi, instruction; mov rax, draw_sprite; call rax
+        self.bytes(&[0xBE]);
+        self.bytes(&(instruction as u32).to_le_bytes());
+        self.bytes(&[0x48, 0xB8]);
+        self.bytes(&(draw_sprite as usize as u64).to_le_bytes());
+        self.bytes(&[0xFF, 0xD0]);
+        // pop r8; pop rsi; pop rdi; test al, al
+        self.bytes(&[0x41, 0x58, 0x5E, 0x5F, 0x84, 0xC0]);
+        let failed = self.jump(Cond::Equal);
+        self.exit(pc + 2, Next::Drew);
+        self.patch(failed);
+        // dec r8
+        self.bytes(&[0x49, 0xFF, 0xC8]);
+        self.exit(pc, Next::Interpret);
+    }
+
+    /// Branches forward if the condition holds, returning where the distance
+    /// goes (see [`patch`](Self::patch)).
+    fn jump(&mut self, cond: Cond) -> usize {
+        // jcc rel32
+        self.bytes(&[0x0F, 0x80 | cond as u8]);
+        self.bytes(&[0; 4]);
+        self.code.len() - 4
+    }
+
+    /// Makes a forward branch land on the code written next.
+    fn patch(&mut self, fixup: usize) {
+        let distance = (self.code.len() - (fixup + 4)) as u32;
+        self.code[fixup..fixup + 4].copy_from_slice(&distance.to_le_bytes());
+    }
+
+    /// Branches back to the code at the label if there's room for another pass
+    /// through the block, returning where the block's length in instructions
+    /// goes (once it's known).
+    fn loop_to(&mut self, label: usize) -> usize {
+        // lea r9, [r8 + instructions]; cmp r9, rsi
+        self.bytes(&[0x4D, 0x8D, 0x48, 0]);
+        let fixup = self.code.len() - 1;
+        self.bytes(&[0x49, 0x39, 0xF1]);
+        // jbe rel32
+        self.bytes(&[0x0F, 0x80 | Cond::BelowOrEqual as u8]);
+        let distance = label.wrapping_sub(self.code.len() + 4) as u32;
+        self.bytes(&distance.to_le_bytes());
+        fixup
+    }
+}
+
+/// Used to describe possible errors when setting up the JIT.
+#[derive(Debug, Error)]
+pub enum JitError {
+    #[error("{0}.")]
+    Io(#[from] IoError),
+}
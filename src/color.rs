@@ -0,0 +1,452 @@
+//! Provides color parsing through [`parse_color`] and multi-color palettes
+//! through [`Palette`]. Colors can be written as `#RRGGBB`, `#RGB`,
+//! `rgb(R, G, B)`, or a CSS color name, and palettes can be one of the
+//! [`PalettePreset`]s or a list of colors.
+
+use serde::Deserialize;
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// A 24-bit RGB color.
+pub type Rgb = [u8; 3];
+
+/// Attempts to parse a color written as `#RRGGBB`, `#RGB`, `rgb(R, G, B)`
+/// (with decimal components), or a CSS color name (case insensitive).
+pub fn parse_color(color: &str) -> Result<Rgb, ColorError> {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(|| ColorError::Color(color.to_string()));
+    }
+    let lowercase = color.to_lowercase();
+    if let Some(components) = lowercase
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        return parse_components(components).ok_or_else(|| ColorError::Color(color.to_string()));
+    }
+    NAMED_COLORS
+        .binary_search_by_key(&lowercase.as_str(), |(name, _)| name)
+        .map(|i| NAMED_COLORS[i].1)
+        .map_err(|_| ColorError::Color(color.to_string()))
+}
+
+/// Parses the digits of a `#RRGGBB` or `#RGB` color.
+fn parse_hex(hex: &str) -> Option<Rgb> {
+    if !hex.chars().all(|digit| digit.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = match hex.len() {
+        3 => hex.chars().flat_map(|digit| [digit, digit]).collect(),
+        6 => hex.to_string(),
+        _ => return None,
+    };
+    let mut rgb = [0; 3];
+    for (i, component) in rgb.iter_mut().enumerate() {
+        *component = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(rgb)
+}
+
+/// Parses the comma separated components of an `rgb(R, G, B)` color.
+fn parse_components(components: &str) -> Option<Rgb> {
+    let components = components
+        .split(',')
+        .map(|component| component.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    components.try_into().ok()
+}
+
+/// A list of colors used to draw the screen: the first is the background and
+/// the second the foreground. XO-CHIP uses four colors (one for each
+/// combination of its two bitplanes), and some extensions like CHIP-8X use
+/// sixteen.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(try_from = "RawPalette")]
+pub struct Palette {
+    colors: Vec<Rgb>,
+}
+
+impl Palette {
+    /// Attempts to construct a palette from the given colors; there must be 2,
+    /// 4, or 16 of them.
+    pub fn new(colors: Vec<Rgb>) -> Result<Self, ColorError> {
+        if ![2, 4, 16].contains(&colors.len()) {
+            return Err(ColorError::PaletteSize(colors.len()));
+        }
+        Ok(Self { colors })
+    }
+
+    /// Returns the color at the given index, wrapping around if the palette has
+    /// fewer colors.
+    pub fn color(&self, index: usize) -> Rgb {
+        self.colors[index % self.colors.len()]
+    }
+
+    /// Returns the background color.
+    pub fn bg(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    /// Returns the foreground color.
+    pub fn fg(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    /// Replaces the background color.
+    pub fn set_bg(&mut self, color: Rgb) {
+        self.colors[0] = color;
+    }
+
+    /// Replaces the foreground color.
+    pub fn set_fg(&mut self, color: Rgb) {
+        self.colors[1] = color;
+    }
+
+    /// Returns the color between the background (at 0) and foreground (at 1)
+    /// colors at the given intensity, like that of a [`Filter`](crate::filter::Filter)'s
+    /// pixels.
+    pub fn blend(&self, intensity: f32) -> Rgb {
+        let intensity = intensity.clamp(0.0, 1.0);
+        let mut color = self.bg();
+        for (component, fg) in color.iter_mut().zip(self.fg()) {
+            *component =
+                (*component as f32 + (fg as f32 - *component as f32) * intensity).round() as u8;
+        }
+        color
+    }
+
+    /// Returns every color in the palette.
+    pub fn colors(&self) -> &[Rgb] {
+        &self.colors
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        PalettePreset::default().palette()
+    }
+}
+
+/// A palette as written in a config file: either a preset's name or a list of
+/// colors.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPalette {
+    Preset(String),
+    Colors(Vec<String>),
+}
+
+impl TryFrom<RawPalette> for Palette {
+    type Error = ColorError;
+
+    fn try_from(raw: RawPalette) -> Result<Self, Self::Error> {
+        match raw {
+            RawPalette::Preset(name) => Ok(name.parse::<PalettePreset>()?.palette()),
+            RawPalette::Colors(colors) => Self::new(
+                colors
+                    .iter()
+                    .map(|color| parse_color(color))
+                    .collect::<Result<_, _>>()?,
+            ),
+        }
+    }
+}
+
+/// The built-in palettes.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PalettePreset {
+    /// White on black.
+    #[default]
+    Mono,
+    /// Octo's default yellows and browns.
+    Octo,
+    /// The greens of the original Game Boy.
+    GameBoy,
+    /// An amber monochrome monitor.
+    Amber,
+    /// A grey-green LCD.
+    Lcd,
+    /// The sixteen colors of CGA.
+    Cga,
+}
+
+impl PalettePreset {
+    /// Every preset, in the order they're cycled through.
+    pub const ALL: [Self; 6] = [
+        Self::Mono,
+        Self::Octo,
+        Self::GameBoy,
+        Self::Amber,
+        Self::Lcd,
+        Self::Cga,
+    ];
+
+    /// Returns the palette of the preset.
+    pub fn palette(&self) -> Palette {
+        let colors: &[Rgb] = match self {
+            Self::Mono => &[
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0xAA, 0xAA, 0xAA],
+                [0x55, 0x55, 0x55],
+            ],
+            Self::Octo => &[
+                [0x99, 0x66, 0x00],
+                [0xFF, 0xCC, 0x00],
+                [0xFF, 0x66, 0x00],
+                [0x66, 0x22, 0x00],
+            ],
+            Self::GameBoy => &[
+                [0x9B, 0xBC, 0x0F],
+                [0x0F, 0x38, 0x0F],
+                [0x30, 0x62, 0x30],
+                [0x8B, 0xAC, 0x0F],
+            ],
+            Self::Amber => &[
+                [0x1A, 0x0F, 0x00],
+                [0xFF, 0xB0, 0x00],
+                [0xCC, 0x7A, 0x00],
+                [0x66, 0x3D, 0x00],
+            ],
+            Self::Lcd => &[
+                [0xC4, 0xCF, 0xA1],
+                [0x1F, 0x1F, 0x1F],
+                [0x4D, 0x53, 0x3C],
+                [0x8B, 0x95, 0x6D],
+            ],
+            Self::Cga => &[
+                [0x00, 0x00, 0x00],
+                [0xFF, 0xFF, 0xFF],
+                [0x00, 0x00, 0xAA],
+                [0x00, 0xAA, 0x00],
+                [0x00, 0xAA, 0xAA],
+                [0xAA, 0x00, 0x00],
+                [0xAA, 0x00, 0xAA],
+                [0xAA, 0x55, 0x00],
+                [0xAA, 0xAA, 0xAA],
+                [0x55, 0x55, 0x55],
+                [0x55, 0x55, 0xFF],
+                [0x55, 0xFF, 0x55],
+                [0x55, 0xFF, 0xFF],
+                [0xFF, 0x55, 0x55],
+                [0xFF, 0x55, 0xFF],
+                [0xFF, 0xFF, 0x55],
+            ],
+        };
+        Palette {
+            colors: colors.to_vec(),
+        }
+    }
+
+    /// Returns the preset whose palette is the given one, if there is one.
+    pub fn of(palette: &Palette) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.palette() == *palette)
+    }
+
+    /// Returns the preset after this one, wrapping around.
+    pub fn next(&self) -> Self {
+        let i = Self::ALL
+            .iter()
+            .position(|preset| preset == self)
+            .unwrap_or_default();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+impl Display for PalettePreset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Mono => write!(f, "mono"),
+            Self::Octo => write!(f, "octo"),
+            Self::GameBoy => write!(f, "gameboy"),
+            Self::Amber => write!(f, "amber"),
+            Self::Lcd => write!(f, "lcd"),
+            Self::Cga => write!(f, "cga"),
+        }
+    }
+}
+
+impl FromStr for PalettePreset {
+    type Err = ColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace([' ', '-', '_'], "").as_ref() {
+            "mono" => Ok(Self::Mono),
+            "octo" => Ok(Self::Octo),
+            "gameboy" => Ok(Self::GameBoy),
+            "amber" => Ok(Self::Amber),
+            "lcd" => Ok(Self::Lcd),
+            "cga" => Ok(Self::Cga),
+            _ => Err(ColorError::Preset(s.to_string())),
+        }
+    }
+}
+
+/// Used to describe possible errors when parsing colors and palettes.
+#[derive(Clone, Debug, Eq, Error, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ColorError {
+    #[error("{0} isn't a #RRGGBB, #RGB, rgb(R, G, B), or CSS color.")]
+    Color(String),
+    #[error("Palette preset {0} doesn't exist.")]
+    Preset(String),
+    #[error("Palettes must have 2, 4, or 16 colors, not {0}.")]
+    PaletteSize(usize),
+}
+
+/// The CSS color names (sorted, so they can be binary searched) and their
+/// colors.
+const NAMED_COLORS: [(&str, [u8; 3]); 148] = [
+    ("aliceblue", [0xF0, 0xF8, 0xFF]),
+    ("antiquewhite", [0xFA, 0xEB, 0xD7]),
+    ("aqua", [0x00, 0xFF, 0xFF]),
+    ("aquamarine", [0x7F, 0xFF, 0xD4]),
+    ("azure", [0xF0, 0xFF, 0xFF]),
+    ("beige", [0xF5, 0xF5, 0xDC]),
+    ("bisque", [0xFF, 0xE4, 0xC4]),
+    ("black", [0x00, 0x00, 0x00]),
+    ("blanchedalmond", [0xFF, 0xEB, 0xCD]),
+    ("blue", [0x00, 0x00, 0xFF]),
+    ("blueviolet", [0x8A, 0x2B, 0xE2]),
+    ("brown", [0xA5, 0x2A, 0x2A]),
+    ("burlywood", [0xDE, 0xB8, 0x87]),
+    ("cadetblue", [0x5F, 0x9E, 0xA0]),
+    ("chartreuse", [0x7F, 0xFF, 0x00]),
+    ("chocolate", [0xD2, 0x69, 0x1E]),
+    ("coral", [0xFF, 0x7F, 0x50]),
+    ("cornflowerblue", [0x64, 0x95, 0xED]),
+    ("cornsilk", [0xFF, 0xF8, 0xDC]),
+    ("crimson", [0xDC, 0x14, 0x3C]),
+    ("cyan", [0x00, 0xFF, 0xFF]),
+    ("darkblue", [0x00, 0x00, 0x8B]),
+    ("darkcyan", [0x00, 0x8B, 0x8B]),
+    ("darkgoldenrod", [0xB8, 0x86, 0x0B]),
+    ("darkgray", [0xA9, 0xA9, 0xA9]),
+    ("darkgreen", [0x00, 0x64, 0x00]),
+    ("darkgrey", [0xA9, 0xA9, 0xA9]),
+    ("darkkhaki", [0xBD, 0xB7, 0x6B]),
+    ("darkmagenta", [0x8B, 0x00, 0x8B]),
+    ("darkolivegreen", [0x55, 0x6B, 0x2F]),
+    ("darkorange", [0xFF, 0x8C, 0x00]),
+    ("darkorchid", [0x99, 0x32, 0xCC]),
+    ("darkred", [0x8B, 0x00, 0x00]),
+    ("darksalmon", [0xE9, 0x96, 0x7A]),
+    ("darkseagreen", [0x8F, 0xBC, 0x8F]),
+    ("darkslateblue", [0x48, 0x3D, 0x8B]),
+    ("darkslategray", [0x2F, 0x4F, 0x4F]),
+    ("darkslategrey", [0x2F, 0x4F, 0x4F]),
+    ("darkturquoise", [0x00, 0xCE, 0xD1]),
+    ("darkviolet", [0x94, 0x00, 0xD3]),
+    ("deeppink", [0xFF, 0x14, 0x93]),
+    ("deepskyblue", [0x00, 0xBF, 0xFF]),
+    ("dimgray", [0x69, 0x69, 0x69]),
+    ("dimgrey", [0x69, 0x69, 0x69]),
+    ("dodgerblue", [0x1E, 0x90, 0xFF]),
+    ("firebrick", [0xB2, 0x22, 0x22]),
+    ("floralwhite", [0xFF, 0xFA, 0xF0]),
+    ("forestgreen", [0x22, 0x8B, 0x22]),
+    ("fuchsia", [0xFF, 0x00, 0xFF]),
+    ("gainsboro", [0xDC, 0xDC, 0xDC]),
+    ("ghostwhite", [0xF8, 0xF8, 0xFF]),
+    ("gold", [0xFF, 0xD7, 0x00]),
+    ("goldenrod", [0xDA, 0xA5, 0x20]),
+    ("gray", [0x80, 0x80, 0x80]),
+    ("green", [0x00, 0x80, 0x00]),
+    ("greenyellow", [0xAD, 0xFF, 0x2F]),
+    ("grey", [0x80, 0x80, 0x80]),
+    ("honeydew", [0xF0, 0xFF, 0xF0]),
+    ("hotpink", [0xFF, 0x69, 0xB4]),
+    ("indianred", [0xCD, 0x5C, 0x5C]),
+    ("indigo", [0x4B, 0x00, 0x82]),
+    ("ivory", [0xFF, 0xFF, 0xF0]),
+    ("khaki", [0xF0, 0xE6, 0x8C]),
+    ("lavender", [0xE6, 0xE6, 0xFA]),
+    ("lavenderblush", [0xFF, 0xF0, 0xF5]),
+    ("lawngreen", [0x7C, 0xFC, 0x00]),
+    ("lemonchiffon", [0xFF, 0xFA, 0xCD]),
+    ("lightblue", [0xAD, 0xD8, 0xE6]),
+    ("lightcoral", [0xF0, 0x80, 0x80]),
+    ("lightcyan", [0xE0, 0xFF, 0xFF]),
+    ("lightgoldenrodyellow", [0xFA, 0xFA, 0xD2]),
+    ("lightgray", [0xD3, 0xD3, 0xD3]),
+    ("lightgreen", [0x90, 0xEE, 0x90]),
+    ("lightgrey", [0xD3, 0xD3, 0xD3]),
+    ("lightpink", [0xFF, 0xB6, 0xC1]),
+    ("lightsalmon", [0xFF, 0xA0, 0x7A]),
+    ("lightseagreen", [0x20, 0xB2, 0xAA]),
+    ("lightskyblue", [0x87, 0xCE, 0xFA]),
+    ("lightslategray", [0x77, 0x88, 0x99]),
+    ("lightslategrey", [0x77, 0x88, 0x99]),
+    ("lightsteelblue", [0xB0, 0xC4, 0xDE]),
+    ("lightyellow", [0xFF, 0xFF, 0xE0]),
+    ("lime", [0x00, 0xFF, 0x00]),
+    ("limegreen", [0x32, 0xCD, 0x32]),
+    ("linen", [0xFA, 0xF0, 0xE6]),
+    ("magenta", [0xFF, 0x00, 0xFF]),
+    ("maroon", [0x80, 0x00, 0x00]),
+    ("mediumaquamarine", [0x66, 0xCD, 0xAA]),
+    ("mediumblue", [0x00, 0x00, 0xCD]),
+    ("mediumorchid", [0xBA, 0x55, 0xD3]),
+    ("mediumpurple", [0x93, 0x70, 0xDB]),
+    ("mediumseagreen", [0x3C, 0xB3, 0x71]),
+    ("mediumslateblue", [0x7B, 0x68, 0xEE]),
+    ("mediumspringgreen", [0x00, 0xFA, 0x9A]),
+    ("mediumturquoise", [0x48, 0xD1, 0xCC]),
+    ("mediumvioletred", [0xC7, 0x15, 0x85]),
+    ("midnightblue", [0x19, 0x19, 0x70]),
+    ("mintcream", [0xF5, 0xFF, 0xFA]),
+    ("mistyrose", [0xFF, 0xE4, 0xE1]),
+    ("moccasin", [0xFF, 0xE4, 0xB5]),
+    ("navajowhite", [0xFF, 0xDE, 0xAD]),
+    ("navy", [0x00, 0x00, 0x80]),
+    ("oldlace", [0xFD, 0xF5, 0xE6]),
+    ("olive", [0x80, 0x80, 0x00]),
+    ("olivedrab", [0x6B, 0x8E, 0x23]),
+    ("orange", [0xFF, 0xA5, 0x00]),
+    ("orangered", [0xFF, 0x45, 0x00]),
+    ("orchid", [0xDA, 0x70, 0xD6]),
+    ("palegoldenrod", [0xEE, 0xE8, 0xAA]),
+    ("palegreen", [0x98, 0xFB, 0x98]),
+    ("paleturquoise", [0xAF, 0xEE, 0xEE]),
+    ("palevioletred", [0xDB, 0x70, 0x93]),
+    ("papayawhip", [0xFF, 0xEF, 0xD5]),
+    ("peachpuff", [0xFF, 0xDA, 0xB9]),
+    ("peru", [0xCD, 0x85, 0x3F]),
+    ("pink", [0xFF, 0xC0, 0xCB]),
+    ("plum", [0xDD, 0xA0, 0xDD]),
+    ("powderblue", [0xB0, 0xE0, 0xE6]),
+    ("purple", [0x80, 0x00, 0x80]),
+    ("rebeccapurple", [0x66, 0x33, 0x99]),
+    ("red", [0xFF, 0x00, 0x00]),
+    ("rosybrown", [0xBC, 0x8F, 0x8F]),
+    ("royalblue", [0x41, 0x69, 0xE1]),
+    ("saddlebrown", [0x8B, 0x45, 0x13]),
+    ("salmon", [0xFA, 0x80, 0x72]),
+    ("sandybrown", [0xF4, 0xA4, 0x60]),
+    ("seagreen", [0x2E, 0x8B, 0x57]),
+    ("seashell", [0xFF, 0xF5, 0xEE]),
+    ("sienna", [0xA0, 0x52, 0x2D]),
+    ("silver", [0xC0, 0xC0, 0xC0]),
+    ("skyblue", [0x87, 0xCE, 0xEB]),
+    ("slateblue", [0x6A, 0x5A, 0xCD]),
+    ("slategray", [0x70, 0x80, 0x90]),
+    ("slategrey", [0x70, 0x80, 0x90]),
+    ("snow", [0xFF, 0xFA, 0xFA]),
+    ("springgreen", [0x00, 0xFF, 0x7F]),
+    ("steelblue", [0x46, 0x82, 0xB4]),
+    ("tan", [0xD2, 0xB4, 0x8C]),
+    ("teal", [0x00, 0x80, 0x80]),
+    ("thistle", [0xD8, 0xBF, 0xD8]),
+    ("tomato", [0xFF, 0x63, 0x47]),
+    ("turquoise", [0x40, 0xE0, 0xD0]),
+    ("violet", [0xEE, 0x82, 0xEE]),
+    ("wheat", [0xF5, 0xDE, 0xB3]),
+    ("white", [0xFF, 0xFF, 0xFF]),
+    ("whitesmoke", [0xF5, 0xF5, 0xF5]),
+    ("yellow", [0xFF, 0xFF, 0x00]),
+    ("yellowgreen", [0x9A, 0xCD, 0x32]),
+];
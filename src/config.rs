@@ -0,0 +1,535 @@
+//! Provides layered settings for the emulator and its frontends through
+//! [`Settings`], which can be loaded from a TOML [`Config`] file. The file can
+//! also override settings for specific ROMs, matched by their file name or hash.
+//! Settings are resolved from built-in defaults, then the file, then the ROM's
+//! section, and finally the command line (see [`Args`](crate::args::Args)).
+
+use crate::{
+    args::{Layout, Mapping},
+    audio::{Synth, Waveform},
+    chip8::{Platform, Quirks},
+    color::{self, ColorError, Palette, PalettePreset},
+    filter::{Filter, FilterMode},
+    gamepad,
+    hotkeys::{Hotkey, Hotkeys},
+    keymap::{self, Keymap, KeymapError},
+    record::RecordFormat,
+};
+use serde::Deserialize;
+use sha1_smol::Sha1;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error;
+use toml::de::Error as TomlError;
+
+/// How many instructions are executed every frame by default.
+pub const DEFAULT_SPEED: u32 = 10;
+
+/// How many times larger than the emulator's screen a window is by default.
+pub const DEFAULT_SCALE: u32 = 10;
+
+/// How long a key stays held by default after a terminal last reported it
+/// pressed or repeated, in milliseconds (long enough to last until the first
+/// repeat of common keyboard repeat delays).
+pub const DEFAULT_KEY_RELEASE: u32 = 600;
+
+/// The default strength of the display filter (from 0 to 100).
+pub const DEFAULT_FILTER_STRENGTH: u8 = 50;
+
+/// The default frequency of the beep in hertz.
+pub const DEFAULT_FREQUENCY: f32 = 700.0;
+
+/// The default volume of the beep (from 0 to 1).
+pub const DEFAULT_VOLUME: f32 = 1.0;
+
+/// How long the beep takes to fade in by default, in milliseconds.
+pub const DEFAULT_ATTACK: f32 = 5.0;
+
+/// How long the beep takes to fade out by default, in milliseconds.
+pub const DEFAULT_RELEASE: f32 = 5.0;
+
+/// A config file, containing settings for every ROM and sections of settings
+/// for specific ROMs (keyed by file name or SHA-1 hash).
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct Config {
+    #[serde(flatten)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub roms: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    /// Attempts to load the config file at the given path.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let file = fs::read_to_string(path)?;
+        Ok(toml::from_str(&file)?)
+    }
+
+    /// Attempts to load the config file at the default path, returning an empty
+    /// config if there isn't one.
+    pub fn load_default() -> Result<Self, ConfigError> {
+        match Self::default_path() {
+            Some(path) if path.is_file() => Self::load(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the default path of the config file (`biscuit8/config.toml` in
+    /// the user's config directory).
+    pub fn default_path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("biscuit8").join("config.toml"))
+    }
+
+    /// Attempts to return the settings for the ROM at the given path with the
+    /// given contents, if there is one: the settings for every ROM, overridden
+    /// by the sections matching its file name and then its hash, and then by any
+    /// keymaps next to it (see [`Keymap::load_for_rom`]).
+    pub fn settings_for(&self, rom: Option<(&Path, &[u8])>) -> Result<Settings, ConfigError> {
+        let mut settings = self.settings.clone();
+        let Some((path, rom)) = rom else {
+            return Ok(settings);
+        };
+        let name = path.file_name().map(|name| name.to_string_lossy());
+        if let Some(section) = name.and_then(|name| self.roms.get(name.as_ref())) {
+            settings = settings.merge(section.clone());
+        }
+        if let Some(section) = self.roms.get(&rom_hash(rom)) {
+            settings = settings.merge(section.clone());
+        }
+        settings = settings.merge(Settings {
+            keymap: Keymap::load_for_rom(path, keymap::ROM_KEYMAP_EXTENSION)?,
+            gamepad_keymap: Keymap::load_for_rom(path, keymap::ROM_GAMEPAD_KEYMAP_EXTENSION)?,
+            ..Settings::default()
+        });
+        Ok(settings)
+    }
+}
+
+/// Settings for the emulator and its frontends; unset settings fall back to
+/// those of a lower layer, and eventually to the built-in defaults.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Settings {
+    /// The platform whose quirks are emulated.
+    pub platform: Option<Platform>,
+    /// Individual quirks overriding those of the platform.
+    pub quirks: QuirkSettings,
+    /// How many instructions are executed every frame.
+    pub speed: Option<u32>,
+    /// The palette used to draw the screen.
+    pub palette: Option<Palette>,
+    /// The background color, overriding the palette's.
+    pub bg: Option<String>,
+    /// The foreground color, overriding the palette's.
+    pub fg: Option<String>,
+    /// How keys are mapped to CHIP-8 keys.
+    pub mapping: Option<Mapping>,
+    /// The keyboard layout used with logical mapping.
+    pub layout: Option<Layout>,
+    /// A keymap used instead of the preset for the mapping.
+    pub keymap: Option<Keymap>,
+    /// A gamepad keymap used instead of the default one.
+    pub gamepad_keymap: Option<Keymap>,
+    /// Settings for the beep.
+    pub audio: AudioSettings,
+    /// The display filter used to reduce flicker.
+    pub filter: Option<FilterMode>,
+    /// The strength of the display filter (from 0 to 100).
+    pub filter_strength: Option<u8>,
+    /// The format gameplay is recorded in.
+    pub record_format: Option<RecordFormat>,
+    /// How many times larger than the emulator's screen a window is.
+    pub scale: Option<u32>,
+    /// How long a key stays held (in milliseconds) after a terminal that
+    /// doesn't report key releases last reported it pressed or repeated.
+    pub key_release: Option<u32>,
+    /// The keys bound to hotkeys, overriding the default ones.
+    pub hotkeys: BTreeMap<Hotkey, String>,
+}
+
+impl Settings {
+    /// Overrides these settings with those set in the given settings. Setting a
+    /// mapping or layout without a keymap drops any keymap set below, so that
+    /// it's used instead.
+    pub fn merge(self, other: Self) -> Self {
+        let mut hotkeys = self.hotkeys;
+        hotkeys.extend(other.hotkeys);
+        let keymap = match other.mapping.is_some() || other.layout.is_some() {
+            true => other.keymap,
+            false => other.keymap.or(self.keymap),
+        };
+        Self {
+            platform: other.platform.or(self.platform),
+            quirks: self.quirks.merge(other.quirks),
+            speed: other.speed.or(self.speed),
+            palette: other.palette.or(self.palette),
+            bg: other.bg.or(self.bg),
+            fg: other.fg.or(self.fg),
+            mapping: other.mapping.or(self.mapping),
+            layout: other.layout.or(self.layout),
+            keymap,
+            gamepad_keymap: other.gamepad_keymap.or(self.gamepad_keymap),
+            audio: self.audio.merge(other.audio),
+            filter: other.filter.or(self.filter),
+            filter_strength: other.filter_strength.or(self.filter_strength),
+            record_format: other.record_format.or(self.record_format),
+            scale: other.scale.or(self.scale),
+            key_release: other.key_release.or(self.key_release),
+            hotkeys,
+        }
+    }
+
+    /// Returns the quirks of the platform with any individual quirks applied.
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+            .apply(self.platform.unwrap_or_default().quirks())
+    }
+
+    /// Returns how many instructions are executed every frame.
+    pub fn speed(&self) -> u32 {
+        self.speed.unwrap_or(DEFAULT_SPEED)
+    }
+
+    /// Attempts to return the palette with the background and foreground colors
+    /// applied.
+    pub fn palette(&self) -> Result<Palette, ColorError> {
+        self.apply_colors(self.palette.clone().unwrap_or_default())
+    }
+
+    /// Returns the preset the palette is, if it's one (the default palette is
+    /// [`PalettePreset::default`]).
+    pub fn palette_preset(&self) -> Option<PalettePreset> {
+        match &self.palette {
+            Some(palette) => PalettePreset::of(palette),
+            None => Some(PalettePreset::default()),
+        }
+    }
+
+    /// Attempts to apply the background and foreground colors to the given
+    /// palette (like a preset switched to while running).
+    pub fn apply_colors(&self, mut palette: Palette) -> Result<Palette, ColorError> {
+        if let Some(bg) = &self.bg {
+            palette.set_bg(color::parse_color(bg)?);
+        }
+        if let Some(fg) = &self.fg {
+            palette.set_fg(color::parse_color(fg)?);
+        }
+        Ok(palette)
+    }
+
+    /// Returns how keys are mapped to CHIP-8 keys.
+    pub fn mapping(&self) -> Mapping {
+        self.mapping.unwrap_or_default()
+    }
+
+    /// Returns the keymap: either the one set, the keyboard layout's for logical
+    /// mapping, or the physical one for physical mapping.
+    pub fn keymap(&self) -> Keymap {
+        match (&self.keymap, self.mapping()) {
+            (Some(keymap), _) => keymap.clone(),
+            (None, Mapping::Logical) => self.layout.unwrap_or_default().keymap(),
+            (None, Mapping::Physical) => Keymap::from_grid(keymap::PHYSICAL),
+        }
+    }
+
+    /// Returns the gamepad keymap: either the one set or the default one.
+    pub fn gamepad_keymap(&self) -> Keymap {
+        self.gamepad_keymap
+            .clone()
+            .unwrap_or_else(gamepad::default_keymap)
+    }
+
+    /// Returns the frequency of the beep in hertz.
+    pub fn frequency(&self) -> f32 {
+        self.audio.frequency.unwrap_or(DEFAULT_FREQUENCY)
+    }
+
+    /// Returns the volume of the beep (from 0 to 1).
+    pub fn volume(&self) -> f32 {
+        self.audio.volume.unwrap_or(DEFAULT_VOLUME).clamp(0.0, 1.0)
+    }
+
+    /// Returns a synthesizer for the beep producing samples at the given sample
+    /// rate.
+    pub fn synth(&self, sample_rate: u32) -> Synth {
+        let duration = |ms: Option<f32>, default| {
+            Duration::from_secs_f32(ms.unwrap_or(default).max(0.0) / 1000.0)
+        };
+        let mut synth = Synth::new(
+            self.audio.waveform.unwrap_or_default(),
+            self.frequency(),
+            self.volume(),
+            duration(self.audio.attack, DEFAULT_ATTACK),
+            duration(self.audio.release, DEFAULT_RELEASE),
+            sample_rate,
+        );
+        synth.set_muted(self.audio.mute.unwrap_or(false));
+        synth
+    }
+
+    /// Returns a display filter with the mode and strength set.
+    pub fn filter(&self) -> Filter {
+        let strength = self.filter_strength.unwrap_or(DEFAULT_FILTER_STRENGTH);
+        Filter::new(
+            self.filter.unwrap_or_default(),
+            strength.min(100) as f32 / 100.0,
+        )
+    }
+
+    /// Returns the format gameplay is recorded in.
+    pub fn record_format(&self) -> RecordFormat {
+        self.record_format.unwrap_or_default()
+    }
+
+    /// Returns how many times larger than the emulator's screen a window is.
+    pub fn scale(&self) -> u32 {
+        self.scale.unwrap_or(DEFAULT_SCALE).max(1)
+    }
+
+    /// Returns how long a key stays held after a terminal that doesn't report
+    /// key releases last reported it pressed or repeated.
+    pub fn key_release(&self) -> Duration {
+        Duration::from_millis(self.key_release.unwrap_or(DEFAULT_KEY_RELEASE).into())
+    }
+
+    /// Returns the default hotkeys with those set applied.
+    pub fn hotkeys(&self) -> Hotkeys {
+        let mut hotkeys = Hotkeys::default();
+        for (hotkey, name) in &self.hotkeys {
+            hotkeys.bind(name.clone(), *hotkey);
+        }
+        hotkeys
+    }
+}
+
+/// Individual quirks, overriding those of a platform when set.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct QuirkSettings {
+    pub vf_reset: Option<bool>,
+    pub memory: Option<bool>,
+    pub shifting: Option<bool>,
+    pub jumping: Option<bool>,
+    pub wrapping: Option<bool>,
+}
+
+impl QuirkSettings {
+    /// Overrides these quirks with those set in the given quirks.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            vf_reset: other.vf_reset.or(self.vf_reset),
+            memory: other.memory.or(self.memory),
+            shifting: other.shifting.or(self.shifting),
+            jumping: other.jumping.or(self.jumping),
+            wrapping: other.wrapping.or(self.wrapping),
+        }
+    }
+
+    /// Overrides the given quirks with those set.
+    pub fn apply(&self, quirks: Quirks) -> Quirks {
+        Quirks {
+            vf_reset: self.vf_reset.unwrap_or(quirks.vf_reset),
+            memory: self.memory.unwrap_or(quirks.memory),
+            shifting: self.shifting.unwrap_or(quirks.shifting),
+            jumping: self.jumping.unwrap_or(quirks.jumping),
+            wrapping: self.wrapping.unwrap_or(quirks.wrapping),
+        }
+    }
+}
+
+/// Settings for the beep.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, PartialOrd)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AudioSettings {
+    /// The shape of the beep's wave.
+    pub waveform: Option<Waveform>,
+    /// The frequency of the beep in hertz.
+    pub frequency: Option<f32>,
+    /// The volume of the beep (from 0 to 1).
+    pub volume: Option<f32>,
+    /// How long the beep takes to fade in, in milliseconds.
+    pub attack: Option<f32>,
+    /// How long the beep takes to fade out, in milliseconds.
+    pub release: Option<f32>,
+    /// Whether the beep is muted.
+    pub mute: Option<bool>,
+}
+
+impl AudioSettings {
+    /// Overrides these settings with those set in the given settings.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            waveform: other.waveform.or(self.waveform),
+            frequency: other.frequency.or(self.frequency),
+            volume: other.volume.or(self.volume),
+            attack: other.attack.or(self.attack),
+            release: other.release.or(self.release),
+            mute: other.mute.or(self.mute),
+        }
+    }
+}
+
+/// Returns the SHA-1 hash of the given ROM in lowercase hexadecimal, which can
+/// be used to identify it regardless of its file name.
+pub fn rom_hash(rom: &[u8]) -> String {
+    Sha1::from(rom).digest().to_string()
+}
+
+/// Used to describe possible errors when loading a config file.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{0}.")]
+    Io(#[from] IoError),
+    #[error("{0}")]
+    Toml(#[from] TomlError),
+    #[error("{0}")]
+    Keymap(#[from] KeymapError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config file overriding settings for `pong.ch8` by name and for the
+    /// ROM `[0x00, 0xE0]` by hash.
+    const CONFIG: &str = r#"
+        speed = 20
+        scale = 4
+        layout = "dvorak"
+
+        [quirks]
+        wrapping = true
+
+        [audio]
+        volume = 0.5
+
+        [keymap]
+        j = 0x5
+
+        [roms."pong.ch8"]
+        speed = 15
+        filter = "or"
+
+        [roms."pong.ch8".audio]
+        frequency = 440.0
+
+        [roms.159ba69f4c40be3042fc54c7fbb2025f7e49f8e0]
+        speed = 30
+    "#;
+
+    /// Returns the settings for the ROM with the given file name and contents.
+    fn settings_for(name: &str, rom: &[u8]) -> Settings {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let path = Path::new("/nonexistent").join(name);
+        config.settings_for(Some((&path, rom))).unwrap()
+    }
+
+    #[test]
+    fn defaults() {
+        let settings = Settings::default();
+        assert_eq!(settings.speed(), DEFAULT_SPEED);
+        assert_eq!(settings.scale(), DEFAULT_SCALE);
+        assert_eq!(settings.quirks(), Platform::Chip8.quirks());
+        assert_eq!(settings.keymap(), Layout::Qwerty.keymap());
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let settings = settings_for("other.ch8", &[]);
+        assert_eq!(settings.speed(), 20);
+        assert_eq!(settings.scale(), 4);
+        assert_eq!(settings.volume(), 0.5);
+        assert_eq!(settings.frequency(), DEFAULT_FREQUENCY);
+        assert!(settings.quirks().wrapping);
+        assert_eq!(settings.keymap().key("j"), Some(0x5));
+    }
+
+    #[test]
+    fn rom_sections_override_the_file() {
+        let settings = settings_for("pong.ch8", &[]);
+        assert_eq!(settings.speed(), 15);
+        assert_eq!(settings.filter, Some(FilterMode::Or));
+        assert_eq!(settings.frequency(), 440.0);
+        assert_eq!(settings.volume(), 0.5);
+        assert_eq!(settings.scale(), 4);
+
+        // The section matching the hash overrides the one matching the name.
+        let rom = [0x00, 0xE0];
+        assert_eq!(rom_hash(&rom), "159ba69f4c40be3042fc54c7fbb2025f7e49f8e0");
+        assert_eq!(settings_for("pong.ch8", &rom).speed(), 30);
+        assert_eq!(settings_for("other.ch8", &rom).speed(), 30);
+    }
+
+    #[test]
+    fn command_line_overrides_everything() {
+        let cli = Settings {
+            speed: Some(5),
+            quirks: QuirkSettings {
+                wrapping: Some(false),
+                ..QuirkSettings::default()
+            },
+            ..Settings::default()
+        };
+        let settings = settings_for("pong.ch8", &[0x00, 0xE0]).merge(cli);
+        assert_eq!(settings.speed(), 5);
+        assert!(!settings.quirks().wrapping);
+        assert_eq!(settings.filter, Some(FilterMode::Or));
+        assert_eq!(settings.keymap().key("j"), Some(0x5));
+    }
+
+    #[test]
+    fn palette_presets_keep_colors() {
+        let settings = Settings {
+            palette: Some(PalettePreset::GameBoy.palette()),
+            fg: Some("#FC0".to_string()),
+            ..Settings::default()
+        };
+        assert_eq!(settings.palette_preset(), Some(PalettePreset::GameBoy));
+        let next = settings.palette_preset().unwrap().next();
+        assert_eq!(next, PalettePreset::Amber);
+        let palette = settings.apply_colors(next.palette()).unwrap();
+        assert_eq!(palette.fg(), [0xFF, 0xCC, 0x00]);
+        assert_eq!(palette.bg(), PalettePreset::Amber.palette().bg());
+
+        assert_eq!(
+            Settings::default().palette_preset(),
+            Some(PalettePreset::Mono)
+        );
+        let custom = Settings {
+            palette: Some(Palette::new(vec![[1, 2, 3], [4, 5, 6]]).unwrap()),
+            ..Settings::default()
+        };
+        assert_eq!(custom.palette_preset(), None);
+    }
+
+    #[test]
+    fn layouts_and_mappings_replace_keymaps_below() {
+        let layout = Settings {
+            layout: Some(Layout::Azerty),
+            ..Settings::default()
+        };
+        let settings = settings_for("pong.ch8", &[]).merge(layout);
+        assert_eq!(settings.keymap(), Layout::Azerty.keymap());
+
+        let mapping = Settings {
+            mapping: Some(Mapping::Physical),
+            ..Settings::default()
+        };
+        let settings = settings_for("pong.ch8", &[]).merge(mapping);
+        assert_eq!(settings.keymap(), Keymap::from_grid(keymap::PHYSICAL));
+
+        // A keymap set along with them is still used.
+        let keymap = Settings {
+            layout: Some(Layout::Azerty),
+            keymap: Some(Keymap::from_grid(keymap::QWERTZ)),
+            ..Settings::default()
+        };
+        let settings = settings_for("pong.ch8", &[]).merge(keymap);
+        assert_eq!(settings.keymap(), Keymap::from_grid(keymap::QWERTZ));
+    }
+}
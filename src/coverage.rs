@@ -0,0 +1,422 @@
+//! Provides [`Coverage`], which tracks which bytes of RAM a ROM executed as
+//! code, read as data (sprites drawn and registers loaded), and wrote (BCD and
+//! registers stored). It's fed the [`Trace`] of every instruction executed, and
+//! exports an annotated disassembly or an lcov tracefile (for tools like
+//! `genhtml`), labelled with an assembler's [`Symbols`]. Errors are
+//! represented by [`CoverageError`].
+
+use crate::{
+    chip8::{Trace, RAM_SIZE, ROM_LOC},
+    instruction::Instruction,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Error as FmtError, Formatter},
+    fs::{self, File},
+    io::{BufWriter, Error as IoError, Write},
+    path::Path,
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// The extensions of coverage reports written as lcov tracefiles instead of
+/// annotated disassemblies.
+pub const LCOV_EXTENSIONS: [&str; 2] = ["info", "lcov"];
+
+/// The labels of an assembled ROM by address, read from a symbol map with a
+/// label and its address on every line (in either order, separated by
+/// whitespace, `=`, or `:`). Blank lines and comments (starting with `#` or
+/// `;`) are ignored, and addresses are hexadecimal with a `0x` or `$` prefix,
+/// or decimal otherwise.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Symbols(BTreeMap<usize, String>);
+
+impl Symbols {
+    /// Constructs an empty symbol map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attempts to read the symbol map at the given path.
+    pub fn load(path: &Path) -> Result<Self, CoverageError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Returns the label at the address, if there is one.
+    pub fn label(&self, address: usize) -> Option<&str> {
+        self.0.get(&address).map(String::as_str)
+    }
+
+    /// Returns the addresses and labels, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.0
+            .iter()
+            .map(|(address, label)| (*address, label.as_str()))
+    }
+
+    /// Parses an address in hexadecimal (with a `0x` or `$` prefix) or decimal.
+    fn address(token: &str) -> Option<usize> {
+        match token.strip_prefix("0x").or_else(|| token.strip_prefix('$')) {
+            Some(hex) => usize::from_str_radix(hex, 16).ok(),
+            None => token.parse().ok(),
+        }
+    }
+}
+
+impl FromStr for Symbols {
+    type Err = CoverageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut symbols = Self::new();
+        for (line, text) in s.lines().enumerate() {
+            let text = text.trim();
+            if text.is_empty() || text.starts_with('#') || text.starts_with(';') {
+                continue;
+            }
+            let tokens: Vec<_> = text
+                .split(|c: char| c.is_whitespace() || c == '=' || c == ':')
+                .filter(|token| !token.is_empty())
+                .collect();
+            let symbol = match tokens[..] {
+                [a, b] => match (Self::address(a), Self::address(b)) {
+                    (_, Some(address)) => Some((address, a)),
+                    (Some(address), None) => Some((address, b)),
+                    (None, None) => None,
+                },
+                _ => None,
+            };
+            let (address, label) = symbol.ok_or(CoverageError::InvalidSymbol(line + 1))?;
+            symbols.0.insert(address, label.to_string());
+        }
+        Ok(symbols)
+    }
+}
+
+/// How a byte of RAM was accessed.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Access {
+    /// How many times an instruction starting at the byte was executed.
+    pub executed: u64,
+    /// Whether it was executed as the second byte of an instruction.
+    pub operand: bool,
+    /// Whether it was read as data.
+    pub read: bool,
+    /// Whether it was written.
+    pub written: bool,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(self.executed > 0 || self.operand, 'X'),
+            flag(self.read, 'R'),
+            flag(self.written, 'W')
+        )
+    }
+}
+
+/// Tracks how a ROM accessed RAM (see the [module](self) docs).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Coverage {
+    rom: Vec<u8>,
+    accesses: Vec<Access>,
+}
+
+impl Coverage {
+    /// Constructs a tracker for the given ROM that hasn't seen anything yet.
+    pub fn new(rom: &[u8]) -> Self {
+        Self {
+            rom: rom.to_vec(),
+            accesses: vec![Access::default(); RAM_SIZE],
+        }
+    }
+
+    /// Records the bytes an executed instruction accessed.
+    pub fn record(&mut self, trace: &Trace) {
+        if let Some(access) = self.accesses.get_mut(trace.pc) {
+            access.executed += 1;
+        }
+        if let Some(access) = self.accesses.get_mut(trace.pc + 1) {
+            access.operand = true;
+        }
+        for address in trace.reads() {
+            if let Some(access) = self.accesses.get_mut(address) {
+                access.read = true;
+            }
+        }
+        for address in trace.writes() {
+            if let Some(access) = self.accesses.get_mut(address) {
+                access.written = true;
+            }
+        }
+    }
+
+    /// Returns how the byte at the address was accessed.
+    pub fn access(&self, address: usize) -> Access {
+        self.accesses.get(address).copied().unwrap_or_default()
+    }
+
+    /// Returns the bytes of the ROM with how they were accessed, by address.
+    fn rom_accesses(&self) -> impl Iterator<Item = (usize, u8, Access)> + '_ {
+        self.rom
+            .iter()
+            .enumerate()
+            .map(|(offset, byte)| (ROM_LOC + offset, *byte, self.access(ROM_LOC + offset)))
+    }
+
+    /// Writes the ROM as an annotated disassembly: every instruction executed
+    /// with its opcode class and how many times it was executed, and every
+    /// other byte in hexadecimal and binary (to make sprites visible), all
+    /// flagged with how they were accessed (e`X`ecuted, `R`ead, or `W`ritten)
+    /// and labelled with the symbols.
+    pub fn write_disassembly(
+        &self,
+        symbols: &Symbols,
+        mut writer: impl Write,
+    ) -> Result<(), IoError> {
+        let mut bytes = self.rom_accesses().peekable();
+        while let Some((address, byte, access)) = bytes.next() {
+            if let Some(label) = symbols.label(address) {
+                writeln!(writer, "{}:", label)?;
+            }
+            if access.executed > 0 {
+                if let Some((_, low, low_access)) =
+                    bytes.next_if(|(address, ..)| symbols.label(*address).is_none())
+                {
+                    let raw = u16::from_be_bytes([byte, low]);
+                    let instruction = Instruction::new(raw);
+                    let flags = Access {
+                        read: access.read || low_access.read,
+                        written: access.written || low_access.written,
+                        ..access
+                    };
+                    writeln!(
+                        writer,
+                        "  {}  {:#05X}  {:04X}      {:<7}  {}x",
+                        flags,
+                        address,
+                        raw,
+                        instruction.class().unwrap_or("unknown"),
+                        access.executed
+                    )?;
+                    continue;
+                }
+            }
+            writeln!(
+                writer,
+                "  {}  {:#05X}  {:02X}        {:08b}",
+                access, address, byte, byte
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes an lcov tracefile for the ROM at the given path, where the line
+    /// numbers are addresses. Every instruction executed is a line, along with
+    /// every other even address that wasn't read as data (code that never ran),
+    /// and every symbol is a function.
+    pub fn write_lcov(
+        &self,
+        rom: &Path,
+        symbols: &Symbols,
+        mut writer: impl Write,
+    ) -> Result<(), IoError> {
+        writeln!(writer, "TN:")?;
+        writeln!(writer, "SF:{}", rom.display())?;
+        let mut hit = 0;
+        for (address, label) in symbols.iter() {
+            writeln!(writer, "FN:{},{}", address, label)?;
+        }
+        for (address, label) in symbols.iter() {
+            let executed = self.access(address).executed;
+            hit += (executed > 0) as usize;
+            writeln!(writer, "FNDA:{},{}", executed, label)?;
+        }
+        writeln!(writer, "FNF:{}", symbols.0.len())?;
+        writeln!(writer, "FNH:{}", hit)?;
+
+        let (mut lines, mut hit) = (0, 0);
+        for (address, _, access) in self.rom_accesses() {
+            let code = access.executed > 0 || address % 2 == 0 && !access.operand && !access.read;
+            if code {
+                lines += 1;
+                hit += (access.executed > 0) as usize;
+                writeln!(writer, "DA:{},{}", address, access.executed)?;
+            }
+        }
+        writeln!(writer, "LF:{}", lines)?;
+        writeln!(writer, "LH:{}", hit)?;
+        writeln!(writer, "end_of_record")
+    }
+
+    /// Attempts to write the coverage of the ROM at the given path to a file:
+    /// an lcov tracefile if its extension is one of [`LCOV_EXTENSIONS`], or an
+    /// annotated disassembly otherwise.
+    pub fn save(&self, path: &Path, rom: &Path, symbols: &Symbols) -> Result<(), IoError> {
+        let lcov = path
+            .extension()
+            .is_some_and(|extension| LCOV_EXTENSIONS.iter().any(|lcov| extension == *lcov));
+        let mut writer = BufWriter::new(File::create(path)?);
+        if lcov {
+            self.write_lcov(rom, symbols, &mut writer)?;
+        } else {
+            writeln!(writer, "; {}", self)?;
+            self.write_disassembly(symbols, &mut writer)?;
+        }
+        writer.flush()
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        let count = |accessed: fn(&Access) -> bool| {
+            self.rom_accesses()
+                .filter(|(_, _, access)| accessed(access))
+                .count()
+        };
+        let percent = |count: usize| count as f64 * 100.0 / self.rom.len().max(1) as f64;
+        let executed = count(|access| access.executed > 0 || access.operand);
+        let read = count(|access| access.read);
+        let written = count(|access| access.written);
+        write!(
+            f,
+            "{} ROM bytes: {} executed ({:.1}%), {} read ({:.1}%), {} written ({:.1}%)",
+            self.rom.len(),
+            executed,
+            percent(executed),
+            read,
+            percent(read),
+            written,
+            percent(written)
+        )
+    }
+}
+
+/// Used to describe possible errors with symbol maps.
+#[derive(Debug, Error)]
+pub enum CoverageError {
+    #[error("Line {0} of the symbol map isn't a label and an address.")]
+    InvalidSymbol(usize),
+    #[error("{0}.")]
+    Io(#[from] IoError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chip8::Chip8, keys::Keys};
+
+    /// Draws a sprite, stores the BCD of V1 and loads it back, then loops.
+    const ROM: [u8; 21] = [
+        0xA2, 0x0C, 0xD0, 0x05, 0xA2, 0x12, 0xF1, 0x33, 0xF1, 0x65, 0x12, 0x0A, 0xF0, 0x90, 0x90,
+        0x90, 0xF0, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Tracks the coverage of the ROM for 8 instructions.
+    fn coverage() -> Coverage {
+        let mut chip8 = Chip8::new(&ROM).unwrap();
+        chip8.set_tracing(true);
+        let mut coverage = Coverage::new(&ROM);
+        for _ in 0..8 {
+            chip8.run(Keys::new(), 1).unwrap();
+            coverage.record(&chip8.trace().unwrap());
+        }
+        coverage
+    }
+
+    /// Labels the start of the ROM, the sprite, and the BCD digits.
+    fn symbols() -> Symbols {
+        "start 0x200\nsprite = $20C\n0x212: digits\n"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn classifies_accesses() {
+        let coverage = coverage();
+        let access = |address| coverage.access(address);
+        assert_eq!(access(0x200).executed, 1);
+        assert!(access(0x201).operand && access(0x201).executed == 0);
+        assert_eq!(access(0x20A).executed, 3);
+        // Sprites drawn (DXYN) and registers loaded (FX65) are read as data.
+        for address in (0x20C..0x211).chain(0x212..0x214) {
+            assert!(access(address).read, "{:#05X} wasn't read", address);
+            assert_eq!(access(address).executed, 0);
+        }
+        assert!(!access(0x211).read && !access(0x214).read);
+        for address in 0x212..0x215 {
+            assert!(access(address).written, "{:#05X} wasn't written", address);
+        }
+        assert!(!access(0x20C).written && !access(0x211).written);
+        assert_eq!(access(0x214).to_string(), "--W");
+        assert_eq!(access(0x212).to_string(), "-RW");
+        assert_eq!(access(0x200).to_string(), "X--");
+        assert_eq!(access(RAM_SIZE), Access::default());
+        assert_eq!(
+            coverage.to_string(),
+            "21 ROM bytes: 12 executed (57.1%), 7 read (33.3%), 3 written (14.3%)"
+        );
+    }
+
+    #[test]
+    fn writes_disassemblies() {
+        let mut disassembly = Vec::new();
+        coverage()
+            .write_disassembly(&symbols(), &mut disassembly)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(disassembly).unwrap(),
+            "start:\n\
+             \x20 X--  0x200  A20C      ANNN     1x\n\
+             \x20 X--  0x202  D005      DXYN     1x\n\
+             \x20 X--  0x204  A212      ANNN     1x\n\
+             \x20 X--  0x206  F133      FX33     1x\n\
+             \x20 X--  0x208  F165      FX65     1x\n\
+             \x20 X--  0x20A  120A      1NNN     3x\n\
+             sprite:\n\
+             \x20 -R-  0x20C  F0        11110000\n\
+             \x20 -R-  0x20D  90        10010000\n\
+             \x20 -R-  0x20E  90        10010000\n\
+             \x20 -R-  0x20F  90        10010000\n\
+             \x20 -R-  0x210  F0        11110000\n\
+             \x20 ---  0x211  00        00000000\n\
+             digits:\n\
+             \x20 -RW  0x212  00        00000000\n\
+             \x20 -RW  0x213  00        00000000\n\
+             \x20 --W  0x214  00        00000000\n"
+        );
+    }
+
+    #[test]
+    fn writes_lcov_tracefiles() {
+        let mut lcov = Vec::new();
+        coverage()
+            .write_lcov(Path::new("rom.ch8"), &symbols(), &mut lcov)
+            .unwrap();
+        // Unexecuted even addresses that weren't read (like 0x214) count as
+        // code that never ran.
+        assert_eq!(
+            String::from_utf8(lcov).unwrap(),
+            "TN:\nSF:rom.ch8\n\
+             FN:512,start\nFN:524,sprite\nFN:530,digits\n\
+             FNDA:1,start\nFNDA:0,sprite\nFNDA:0,digits\nFNF:3\nFNH:1\n\
+             DA:512,1\nDA:514,1\nDA:516,1\nDA:518,1\nDA:520,1\nDA:522,3\nDA:532,0\n\
+             LF:7\nLH:6\nend_of_record\n"
+        );
+    }
+
+    #[test]
+    fn parses_symbols() {
+        assert_eq!(
+            symbols().iter().collect::<Vec<_>>(),
+            [(0x200, "start"), (0x20C, "sprite"), (0x212, "digits")]
+        );
+        let symbols: Symbols = "# comment\n\n; comment\nloop 520\n".parse().unwrap();
+        assert_eq!(symbols.label(520), Some("loop"));
+        let err = "start 0x200\nstart\n".parse::<Symbols>().err();
+        assert!(matches!(err, Some(CoverageError::InvalidSymbol(2))));
+    }
+}
@@ -0,0 +1,125 @@
+//! Provides an optional display filter through [`Filter`], which blends the
+//! screens drawn over recent frames to hide the flicker caused by sprites being
+//! erased and redrawn. A frontend feeds it every screen drawn by the emulator
+//! and draws the intensity of every pixel at the end of each frame.
+
+use crate::screen::{Screen, HEIGHT, WIDTH};
+use std::{
+    collections::VecDeque,
+    fmt::{Display, Error as FmtError, Formatter},
+};
+
+/// The most frames blended by [`FilterMode::Or`].
+pub const MAX_OR_FRAMES: usize = 8;
+
+/// The ways recent frames can be blended.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum FilterMode {
+    /// Every screen is shown as soon as it's drawn.
+    #[default]
+    None,
+    /// Pixels lit during a frame fade out over the following frames, like the
+    /// phosphor of a CRT.
+    Decay,
+    /// Pixels lit during any of the last few frames are shown.
+    Or,
+    /// Only the screen at the end of every frame is shown.
+    Vblank,
+}
+
+impl Display for FilterMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Decay => write!(f, "decay"),
+            Self::Or => write!(f, "or"),
+            Self::Vblank => write!(f, "vblank"),
+        }
+    }
+}
+
+/// A display filter blending the screens drawn over recent frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filter {
+    mode: FilterMode,
+    strength: f32,
+    latest: Screen,
+    lit: Screen,
+    history: VecDeque<Screen>,
+    intensity: Vec<f32>,
+}
+
+impl Filter {
+    /// Constructs a filter with the given mode and strength (from 0 to 1): how
+    /// much of a pixel's intensity is kept every frame when decaying, or how
+    /// many frames are blended (up to [`MAX_OR_FRAMES`]) when ORing.
+    pub fn new(mode: FilterMode, strength: f32) -> Self {
+        Self {
+            mode,
+            strength: strength.clamp(0.0, 1.0),
+            latest: Screen::new(),
+            lit: Screen::new(),
+            history: VecDeque::new(),
+            intensity: vec![0.0; WIDTH * HEIGHT],
+        }
+    }
+
+    /// Returns the mode of the filter.
+    pub const fn mode(&self) -> FilterMode {
+        self.mode
+    }
+
+    /// Feeds the filter a screen drawn by the emulator during the current frame.
+    pub fn draw(&mut self, screen: &Screen) {
+        self.latest = screen.clone();
+        match self.mode {
+            FilterMode::None => self.show(screen),
+            FilterMode::Decay | FilterMode::Or => self.lit.or(screen),
+            FilterMode::Vblank => (),
+        }
+    }
+
+    /// Ends the current frame, updating the intensity of every pixel.
+    pub fn frame(&mut self) {
+        match self.mode {
+            FilterMode::None => (),
+            FilterMode::Decay => {
+                for (i, intensity) in self.intensity.iter_mut().enumerate() {
+                    let lit = self.lit.pixel(i % WIDTH, i / WIDTH);
+                    *intensity = if lit { 1.0 } else { *intensity * self.strength };
+                }
+            }
+            FilterMode::Or => {
+                let frames = 1 + (self.strength * (MAX_OR_FRAMES - 1) as f32).round() as usize;
+                self.history.push_front(self.lit.clone());
+                self.history.truncate(frames);
+                let mut blended = Screen::new();
+                for screen in &self.history {
+                    blended.or(screen);
+                }
+                self.show(&blended);
+            }
+            FilterMode::Vblank => self.show(&self.latest.clone()),
+        }
+        self.lit = self.latest.clone();
+    }
+
+    /// Returns the intensity (from 0 to 1) of the pixel at the provided
+    /// position.
+    pub fn intensity(&self, x: usize, y: usize) -> f32 {
+        self.intensity[y * WIDTH + x]
+    }
+
+    /// Sets the intensity of every pixel to whether it's lit on the screen.
+    fn show(&mut self, screen: &Screen) {
+        for (i, intensity) in self.intensity.iter_mut().enumerate() {
+            *intensity = screen.pixel(i % WIDTH, i / WIDTH) as u8 as f32;
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new(FilterMode::default(), 0.0)
+    }
+}
@@ -0,0 +1,274 @@
+//! Provides the [`Frontend`] trait, which is everything a frontend has to
+//! implement to be driven by a [`Runner`](crate::runner::Runner): drawing,
+//! input, and sound. Input is fed into [`Input`], which maps the names of the
+//! frontend's keys and gamepad buttons to CHIP-8 keys and hotkeys, and the
+//! state a frontend may want to show is described by [`Status`].
+
+use crate::{
+    args::Mapping,
+    audio,
+    cheats::Cheats,
+    color::Palette,
+    config::Settings,
+    filter::Filter,
+    gamepad::Gamepads,
+    hotkeys::{Hotkey, Hotkeys},
+    keymap::Keymap,
+    keys::Keys,
+    runner::RunnerError,
+};
+use std::{path::PathBuf, time::Instant};
+
+/// A frontend driven by a [`Runner`](crate::runner::Runner); only drawing and
+/// input have to be implemented.
+pub trait Frontend {
+    /// The frontend's error type, which runner errors have to convert into.
+    type Error: From<RunnerError>;
+
+    /// A ROM run while waiting for one to be loaded (through
+    /// [`Input::load_rom`]); without one, a ROM has to be given.
+    const PLACEHOLDER_ROM: Option<&'static [u8]> = None;
+
+    /// Whether the frontend is headless, running ROMs for a given number of
+    /// frames (`--frames`) and analyzing them (`--analyze`); the runner rejects
+    /// those options for other frontends.
+    const HEADLESS: bool = false;
+
+    /// Handles pending input, feeding it into `input`. When there isn't any,
+    /// this should wait for some until the deadline (the start of the next
+    /// frame), returning early once there is.
+    fn poll_input(&mut self, input: &mut Input, deadline: Instant) -> Result<(), Self::Error>;
+
+    /// Draws what the display filter shows, blending the palette's background
+    /// and foreground colors by the intensity of every pixel (see
+    /// [`Palette::blend`]).
+    fn present(&mut self, filter: &Filter, palette: &Palette) -> Result<(), Self::Error>;
+
+    /// Plays a frame of sound: whether the beep is playing (and not muted) and
+    /// the samples produced by the synthesizer.
+    fn set_sound(&mut self, _beeping: bool, _samples: &[f32]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Returns the sample rate the frontend plays samples at, in hertz.
+    fn sample_rate(&self) -> u32 {
+        audio::SAMPLE_RATE
+    }
+
+    /// Returns whether the user wants to quit.
+    fn should_quit(&self) -> bool {
+        false
+    }
+
+    /// Performs the action of a pressed hotkey that the runner doesn't handle
+    /// itself (like those for recent ROMs).
+    fn hotkey(&mut self, _hotkey: Hotkey, _input: &mut Input) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Shows the status of the runner, which is called whenever it changes.
+    fn status(&mut self, _status: &Status) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Shows a message for the user (like where a recording was saved).
+    fn message(&mut self, message: &str) {
+        println!("{}", message);
+    }
+
+    /// Reports an error that isn't fatal (like failing to load a ROM).
+    fn report(&mut self, error: &RunnerError) {
+        eprintln!("{}", error);
+    }
+}
+
+/// The state of a runner that a frontend may want to show.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Status {
+    /// The path of the ROM running, if it isn't the placeholder.
+    pub rom: Option<PathBuf>,
+    /// Whether emulation is paused.
+    pub paused: bool,
+    /// Whether the beep is muted.
+    pub muted: bool,
+    /// Whether gameplay is being recorded.
+    pub recording: bool,
+    /// Whether an input movie is being replayed.
+    pub replaying: bool,
+    /// The cheats for the ROM, which can be toggled with
+    /// [`Input::toggle_cheat`].
+    pub cheats: Cheats,
+}
+
+/// The input of a frontend: the CHIP-8 keys held, and the hotkeys pressed, ROM
+/// requested, and cheats toggled since the last frame. Keys and hotkeys are
+/// named as keymaps (see [`Keymap`]) and [`Hotkeys`] expect.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Input {
+    keys: Keys,
+    keyboard: Keys,
+    keymap: Keymap,
+    mapping: Mapping,
+    hotkeys: Hotkeys,
+    gamepads: Gamepads,
+    pressed_hotkeys: Vec<Hotkey>,
+    rom: Option<PathBuf>,
+    toggled_cheats: Vec<usize>,
+}
+
+impl Input {
+    /// Constructs input mapped according to the given settings.
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            keys: Keys::new(),
+            keyboard: Keys::new(),
+            keymap: settings.keymap(),
+            mapping: settings.mapping(),
+            hotkeys: settings.hotkeys(),
+            gamepads: Gamepads::new(settings.gamepad_keymap()),
+            pressed_hotkeys: Vec::new(),
+            rom: None,
+            toggled_cheats: Vec::new(),
+        }
+    }
+
+    /// Maps input according to the given settings, releasing every key.
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        *self = Self {
+            rom: self.rom.take(),
+            ..Self::new(settings)
+        };
+    }
+
+    /// Returns how keys should be named for the keymap.
+    pub const fn mapping(&self) -> Mapping {
+        self.mapping
+    }
+
+    /// Returns the keys bound to hotkeys.
+    pub const fn hotkeys(&self) -> &Hotkeys {
+        &self.hotkeys
+    }
+
+    /// Returns the CHIP-8 keys held.
+    pub const fn keys(&self) -> Keys {
+        self.keys
+    }
+
+    /// Returns whether the key with the given name is bound to a hotkey,
+    /// queueing the hotkey if it was just pressed (and not repeated). Keys bound
+    /// to hotkeys shouldn't also be fed as CHIP-8 keys.
+    pub fn hotkey(&mut self, name: &str, pressed: bool) -> bool {
+        let Some(hotkey) = self.hotkeys.hotkey(name) else {
+            return false;
+        };
+        if pressed {
+            self.pressed_hotkeys.push(hotkey);
+        }
+        true
+    }
+
+    /// Presses the CHIP-8 key bound to the key with the given name.
+    pub fn press(&mut self, name: &str) {
+        if let Some(key) = self.keymap.key(name) {
+            self.keyboard.press_key(key);
+            self.keys.press_key(key);
+        }
+    }
+
+    /// Releases the CHIP-8 key bound to the key with the given name, unless a
+    /// gamepad holds it.
+    pub fn release(&mut self, name: &str) {
+        if let Some(key) = self.keymap.key(name) {
+            self.keyboard.release_key(key);
+            if !self.gamepads.holds(key) {
+                self.keys.release_key(key);
+            }
+        }
+    }
+
+    /// Presses the CHIP-8 key bound to the named button of the given gamepad.
+    pub fn gamepad_press(&mut self, gamepad: usize, button: &str) {
+        self.gamepads.press(gamepad, button, &mut self.keys);
+    }
+
+    /// Releases the CHIP-8 key bound to the named button of the given gamepad.
+    pub fn gamepad_release(&mut self, gamepad: usize, button: &str) {
+        self.gamepads
+            .release(gamepad, button, self.keyboard, &mut self.keys);
+    }
+
+    /// Feeds the position of the named stick axis of the given gamepad.
+    pub fn gamepad_axis(&mut self, gamepad: usize, axis: &str, value: f32) {
+        self.gamepads
+            .axis(gamepad, axis, value, self.keyboard, &mut self.keys);
+    }
+
+    /// Releases every CHIP-8 key held by the given gamepad.
+    pub fn gamepad_disconnect(&mut self, gamepad: usize) {
+        self.gamepads
+            .disconnect(gamepad, self.keyboard, &mut self.keys);
+    }
+
+    /// Requests the ROM at the given path to be loaded.
+    pub fn load_rom(&mut self, path: PathBuf) {
+        self.rom = Some(path);
+    }
+
+    /// Requests the cheat at the given index (in [`Status::cheats`]) to be
+    /// enabled or disabled.
+    pub fn toggle_cheat(&mut self, index: usize) {
+        self.toggled_cheats.push(index);
+    }
+
+    /// Replaces the CHIP-8 keys held (like when replaying an input movie).
+    pub(crate) fn set_keys(&mut self, keys: Keys) {
+        self.keys = keys;
+    }
+
+    /// Releases every CHIP-8 key.
+    pub(crate) fn release_all(&mut self) {
+        self.keyboard = Keys::new();
+        self.gamepads.release_all(self.keyboard, &mut self.keys);
+        self.keys = Keys::new();
+    }
+
+    /// Resets the last pressed key, which is done after every instruction.
+    pub(crate) fn reset_last_pressed(&mut self) {
+        self.keys.reset_last_pressed();
+    }
+
+    /// Takes the hotkeys pressed since this was last called.
+    pub(crate) fn take_hotkeys(&mut self) -> Vec<Hotkey> {
+        std::mem::take(&mut self.pressed_hotkeys)
+    }
+
+    /// Takes the ROM requested since this was last called.
+    pub(crate) fn take_rom(&mut self) -> Option<PathBuf> {
+        self.rom.take()
+    }
+
+    /// Takes the cheats toggled since this was last called.
+    pub(crate) fn take_cheats(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.toggled_cheats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_and_gamepads_hold_keys_together() {
+        let mut input = Input::new(&Settings::default());
+        input.press("w");
+        input.gamepad_press(0, "DPadUp");
+        input.release("w");
+        assert!(input.keys().key_pressed(0x5));
+        input.press("w");
+        input.gamepad_release(0, "DPadUp");
+        assert!(input.keys().key_pressed(0x5));
+        input.release("w");
+        assert!(!input.keys().key_pressed(0x5));
+    }
+}
@@ -0,0 +1,232 @@
+//! Provides a way to feed gamepad input into [`Keys`] through [`Gamepads`],
+//! which maps buttons and stick directions to CHIP-8 keys using a [`Keymap`].
+//! It doesn't depend on any gamepad library; a frontend only has to name the
+//! buttons and axes of its events. Keys held on the keyboard are passed along
+//! when releasing, so that a gamepad never releases them.
+
+use crate::{keymap::Keymap, keys::Keys};
+use std::collections::BTreeMap;
+
+/// How far a stick has to be pushed before it counts as a button press.
+pub const DEADZONE: f32 = 0.5;
+
+/// The default bindings of gamepad buttons and stick directions, named after
+/// [`gilrs`](https://docs.rs/gilrs)'s buttons and axes. Stick directions are
+/// named after their axis followed by `+` or `-`.
+pub const DEFAULT_BINDINGS: [(&str, u8); 14] = [
+    ("DPadUp", 0x5),
+    ("DPadLeft", 0x7),
+    ("DPadDown", 0x8),
+    ("DPadRight", 0x9),
+    ("LeftStickY+", 0x5),
+    ("LeftStickX-", 0x7),
+    ("LeftStickY-", 0x8),
+    ("LeftStickX+", 0x9),
+    ("South", 0x6),
+    ("East", 0x4),
+    ("West", 0xA),
+    ("North", 0xB),
+    ("Select", 0xC),
+    ("Start", 0xF),
+];
+
+/// Returns the default gamepad keymap (see [`DEFAULT_BINDINGS`]).
+pub fn default_keymap() -> Keymap {
+    let mut keymap = Keymap::new();
+    for (name, key) in DEFAULT_BINDINGS {
+        keymap.bind(name, key);
+    }
+    keymap
+}
+
+/// Tracks which buttons of every connected gamepad are held, so that a CHIP-8
+/// key bound to several buttons (or pressed on several gamepads) is only
+/// released once none of them are held.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Gamepads {
+    keymap: Keymap,
+    held: BTreeMap<(usize, String), u8>,
+}
+
+impl Gamepads {
+    /// Constructs a tracker mapping gamepad input with the given keymap.
+    pub const fn new(keymap: Keymap) -> Self {
+        Self {
+            keymap,
+            held: BTreeMap::new(),
+        }
+    }
+
+    /// Replaces the keymap, releasing every key held by a gamepad (but not those
+    /// the keyboard holds).
+    pub fn set_keymap(&mut self, keymap: Keymap, keyboard: Keys, keys: &mut Keys) {
+        self.release_all(keyboard, keys);
+        self.keymap = keymap;
+    }
+
+    /// Presses the key bound to the named button of the given gamepad.
+    pub fn press(&mut self, gamepad: usize, button: &str, keys: &mut Keys) {
+        let Some(key) = self.keymap.key(button) else {
+            return;
+        };
+        if self
+            .held
+            .insert((gamepad, button.to_string()), key)
+            .is_none()
+        {
+            keys.press_key(key);
+        }
+    }
+
+    /// Releases the key bound to the named button of the given gamepad, unless
+    /// another held button is bound to it too or the keyboard holds it.
+    pub fn release(&mut self, gamepad: usize, button: &str, keyboard: Keys, keys: &mut Keys) {
+        if let Some(key) = self.held.remove(&(gamepad, button.to_string())) {
+            self.release_unheld(key, keyboard, keys);
+        }
+    }
+
+    /// Returns whether any gamepad holds a button bound to the given key.
+    pub fn holds(&self, key: u8) -> bool {
+        self.held.values().any(|held| *held == key)
+    }
+
+    /// Turns the position of the named stick axis of the given gamepad into
+    /// presses and releases of its two directions (`+` and `-`).
+    pub fn axis(
+        &mut self,
+        gamepad: usize,
+        axis: &str,
+        value: f32,
+        keyboard: Keys,
+        keys: &mut Keys,
+    ) {
+        let positive = format!("{}+", axis);
+        let negative = format!("{}-", axis);
+        for (direction, pushed) in [
+            (positive, value >= DEADZONE),
+            (negative, value <= -DEADZONE),
+        ] {
+            if pushed {
+                self.press(gamepad, &direction, keys);
+            } else {
+                self.release(gamepad, &direction, keyboard, keys);
+            }
+        }
+    }
+
+    /// Releases every key held by the given gamepad, which should be done when
+    /// it's disconnected.
+    pub fn disconnect(&mut self, gamepad: usize, keyboard: Keys, keys: &mut Keys) {
+        let buttons = self
+            .held
+            .keys()
+            .filter(|(id, _)| *id == gamepad)
+            .map(|(_, button)| button.clone())
+            .collect::<Vec<_>>();
+        for button in buttons {
+            self.release(gamepad, &button, keyboard, keys);
+        }
+    }
+
+    /// Releases every key held by any gamepad (but not those the keyboard
+    /// holds).
+    pub fn release_all(&mut self, keyboard: Keys, keys: &mut Keys) {
+        for (_, key) in std::mem::take(&mut self.held) {
+            self.release_unheld(key, keyboard, keys);
+        }
+    }
+
+    /// Releases the given key if no held button is bound to it anymore and the
+    /// keyboard doesn't hold it.
+    fn release_unheld(&self, key: u8, keyboard: Keys, keys: &mut Keys) {
+        if !self.holds(key) && !keyboard.key_pressed(key) {
+            keys.release_key(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons() {
+        let mut gamepads = Gamepads::new(default_keymap());
+        let mut keys = Keys::new();
+        gamepads.press(0, "South", &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x6);
+        assert_eq!(keys.last_pressed(), Some(0x6));
+        gamepads.press(0, "Mode", &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x6);
+        gamepads.release(0, "South", Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 0);
+    }
+
+    #[test]
+    fn buttons_bound_to_the_same_key() {
+        let mut gamepads = Gamepads::new(default_keymap());
+        let mut keys = Keys::new();
+        gamepads.press(0, "DPadUp", &mut keys);
+        gamepads.press(0, "LeftStickY+", &mut keys);
+        gamepads.release(0, "DPadUp", Keys::new(), &mut keys);
+        assert!(keys.key_pressed(0x5));
+        gamepads.release(0, "LeftStickY+", Keys::new(), &mut keys);
+        assert!(!keys.key_pressed(0x5));
+    }
+
+    #[test]
+    fn axes() {
+        let mut gamepads = Gamepads::new(default_keymap());
+        let mut keys = Keys::new();
+        gamepads.axis(0, "LeftStickX", DEADZONE / 2.0, Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 0);
+        gamepads.axis(0, "LeftStickX", 1.0, Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x9);
+        gamepads.axis(0, "LeftStickX", -1.0, Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x7);
+        gamepads.axis(0, "LeftStickX", 0.0, Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 0);
+    }
+
+    #[test]
+    fn disconnect_and_hotplug() {
+        let mut gamepads = Gamepads::new(default_keymap());
+        let mut keys = Keys::new();
+        gamepads.press(0, "South", &mut keys);
+        gamepads.press(0, "Start", &mut keys);
+        gamepads.press(1, "South", &mut keys);
+        gamepads.disconnect(0, Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x6);
+        gamepads.release(1, "South", Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 0);
+
+        // A gamepad connected later (or reconnected) works straight away.
+        gamepads.press(2, "North", &mut keys);
+        gamepads.press(0, "East", &mut keys);
+        assert_eq!(keys.raw(), 1 << 0xB | 1 << 0x4);
+        gamepads.disconnect(2, Keys::new(), &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x4);
+    }
+
+    #[test]
+    fn keyboard_keys_stay_held() {
+        let mut gamepads = Gamepads::new(default_keymap());
+        let mut keyboard = Keys::new();
+        keyboard.press_key(0x6);
+        let mut keys = keyboard;
+        gamepads.press(0, "South", &mut keys);
+        gamepads.release(0, "South", keyboard, &mut keys);
+        assert!(keys.key_pressed(0x6));
+        gamepads.press(0, "South", &mut keys);
+        gamepads.disconnect(0, keyboard, &mut keys);
+        assert!(keys.key_pressed(0x6));
+        gamepads.press(0, "South", &mut keys);
+        gamepads.press(0, "Start", &mut keys);
+        gamepads.release_all(keyboard, &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x6);
+        gamepads.press(0, "South", &mut keys);
+        gamepads.set_keymap(Keymap::new(), keyboard, &mut keys);
+        assert_eq!(keys.raw(), 1 << 0x6);
+    }
+}
@@ -0,0 +1,80 @@
+//! Provides the actions a frontend can perform outside of the emulator through
+//! [`Hotkey`], and a way to bind keys to them through [`Hotkeys`].
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The actions a frontend can perform when a hotkey is pressed.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[serde(rename_all = "kebab-case")]
+pub enum Hotkey {
+    /// Selects the next recently loaded ROM.
+    RecentNext,
+    /// Loads the selected recently loaded ROM.
+    RecentLoad,
+    /// Stops selecting a recently loaded ROM.
+    RecentCancel,
+    /// Switches to the next palette preset, keeping the configured background
+    /// and foreground colors.
+    CyclePalette,
+    /// Mutes or unmutes the beep.
+    Mute,
+    /// Pauses or resumes emulation.
+    Pause,
+    /// Starts or stops recording gameplay.
+    Record,
+    /// Shows or hides the cheats (in frontends that can).
+    Cheats,
+}
+
+/// The default key bound to every hotkey.
+pub const DEFAULT_BINDINGS: [(Hotkey, &str); 8] = [
+    (Hotkey::RecentNext, "Tab"),
+    (Hotkey::RecentLoad, "Enter"),
+    (Hotkey::RecentCancel, "Escape"),
+    (Hotkey::CyclePalette, "F1"),
+    (Hotkey::Mute, "F2"),
+    (Hotkey::Record, "F3"),
+    (Hotkey::Pause, "F4"),
+    (Hotkey::Cheats, "F6"),
+];
+
+/// Maps the names of a frontend's keys to hotkeys. Named keys (like "Tab" or
+/// "F1") are named after the UI Events `key` values, and other keys after the
+/// character they type.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Hotkeys {
+    raw: BTreeMap<String, Hotkey>,
+}
+
+impl Hotkeys {
+    /// Binds the key with the given name to the hotkey, replacing the key it was
+    /// previously bound to.
+    pub fn bind(&mut self, name: impl Into<String>, hotkey: Hotkey) {
+        self.raw.retain(|_, bound| *bound != hotkey);
+        self.raw.insert(name.into(), hotkey);
+    }
+
+    /// Returns the hotkey bound to the key with the given name.
+    pub fn hotkey(&self, name: &str) -> Option<Hotkey> {
+        self.raw.get(name).copied()
+    }
+
+    /// Returns the name of the key bound to the hotkey.
+    pub fn key(&self, hotkey: Hotkey) -> Option<&str> {
+        self.raw
+            .iter()
+            .find(|(_, bound)| **bound == hotkey)
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        let raw = DEFAULT_BINDINGS
+            .into_iter()
+            .map(|(hotkey, name)| (name.to_string(), hotkey))
+            .collect();
+        Self { raw }
+    }
+}
@@ -1,6 +1,6 @@
 //! Provides a way for CHIP-8's 16-bit instructions to be represented (the [`Instruction`] struct).
 
-use std::fmt::{Display, Error, Formatter};
+use core::fmt::{Display, Error, Formatter};
 
 /// Used to represent an instruction (opcode and values).
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -48,6 +48,136 @@ impl Instruction {
     pub const fn nnn(&self) -> usize {
         (self.raw & 0x0FFF) as usize
     }
+
+    /// Decodes the instruction's opcode, or returns nothing if it's unknown.
+    pub(crate) const fn opcode(&self) -> Option<Opcode> {
+        let opcode = match self.nibbles() {
+            (0x0, 0x0, 0x0, 0x0) => Opcode::Nop,
+            (0x0, 0x0, 0xE, 0x0) => Opcode::ClearScreen,
+            (0x0, 0x0, 0xE, 0xE) => Opcode::SubroutineReturn,
+            (0x1, _, _, _) => Opcode::JumpAddr,
+            (0x2, _, _, _) => Opcode::CallSubroutine,
+            (0x3, _, _, _) => Opcode::SkipEqByte,
+            (0x4, _, _, _) => Opcode::SkipNotByte,
+            (0x5, _, _, 0x0) => Opcode::SkipEqReg,
+            (0x6, _, _, _) => Opcode::SetRegByte,
+            (0x7, _, _, _) => Opcode::AddByte,
+            (0x8, _, _, 0x0) => Opcode::SetRegReg,
+            (0x8, _, _, 0x1) => Opcode::OrReg,
+            (0x8, _, _, 0x2) => Opcode::AndReg,
+            (0x8, _, _, 0x3) => Opcode::XorReg,
+            (0x8, _, _, 0x4) => Opcode::AddReg,
+            (0x8, _, _, 0x5) => Opcode::SubReg,
+            (0x8, _, _, 0x6) => Opcode::ShrReg,
+            (0x8, _, _, 0x7) => Opcode::RevSubReg,
+            (0x8, _, _, 0xE) => Opcode::ShlReg,
+            (0x9, _, _, 0x0) => Opcode::SkipNotReg,
+            (0xA, _, _, _) => Opcode::SetIndexAddr,
+            (0xB, _, _, _) => Opcode::JumpAddAddr,
+            (0xC, _, _, _) => Opcode::RandAndByte,
+            (0xD, _, _, _) => Opcode::DrawSprite,
+            (0xE, _, 0x9, 0xE) => Opcode::SkipEqKey,
+            (0xE, _, 0xA, 0x1) => Opcode::SkipNotKey,
+            (0xF, _, 0x0, 0x7) => Opcode::SetRegDelay,
+            (0xF, _, 0x0, 0xA) => Opcode::SetRegKey,
+            (0xF, _, 0x1, 0x5) => Opcode::SetDelayReg,
+            (0xF, _, 0x1, 0x8) => Opcode::SetSoundReg,
+            (0xF, _, 0x1, 0xE) => Opcode::AddIndexReg,
+            (0xF, _, 0x2, 0x9) => Opcode::SetIndexChar,
+            (0xF, _, 0x3, 0x3) => Opcode::SetIndexBcd,
+            (0xF, _, 0x5, 0x5) => Opcode::SetIndexReg,
+            (0xF, _, 0x6, 0x5) => Opcode::SetRegIndex,
+            _ => return None,
+        };
+        Some(opcode)
+    }
+
+    /// Returns the pattern of the instruction's opcode (like `8XY4` or `DXYN`),
+    /// or nothing if it's unknown.
+    pub const fn class(&self) -> Option<&'static str> {
+        let Some(opcode) = self.opcode() else {
+            return None;
+        };
+        let class = match opcode {
+            Opcode::Nop => "0000",
+            Opcode::ClearScreen => "00E0",
+            Opcode::SubroutineReturn => "00EE",
+            Opcode::JumpAddr => "1NNN",
+            Opcode::CallSubroutine => "2NNN",
+            Opcode::SkipEqByte => "3XNN",
+            Opcode::SkipNotByte => "4XNN",
+            Opcode::SkipEqReg => "5XY0",
+            Opcode::SetRegByte => "6XNN",
+            Opcode::AddByte => "7XNN",
+            Opcode::SetRegReg => "8XY0",
+            Opcode::OrReg => "8XY1",
+            Opcode::AndReg => "8XY2",
+            Opcode::XorReg => "8XY3",
+            Opcode::AddReg => "8XY4",
+            Opcode::SubReg => "8XY5",
+            Opcode::ShrReg => "8XY6",
+            Opcode::RevSubReg => "8XY7",
+            Opcode::ShlReg => "8XYE",
+            Opcode::SkipNotReg => "9XY0",
+            Opcode::SetIndexAddr => "ANNN",
+            Opcode::JumpAddAddr => "BNNN",
+            Opcode::RandAndByte => "CXNN",
+            Opcode::DrawSprite => "DXYN",
+            Opcode::SkipEqKey => "EX9E",
+            Opcode::SkipNotKey => "EXA1",
+            Opcode::SetRegDelay => "FX07",
+            Opcode::SetRegKey => "FX0A",
+            Opcode::SetDelayReg => "FX15",
+            Opcode::SetSoundReg => "FX18",
+            Opcode::AddIndexReg => "FX1E",
+            Opcode::SetIndexChar => "FX29",
+            Opcode::SetIndexBcd => "FX33",
+            Opcode::SetIndexReg => "FX55",
+            Opcode::SetRegIndex => "FX65",
+        };
+        Some(class)
+    }
+}
+
+/// What an [`Instruction`] does, decoded from its nibbles once so that the
+/// emulator can cache it (the operands are still read from the instruction).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) enum Opcode {
+    Nop,
+    ClearScreen,
+    SubroutineReturn,
+    JumpAddr,
+    CallSubroutine,
+    SkipEqByte,
+    SkipNotByte,
+    SkipEqReg,
+    SetRegByte,
+    AddByte,
+    SetRegReg,
+    OrReg,
+    AndReg,
+    XorReg,
+    AddReg,
+    SubReg,
+    ShrReg,
+    RevSubReg,
+    ShlReg,
+    SkipNotReg,
+    SetIndexAddr,
+    JumpAddAddr,
+    RandAndByte,
+    DrawSprite,
+    SkipEqKey,
+    SkipNotKey,
+    SetRegDelay,
+    SetRegKey,
+    SetDelayReg,
+    SetSoundReg,
+    AddIndexReg,
+    SetIndexChar,
+    SetIndexBcd,
+    SetIndexReg,
+    SetRegIndex,
 }
 
 impl Display for Instruction {
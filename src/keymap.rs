@@ -0,0 +1,180 @@
+//! Provides mappings from a frontend's keys to CHIP-8 keys through [`Keymap`],
+//! along with presets for common keyboard layouts. Keymaps can also be loaded
+//! from TOML files, either for every ROM or for a specific one.
+
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::Error as IoError,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use toml::de::Error as TomlError;
+
+/// The extension of a keyboard keymap specific to a ROM, which replaces the
+/// ROM's own extension (`pong.ch8` uses `pong.keys.toml`).
+pub const ROM_KEYMAP_EXTENSION: &str = "keys.toml";
+
+/// The extension of a gamepad keymap specific to a ROM, which replaces the ROM's
+/// own extension (`pong.ch8` uses `pong.pad.toml`).
+pub const ROM_GAMEPAD_KEYMAP_EXTENSION: &str = "pad.toml";
+
+/// The CHIP-8 keys in the order they're usually laid out on a keyboard (a 4x4
+/// grid, left to right and top to bottom).
+pub const GRID: [u8; 0x10] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+/// The characters of a QWERTY keyboard in the order of [`GRID`].
+pub const QWERTY: [&str; 0x10] = [
+    "1", "2", "3", "4", //
+    "q", "w", "e", "r", //
+    "a", "s", "d", "f", //
+    "z", "x", "c", "v", //
+];
+
+/// The characters of a Colemak keyboard in the order of [`GRID`].
+pub const COLEMAK: [&str; 0x10] = [
+    "1", "2", "3", "4", //
+    "q", "w", "f", "p", //
+    "a", "r", "s", "t", //
+    "z", "x", "c", "v", //
+];
+
+/// The characters of a Dvorak keyboard in the order of [`GRID`].
+pub const DVORAK: [&str; 0x10] = [
+    "1", "2", "3", "4", //
+    "'", ",", ".", "p", //
+    "a", "o", "e", "u", //
+    ";", "q", "j", "k", //
+];
+
+/// The characters of an AZERTY keyboard in the order of [`GRID`] (the number
+/// row is unshifted, so it types symbols).
+pub const AZERTY: [&str; 0x10] = [
+    "&", "é", "\"", "'", //
+    "a", "z", "e", "r", //
+    "q", "s", "d", "f", //
+    "w", "x", "c", "v", //
+];
+
+/// The characters of a QWERTZ keyboard in the order of [`GRID`].
+pub const QWERTZ: [&str; 0x10] = [
+    "1", "2", "3", "4", //
+    "q", "w", "e", "r", //
+    "a", "s", "d", "f", //
+    "y", "x", "c", "v", //
+];
+
+/// The physical positions of keys in the order of [`GRID`], named after the UI
+/// Events `code` values (the left side of a keyboard, whatever its layout).
+pub const PHYSICAL: [&str; 0x10] = [
+    "Digit1", "Digit2", "Digit3", "Digit4", //
+    "KeyQ", "KeyW", "KeyE", "KeyR", //
+    "KeyA", "KeyS", "KeyD", "KeyF", //
+    "KeyZ", "KeyX", "KeyC", "KeyV", //
+];
+
+/// Maps the names of a frontend's keys to CHIP-8 keys. Any amount of keys can
+/// be bound to the same CHIP-8 key.
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Keymap {
+    raw: BTreeMap<String, u8>,
+}
+
+impl Keymap {
+    /// Constructs a keymap without any bindings.
+    pub const fn new() -> Self {
+        Self {
+            raw: BTreeMap::new(),
+        }
+    }
+
+    /// Constructs a keymap binding the given key names in the order of
+    /// [`GRID`].
+    pub fn from_grid(names: [&str; 0x10]) -> Self {
+        let mut keymap = Self::new();
+        for (name, key) in names.into_iter().zip(GRID) {
+            keymap.bind(name, key);
+        }
+        keymap
+    }
+
+    /// Attempts to load a keymap from a TOML file, in which every key name is
+    /// assigned a CHIP-8 key (`w = 0x5`, for example).
+    pub fn load(path: &Path) -> Result<Self, KeymapError> {
+        let file = fs::read_to_string(path)?;
+        let raw = toml::from_str::<BTreeMap<String, u8>>(&file)?;
+        Self::try_from(raw)
+    }
+
+    /// Attempts to load the keymap specific to the ROM at the given path, which
+    /// is in a file next to it with the given extension (like
+    /// [`ROM_KEYMAP_EXTENSION`]). Nothing is returned if there isn't one.
+    pub fn load_for_rom(rom: &Path, extension: &str) -> Result<Option<Self>, KeymapError> {
+        let path = Self::rom_keymap_path(rom, extension);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::load(&path).map(Some)
+    }
+
+    /// Returns the path of the keymap with the given extension specific to the
+    /// ROM at the given path.
+    pub fn rom_keymap_path(rom: &Path, extension: &str) -> PathBuf {
+        rom.with_extension(extension)
+    }
+
+    /// Binds the key with the given name to the CHIP-8 key.
+    pub fn bind(&mut self, name: impl Into<String>, key: u8) {
+        self.raw.insert(name.into(), key);
+    }
+
+    /// Removes the binding of the key with the given name.
+    pub fn unbind(&mut self, name: &str) {
+        self.raw.remove(name);
+    }
+
+    /// Returns the CHIP-8 key bound to the key with the given name.
+    pub fn key(&self, name: &str) -> Option<u8> {
+        self.raw.get(name).copied()
+    }
+
+    /// Returns an iterator over every binding (key name and CHIP-8 key).
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, u8)> {
+        self.raw.iter().map(|(name, key)| (name.as_str(), *key))
+    }
+}
+
+impl TryFrom<BTreeMap<String, u8>> for Keymap {
+    type Error = KeymapError;
+
+    fn try_from(raw: BTreeMap<String, u8>) -> Result<Self, Self::Error> {
+        if let Some((name, key)) = raw.iter().find(|(_, key)| **key > 0xF) {
+            return Err(KeymapError::InvalidKey(name.clone(), *key));
+        }
+        Ok(Self { raw })
+    }
+}
+
+impl<'de> Deserialize<'de> for Keymap {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = BTreeMap::<String, u8>::deserialize(deserializer)?;
+        Self::try_from(raw).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Used to describe possible errors when loading a keymap.
+#[derive(Debug, Error)]
+pub enum KeymapError {
+    #[error("Key {0} is bound to {1:#X}, which isn't a CHIP-8 key.")]
+    InvalidKey(String, u8),
+    #[error("{0}.")]
+    Io(#[from] IoError),
+    #[error("{0}")]
+    Toml(#[from] TomlError),
+}
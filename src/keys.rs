@@ -1,6 +1,11 @@
 //! Provides intermediary constructs for input between a frontend and the
 //! backend.
 
+/// Where the bitmask of held keys is in a set of keys, for the native code the
+/// JIT writes (see [`jit`](crate::chip8::jit)).
+#[cfg(all(feature = "jit", target_arch = "x86_64"))]
+pub(crate) const RAW_OFFSET: usize = core::mem::offset_of!(Keys, raw);
+
 /// This represents any keys for input currently held down or released.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Keys {
@@ -17,6 +22,17 @@ impl Keys {
         }
     }
 
+    /// Constructs a set of keys from a bitmask of the held keys (with bit `n`
+    /// set for key `n`) and the last key pressed.
+    pub const fn from_raw(raw: u16, last_pressed: Option<u8>) -> Self {
+        Self { raw, last_pressed }
+    }
+
+    /// Returns a bitmask of the held keys (with bit `n` set for key `n`).
+    pub const fn raw(&self) -> u16 {
+        self.raw
+    }
+
     /// Presses the specified key.
     pub fn press_key(&mut self, key: u8) {
         self.raw |= 1 << key;
@@ -5,9 +5,52 @@
 //! required to be implemented by the frontend, but numerous helper constructs
 //! are provided to assist with bridging the gap. This project also implements
 //! some frontends itself too.
+//!
+//! The core ([`chip8`], [`screen`], [`keys`], and [`instruction`]) doesn't need
+//! `std` or even an allocator, so it can be embedded in microcontroller
+//! firmware by disabling the default features. Everything else needs the `std`
+//! feature, and command line parsing through [`argh`] (along with the
+//! [`frontend`] and [`runner`] built on it) needs the `args` feature too.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod analyzer;
+#[cfg(feature = "std")]
 pub mod args;
+#[cfg(feature = "std")]
+pub mod audio;
+#[cfg(feature = "std")]
+pub mod cheats;
 pub mod chip8;
+#[cfg(feature = "std")]
+pub mod color;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod coverage;
+#[cfg(feature = "std")]
+pub mod filter;
+#[cfg(feature = "args")]
+pub mod frontend;
+#[cfg(feature = "std")]
+pub mod gamepad;
+#[cfg(feature = "std")]
+pub mod hotkeys;
 pub mod instruction;
+#[cfg(feature = "std")]
+pub mod keymap;
 pub mod keys;
+#[cfg(feature = "std")]
+pub mod movie;
+#[cfg(feature = "std")]
+pub mod octo;
+#[cfg(feature = "std")]
+pub mod profiler;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "args")]
+pub mod runner;
 pub mod screen;
+#[cfg(feature = "std")]
+pub mod wav;
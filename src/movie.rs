@@ -0,0 +1,144 @@
+//! Provides input movies through [`Movie`], which record the [`Keys`] held
+//! during every frame along with everything else the emulator needs to replay
+//! them exactly: the hash of the ROM, the seed of its random number generator,
+//! its speed, and its quirks. Movies are saved as TOML files, so a tester's
+//! session can be shared and turned into a reproducible test case.
+
+use crate::{
+    chip8::{Chip8, Quirks},
+    config,
+    keys::Keys,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::Error as IoError, path::Path};
+use thiserror::Error;
+use toml::{de::Error as TomlError, ser::Error as TomlSerError};
+
+/// An input movie for a ROM.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Movie {
+    /// The SHA-1 hash of the ROM (see [`config::rom_hash`]).
+    pub rom_hash: String,
+    /// The seed of the emulator's random number generator.
+    pub seed: u64,
+    /// How many instructions are executed every frame.
+    pub speed: u32,
+    /// The quirks the emulator runs with.
+    pub quirks: Quirks,
+    /// The keys of every frame, as the hexadecimal bitmask of held keys,
+    /// followed by the last pressed key if one was pressed (like `0020 5`).
+    frames: Vec<String>,
+}
+
+impl Movie {
+    /// Starts recording a movie of the given emulator running the given ROM at
+    /// the given speed, seeding the emulator with a random seed.
+    pub fn record(chip8: &mut Chip8, rom: &[u8], speed: u32) -> Self {
+        // TOML integers are signed, so the seed has to fit in an `i64`.
+        let seed = fastrand::u64(..=i64::MAX as u64);
+        chip8.set_seed(seed);
+        Self {
+            rom_hash: config::rom_hash(rom),
+            seed,
+            speed,
+            quirks: chip8.quirks(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Returns an empty movie with the same ROM, seed, speed, and quirks as this
+    /// one, which can be used to record a movie continuing this one while it's
+    /// replayed.
+    pub fn rerecord(&self) -> Self {
+        Self {
+            frames: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Attempts to load the movie at the given path.
+    pub fn load(path: &Path) -> Result<Self, MovieError> {
+        let file = fs::read_to_string(path)?;
+        let movie: Self = toml::from_str(&file)?;
+        if let Some(frame) = movie
+            .frames
+            .iter()
+            .find(|frame| parse_keys(frame).is_none())
+        {
+            return Err(MovieError::Frame(frame.clone()));
+        }
+        Ok(movie)
+    }
+
+    /// Attempts to save the movie to the given path.
+    pub fn save(&self, path: &Path) -> Result<(), MovieError> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Attempts to prepare the given emulator running the given ROM to replay
+    /// the movie, failing if the movie was recorded with another ROM.
+    pub fn play(&self, chip8: &mut Chip8, rom: &[u8]) -> Result<(), MovieError> {
+        if config::rom_hash(rom) != self.rom_hash {
+            return Err(MovieError::RomMismatch);
+        }
+        chip8.set_quirks(self.quirks);
+        chip8.set_seed(self.seed);
+        Ok(())
+    }
+
+    /// Appends the keys of the next frame, which should be done at the start
+    /// of every frame.
+    pub fn push(&mut self, keys: Keys) {
+        let frame = match keys.last_pressed() {
+            Some(key) => format!("{:04X} {:X}", keys.raw(), key),
+            None => format!("{:04X}", keys.raw()),
+        };
+        self.frames.push(frame);
+    }
+
+    /// Returns the keys of the given frame, if the movie is long enough.
+    pub fn keys(&self, frame: usize) -> Option<Keys> {
+        self.frames.get(frame).and_then(|frame| parse_keys(frame))
+    }
+
+    /// Returns how many frames long the movie is.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns whether the movie doesn't have any frames.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+/// Parses the keys of a frame in the format used by movies.
+fn parse_keys(frame: &str) -> Option<Keys> {
+    let mut parts = frame.split_whitespace();
+    let raw = u16::from_str_radix(parts.next()?, 16).ok()?;
+    let last_pressed = match parts.next() {
+        Some(key) => Some(u8::from_str_radix(key, 16).ok().filter(|key| *key <= 0xF)?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(Keys::from_raw(raw, last_pressed))
+}
+
+/// Used to describe possible errors when loading, saving, or playing movies.
+#[derive(Debug, Error)]
+pub enum MovieError {
+    #[error("{0}.")]
+    Io(#[from] IoError),
+    #[error("{0}")]
+    Toml(#[from] TomlError),
+    #[error("{0}")]
+    TomlSer(#[from] TomlSerError),
+    #[error("Movie frame {0:?} isn't a keys bitmask optionally followed by a key.")]
+    Frame(String),
+    #[error("Movie was recorded with a different ROM.")]
+    RomMismatch,
+}
@@ -0,0 +1,919 @@
+//! Provides [`assemble`], which assembles [Octo](https://github.com/JohnEarnest/Octo)
+//! source code into a ROM, so that `.8o` files can be run directly. It covers
+//! the language's statements (including the SUPER-CHIP and XO-CHIP ones),
+//! labels, constants, aliases, conditionals and loops, and the `:org`,
+//! `:byte`, `:pointer`, `:unpack`, `:call`, and `:next` directives, but not
+//! macros, `:calc` expressions, or string modes. Errors are represented by
+//! [`OctoError`].
+
+use crate::chip8::ROM_LOC;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The size of the address space an assembled program can fill (XO-CHIP's).
+const ADDRESS_SPACE: usize = 0x10000;
+
+/// The words that can't be used as names of labels, constants, or aliases.
+const KEYWORDS: [&str; 36] = [
+    "return",
+    "clear",
+    "bcd",
+    "save",
+    "load",
+    "saveflags",
+    "loadflags",
+    "sprite",
+    "jump",
+    "jump0",
+    "native",
+    "hires",
+    "lores",
+    "exit",
+    "scroll-down",
+    "scroll-up",
+    "scroll-left",
+    "scroll-right",
+    "plane",
+    "audio",
+    "pitch",
+    "delay",
+    "buzzer",
+    "i",
+    "if",
+    "then",
+    "begin",
+    "else",
+    "end",
+    "loop",
+    "while",
+    "again",
+    "key",
+    "hex",
+    "bighex",
+    "random",
+];
+
+/// Attempts to assemble Octo source code into a ROM (loaded at
+/// [`ROM_LOC`]). Like Octo, the ROM starts with a jump to the `main` label.
+pub fn assemble(source: &str) -> Result<Vec<u8>, OctoError> {
+    let mut assembler = Assembler::new(source);
+    while assembler.pos < assembler.tokens.len() {
+        assembler.statement()?;
+    }
+    assembler.finish()
+}
+
+/// How a reference to a label is patched into the ROM once it's defined.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Patch {
+    /// The low 12 bits of an instruction.
+    Address,
+    /// A whole 16-bit word.
+    Long,
+    /// The bytes of two `VX := NN` instructions: the high nibble of the first
+    /// one, followed by the high 4 bits of the address, and the low byte of the
+    /// address in the second one.
+    Unpack(u8),
+    /// The bytes of two `VX := NN` instructions: the high byte of the address
+    /// in the first one, and the low byte in the second one.
+    UnpackLong,
+}
+
+impl Patch {
+    /// Returns the largest address that can be patched in.
+    const fn limit(&self) -> usize {
+        match self {
+            Self::Address | Self::Unpack(_) => 0xFFF,
+            Self::Long | Self::UnpackLong => 0xFFFF,
+        }
+    }
+}
+
+/// A reference to a label that wasn't defined yet where it was used.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Fixup {
+    line: usize,
+    name: String,
+    address: usize,
+    patch: Patch,
+}
+
+/// A conditional or loop whose end hasn't been reached yet.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+enum Block {
+    /// An `if ... begin` block, with the jump past it (to its `else` branch, if
+    /// it has one) and, once the `else` is reached, the jump past that.
+    If { skip: usize, end: Option<usize> },
+    /// A `loop` block, with the address it starts at and the jumps out of it
+    /// made by `while`.
+    Loop { start: usize, exits: Vec<usize> },
+}
+
+/// A condition tested by `if` or `while`: the instructions setting `VF` for
+/// comparisons, and the skip instructions taken when it's true and when it's
+/// false.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Condition {
+    prelude: Vec<u16>,
+    skip_if_true: u16,
+    skip_if_false: u16,
+}
+
+/// Assembles a program a token at a time.
+struct Assembler<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+    memory: Vec<Option<u8>>,
+    here: usize,
+    end: usize,
+    values: HashMap<&'a str, usize>,
+    aliases: HashMap<&'a str, u8>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<(usize, Block)>,
+}
+
+impl<'a> Assembler<'a> {
+    /// Constructs an assembler for the source, splitting it into tokens
+    /// (ignoring comments) and reserving room for the jump to `main`.
+    fn new(source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(line, text)| {
+                let code = text.split('#').next().unwrap_or_default();
+                code.split_whitespace().map(move |token| (line + 1, token))
+            })
+            .collect();
+        let mut memory = vec![None; ADDRESS_SPACE];
+        memory[ROM_LOC] = Some(0x10);
+        memory[ROM_LOC + 1] = Some(0x00);
+        Self {
+            tokens,
+            pos: 0,
+            memory,
+            here: ROM_LOC + 2,
+            end: ROM_LOC + 2,
+            values: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: vec![Fixup {
+                line: 0,
+                name: "main".to_string(),
+                address: ROM_LOC,
+                patch: Patch::Address,
+            }],
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Attempts to resolve every reference to a label, returning the ROM.
+    fn finish(mut self) -> Result<Vec<u8>, OctoError> {
+        if let Some((line, _)) = self.blocks.last() {
+            return Err(OctoError::Unclosed(*line));
+        }
+        if !self.values.contains_key("main") {
+            return Err(OctoError::MissingMain);
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            let value = *self
+                .values
+                .get(fixup.name.as_str())
+                .ok_or(OctoError::Undefined(fixup.line, fixup.name))?;
+            self.patch(fixup.line, fixup.address, fixup.patch, value)?;
+        }
+        Ok(self.memory[ROM_LOC..self.end]
+            .iter()
+            .map(|byte| byte.unwrap_or_default())
+            .collect())
+    }
+
+    /// Returns the line of the current token (or the last one, at the end).
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+            .map_or(0, |(line, _)| *line)
+    }
+
+    /// Returns the next token without consuming it.
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|(_, token)| *token)
+    }
+
+    /// Attempts to consume the next token.
+    fn next(&mut self) -> Result<&'a str, OctoError> {
+        let token = self
+            .peek()
+            .ok_or_else(|| OctoError::UnexpectedEnd(self.line()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Attempts to consume the next token, which has to be the given one.
+    fn expect(&mut self, expected: &'static str) -> Result<(), OctoError> {
+        let line = self.line();
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(OctoError::Expected(line, expected, token.to_string())),
+        }
+    }
+
+    /// Attempts to consume a register (`v0` to `vf`, or an alias of one).
+    fn register(&mut self) -> Result<u8, OctoError> {
+        let line = self.line();
+        let token = self.next()?;
+        self.as_register(token)
+            .ok_or_else(|| OctoError::Expected(line, "a register", token.to_string()))
+    }
+
+    /// Returns the register the token names, if it's one.
+    fn as_register(&self, token: &str) -> Option<u8> {
+        match token.strip_prefix(['v', 'V']) {
+            Some(digit) if digit.len() == 1 => u8::from_str_radix(digit, 16).ok(),
+            _ => self.aliases.get(token).copied(),
+        }
+    }
+
+    /// Returns whether the token could name a label, constant, or alias.
+    fn is_name(&self, token: &str) -> bool {
+        token.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && !KEYWORDS.contains(&token)
+            && self.as_register(token).is_none()
+    }
+
+    /// Attempts to consume a new name for a label, constant, or alias.
+    fn name(&mut self) -> Result<&'a str, OctoError> {
+        let line = self.line();
+        let token = self.next()?;
+        if !self.is_name(token) {
+            return Err(OctoError::Expected(line, "a name", token.to_string()));
+        }
+        if self.values.contains_key(token) {
+            return Err(OctoError::Redefined(line, token.to_string()));
+        }
+        Ok(token)
+    }
+
+    /// Attempts to consume a number (decimal, or hexadecimal or binary with a
+    /// `0x` or `0b` prefix, optionally negative) or an already defined label or
+    /// constant, checking that it's within the range.
+    fn value(&mut self, min: i64, max: i64) -> Result<i64, OctoError> {
+        let line = self.line();
+        let token = self.next()?;
+        let value = match (Self::number(token), self.values.get(token)) {
+            (Some(value), _) => value,
+            (None, Some(value)) => *value as i64,
+            (None, None) if self.is_name(token) => {
+                return Err(OctoError::Undefined(line, token.to_string()))
+            }
+            (None, None) => return Err(OctoError::Expected(line, "a number", token.to_string())),
+        };
+        if !(min..=max).contains(&value) {
+            return Err(OctoError::OutOfRange(line, value));
+        }
+        Ok(value)
+    }
+
+    /// Attempts to consume a byte, which may be negative.
+    fn byte(&mut self) -> Result<u8, OctoError> {
+        Ok(self.value(-128, 0xFF)? as u8)
+    }
+
+    /// Attempts to consume a nibble.
+    fn nibble(&mut self) -> Result<u8, OctoError> {
+        Ok(self.value(0, 0xF)? as u8)
+    }
+
+    /// Parses a number, if the token is one.
+    fn number(token: &str) -> Option<i64> {
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16).ok()?
+        } else if let Some(binary) = digits.strip_prefix("0b") {
+            i64::from_str_radix(binary, 2).ok()?
+        } else {
+            digits.parse().ok()?
+        };
+        Some(if negative { -value } else { value })
+    }
+
+    /// Attempts to emit an instruction referring to an address (a number, or a
+    /// label that may be defined later) patched in the given way.
+    fn reference(&mut self, opcode: &[u8], patch: Patch) -> Result<(), OctoError> {
+        let line = self.line();
+        let address = self.here;
+        match self.peek() {
+            Some(token) if self.is_name(token) && !self.values.contains_key(token) => {
+                self.pos += 1;
+                self.fixups.push(Fixup {
+                    line,
+                    name: token.to_string(),
+                    address,
+                    patch,
+                });
+                self.bytes(opcode)
+            }
+            _ => {
+                let value = self.value(0, patch.limit() as i64)? as usize;
+                self.bytes(opcode)?;
+                self.patch(line, address, patch, value)
+            }
+        }
+    }
+
+    /// Attempts to patch an address into the instructions at the given
+    /// address.
+    fn patch(
+        &mut self,
+        line: usize,
+        address: usize,
+        patch: Patch,
+        value: usize,
+    ) -> Result<(), OctoError> {
+        if value > patch.limit() {
+            return Err(OctoError::OutOfRange(line, value as i64));
+        }
+        let high = self.memory[address].unwrap_or_default() & 0xF0;
+        let mut set = |offset: usize, byte: u8| self.memory[address + offset] = Some(byte);
+        match patch {
+            Patch::Address => {
+                set(0, high | (value >> 8) as u8);
+                set(1, value as u8);
+            }
+            Patch::Long => {
+                set(0, (value >> 8) as u8);
+                set(1, value as u8);
+            }
+            Patch::Unpack(nibble) => {
+                set(1, nibble << 4 | (value >> 8) as u8);
+                set(3, value as u8);
+            }
+            Patch::UnpackLong => {
+                set(1, (value >> 8) as u8);
+                set(3, value as u8);
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to emit bytes at the current address.
+    fn bytes(&mut self, bytes: &[u8]) -> Result<(), OctoError> {
+        for &byte in bytes {
+            let line = self.line();
+            let slot = self
+                .memory
+                .get_mut(self.here)
+                .ok_or(OctoError::OutOfRange(line, self.here as i64))?;
+            if slot.is_some() {
+                return Err(OctoError::Overlap(line, self.here));
+            }
+            *slot = Some(byte);
+            self.here += 1;
+            self.end = self.end.max(self.here);
+        }
+        Ok(())
+    }
+
+    /// Attempts to emit an instruction.
+    fn emit(&mut self, instruction: u16) -> Result<(), OctoError> {
+        self.bytes(&instruction.to_be_bytes())
+    }
+
+    /// Attempts to emit a jump whose address is patched later, returning where
+    /// it is.
+    fn placeholder_jump(&mut self) -> Result<usize, OctoError> {
+        let address = self.here;
+        self.emit(0x1000)?;
+        Ok(address)
+    }
+
+    /// Attempts to assemble a statement (or directive).
+    fn statement(&mut self) -> Result<(), OctoError> {
+        let line = self.line();
+        let token = self.next()?;
+        let x = |register: u8| (register as u16) << 8;
+        let y = |register: u8| (register as u16) << 4;
+        match token {
+            ";" | "return" => self.emit(0x00EE),
+            "clear" => self.emit(0x00E0),
+            "hires" => self.emit(0x00FF),
+            "lores" => self.emit(0x00FE),
+            "exit" => self.emit(0x00FD),
+            "scroll-left" => self.emit(0x00FC),
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n as u16)
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n as u16)
+            }
+            "audio" => self.emit(0xF002),
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(0xF001 | x(n))
+            }
+            "bcd" => {
+                let vx = self.register()?;
+                self.emit(0xF033 | x(vx))
+            }
+            "saveflags" => {
+                let vx = self.register()?;
+                self.emit(0xF075 | x(vx))
+            }
+            "loadflags" => {
+                let vx = self.register()?;
+                self.emit(0xF085 | x(vx))
+            }
+            "save" | "load" => {
+                let vx = self.register()?;
+                let (single, range) = match token {
+                    "save" => (0xF055, 0x5002),
+                    _ => (0xF065, 0x5003),
+                };
+                if self.peek() == Some("-") {
+                    self.pos += 1;
+                    let vy = self.register()?;
+                    self.emit(range | x(vx) | y(vy))
+                } else {
+                    self.emit(single | x(vx))
+                }
+            }
+            "sprite" => {
+                let vx = self.register()?;
+                let vy = self.register()?;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x(vx) | y(vy) | n as u16)
+            }
+            "jump" => self.reference(&[0x10, 0x00], Patch::Address),
+            "jump0" => self.reference(&[0xB0, 0x00], Patch::Address),
+            "native" => self.reference(&[0x00, 0x00], Patch::Address),
+            ":call" => self.reference(&[0x20, 0x00], Patch::Address),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let vx = self.register()?;
+                let opcode = match token {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.emit(opcode | x(vx))
+            }
+            "i" => self.index(),
+            "if" => self.conditional(line),
+            "else" => match self.blocks.pop() {
+                Some((opened, Block::If { skip, end: None })) => {
+                    let end = self.placeholder_jump()?;
+                    self.patch(line, skip, Patch::Address, self.here)?;
+                    self.blocks.push((
+                        opened,
+                        Block::If {
+                            skip,
+                            end: Some(end),
+                        },
+                    ));
+                    Ok(())
+                }
+                _ => Err(OctoError::Unmatched(line, token.to_string())),
+            },
+            "end" => match self.blocks.pop() {
+                Some((_, Block::If { skip, end })) => {
+                    self.patch(line, end.unwrap_or(skip), Patch::Address, self.here)
+                }
+                _ => Err(OctoError::Unmatched(line, token.to_string())),
+            },
+            "loop" => {
+                let start = self.here;
+                self.blocks.push((
+                    line,
+                    Block::Loop {
+                        start,
+                        exits: Vec::new(),
+                    },
+                ));
+                Ok(())
+            }
+            "while" => {
+                let condition = self.condition()?;
+                for instruction in condition.prelude {
+                    self.emit(instruction)?;
+                }
+                self.emit(condition.skip_if_true)?;
+                let exit = self.placeholder_jump()?;
+                let exits = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find_map(|(_, block)| match block {
+                        Block::Loop { exits, .. } => Some(exits),
+                        Block::If { .. } => None,
+                    });
+                exits
+                    .ok_or_else(|| OctoError::Unmatched(line, token.to_string()))?
+                    .push(exit);
+                Ok(())
+            }
+            "again" => match self.blocks.pop() {
+                Some((_, Block::Loop { start, exits })) => {
+                    let again = self.placeholder_jump()?;
+                    self.patch(line, again, Patch::Address, start)?;
+                    for exit in exits {
+                        self.patch(line, exit, Patch::Address, self.here)?;
+                    }
+                    Ok(())
+                }
+                _ => Err(OctoError::Unmatched(line, token.to_string())),
+            },
+            ":" | ":next" => {
+                let name = self.name()?;
+                let offset = if token == ":next" { 1 } else { 0 };
+                self.values.insert(name, self.here + offset);
+                Ok(())
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value(0, 0xFFFF)?;
+                self.values.insert(name, value as usize);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+                Ok(())
+            }
+            ":org" => {
+                self.here = self.value(ROM_LOC as i64, ADDRESS_SPACE as i64 - 1)? as usize;
+                Ok(())
+            }
+            ":byte" => {
+                let byte = self.byte()?;
+                self.bytes(&[byte])
+            }
+            ":pointer" => self.reference(&[0x00, 0x00], Patch::Long),
+            ":unpack" => {
+                let patch = if self.peek() == Some("long") {
+                    self.pos += 1;
+                    Patch::UnpackLong
+                } else {
+                    Patch::Unpack(self.nibble()?)
+                };
+                self.reference(&[0x60, 0x00, 0x61, 0x00], patch)
+            }
+            ":breakpoint" => self.next().map(|_| ()),
+            ":monitor" => self.next().and_then(|_| self.next()).map(|_| ()),
+            _ if token.starts_with(':') || token.starts_with('{') => {
+                Err(OctoError::Unsupported(line, token.to_string()))
+            }
+            _ => {
+                if let Some(vx) = self.as_register(token) {
+                    self.assignment(vx)
+                } else if Self::number(token).is_some() {
+                    self.pos -= 1;
+                    let byte = self.byte()?;
+                    self.bytes(&[byte])
+                } else if self.is_name(token) {
+                    self.pos -= 1;
+                    self.reference(&[0x20, 0x00], Patch::Address)
+                } else {
+                    Err(OctoError::Expected(line, "a statement", token.to_string()))
+                }
+            }
+        }
+    }
+
+    /// Attempts to assemble a statement setting `I`.
+    fn index(&mut self) -> Result<(), OctoError> {
+        let line = self.line();
+        match self.next()? {
+            ":=" => match self.peek() {
+                Some("hex") | Some("bighex") => {
+                    let opcode = if self.next()? == "hex" {
+                        0xF029
+                    } else {
+                        0xF030
+                    };
+                    let vx = self.register()?;
+                    self.emit(opcode | (vx as u16) << 8)
+                }
+                Some("long") => {
+                    self.pos += 1;
+                    self.emit(0xF000)?;
+                    self.reference(&[0x00, 0x00], Patch::Long)
+                }
+                _ => self.reference(&[0xA0, 0x00], Patch::Address),
+            },
+            "+=" => {
+                let vx = self.register()?;
+                self.emit(0xF01E | (vx as u16) << 8)
+            }
+            token => Err(OctoError::Expected(line, "an operator", token.to_string())),
+        }
+    }
+
+    /// Attempts to assemble a statement assigning to a register.
+    fn assignment(&mut self, vx: u8) -> Result<(), OctoError> {
+        let line = self.line();
+        let operator = self.next()?;
+        let x = (vx as u16) << 8;
+        let source = self.peek().and_then(|token| self.as_register(token));
+        if let Some(vy) = source {
+            self.pos += 1;
+            let opcode = match operator {
+                ":=" => 0x8000,
+                "|=" => 0x8001,
+                "&=" => 0x8002,
+                "^=" => 0x8003,
+                "+=" => 0x8004,
+                "-=" => 0x8005,
+                ">>=" => 0x8006,
+                "=-" => 0x8007,
+                "<<=" => 0x800E,
+                _ => {
+                    return Err(OctoError::Expected(
+                        line,
+                        "an operator",
+                        operator.to_string(),
+                    ))
+                }
+            };
+            return self.emit(opcode | x | (vy as u16) << 4);
+        }
+        match (operator, self.peek()) {
+            (":=", Some("delay")) => {
+                self.pos += 1;
+                self.emit(0xF007 | x)
+            }
+            (":=", Some("key")) => {
+                self.pos += 1;
+                self.emit(0xF00A | x)
+            }
+            (":=", Some("random")) => {
+                self.pos += 1;
+                let mask = self.byte()?;
+                self.emit(0xC000 | x | mask as u16)
+            }
+            (":=", _) => {
+                let byte = self.byte()?;
+                self.emit(0x6000 | x | byte as u16)
+            }
+            ("+=", _) => {
+                let byte = self.byte()?;
+                self.emit(0x7000 | x | byte as u16)
+            }
+            ("-=", _) => {
+                let byte = self.byte()?;
+                self.emit(0x7000 | x | byte.wrapping_neg() as u16)
+            }
+            _ => Err(OctoError::Expected(
+                line,
+                "an operator",
+                operator.to_string(),
+            )),
+        }
+    }
+
+    /// Attempts to assemble an `if`, either skipping the statement after
+    /// `then`, or starting a block with `begin`.
+    fn conditional(&mut self, line: usize) -> Result<(), OctoError> {
+        let condition = self.condition()?;
+        for &instruction in &condition.prelude {
+            self.emit(instruction)?;
+        }
+        let token_line = self.line();
+        match self.next()? {
+            "then" => {
+                self.emit(condition.skip_if_false)?;
+                self.statement()
+            }
+            "begin" => {
+                self.emit(condition.skip_if_true)?;
+                let skip = self.placeholder_jump()?;
+                self.blocks.push((line, Block::If { skip, end: None }));
+                Ok(())
+            }
+            token => Err(OctoError::Expected(
+                token_line,
+                "then or begin",
+                token.to_string(),
+            )),
+        }
+    }
+
+    /// Attempts to parse a condition: a register compared with a register or a
+    /// byte (`==`, `!=`, `<`, `>`, `<=`, or `>=`), or a register followed by
+    /// `key` or `-key`. Ordered comparisons are made by subtracting into `VF`.
+    fn condition(&mut self) -> Result<Condition, OctoError> {
+        let vx = self.register()?;
+        let line = self.line();
+        let operator = self.next()?;
+        let x = (vx as u16) << 8;
+        let simple = |skip_if_true, skip_if_false| Condition {
+            prelude: Vec::new(),
+            skip_if_true,
+            skip_if_false,
+        };
+        match operator {
+            "key" => return Ok(simple(0xE09E | x, 0xE0A1 | x)),
+            "-key" => return Ok(simple(0xE0A1 | x, 0xE09E | x)),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => (),
+            _ => {
+                return Err(OctoError::Expected(
+                    line,
+                    "a comparison",
+                    operator.to_string(),
+                ))
+            }
+        }
+        let vy = self.peek().and_then(|token| self.as_register(token));
+        let operand = match vy {
+            Some(vy) => {
+                self.pos += 1;
+                Err(vy)
+            }
+            None => Ok(self.byte()?),
+        };
+        let (equal, not_equal) = match operand {
+            Ok(byte) => (0x3000 | x | byte as u16, 0x4000 | x | byte as u16),
+            Err(vy) => (0x5000 | x | (vy as u16) << 4, 0x9000 | x | (vy as u16) << 4),
+        };
+        // VF is set to whether the left side is greater than or equal to the
+        // right side (the flag of subtracting them), swapping the sides for
+        // > and <=.
+        let swapped = matches!(operator, ">" | "<=");
+        let prelude = match (operand, swapped) {
+            (Ok(byte), false) => vec![0x6F00 | byte as u16, 0x8F07 | x >> 4],
+            (Ok(byte), true) => vec![0x6F00 | byte as u16, 0x8F05 | x >> 4],
+            (Err(vy), false) => vec![0x8F00 | x >> 4, 0x8F05 | (vy as u16) << 4],
+            (Err(vy), true) => vec![0x8F00 | (vy as u16) << 4, 0x8F05 | x >> 4],
+        };
+        let (flag_clear, flag_set) = (0x3F00, 0x4F00);
+        Ok(match operator {
+            "==" => simple(equal, not_equal),
+            "!=" => simple(not_equal, equal),
+            "<" | ">" => Condition {
+                prelude,
+                skip_if_true: flag_clear,
+                skip_if_false: flag_set,
+            },
+            _ => Condition {
+                prelude,
+                skip_if_true: flag_set,
+                skip_if_false: flag_clear,
+            },
+        })
+    }
+}
+
+/// Used to describe possible errors when assembling Octo source code.
+#[derive(Debug, Error)]
+pub enum OctoError {
+    #[error("Line {0}: expected {1}, found \"{2}\".")]
+    Expected(usize, &'static str, String),
+    #[error("Line {0}: unexpected end of the source.")]
+    UnexpectedEnd(usize),
+    #[error("Line {0}: {1} isn't defined.")]
+    Undefined(usize, String),
+    #[error("Line {0}: {1} is already defined.")]
+    Redefined(usize, String),
+    #[error("Line {0}: {1} is out of range.")]
+    OutOfRange(usize, i64),
+    #[error("Line {0}: overwrites the byte at 0x{1:03X}.")]
+    Overlap(usize, usize),
+    #[error("Line {0}: {1} doesn't match a block.")]
+    Unmatched(usize, String),
+    #[error("Line {0}: this block is never closed.")]
+    Unclosed(usize),
+    #[error("Line {0}: {1} isn't supported.")]
+    Unsupported(usize, String),
+    #[error("The program doesn't have a main label.")]
+    MissingMain,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chip8::Chip8, keys::Keys};
+
+    /// Assembles the source and runs it until it loops forever (jumps to
+    /// itself), returning the registers.
+    fn run(source: &str) -> [u8; 0x10] {
+        let mut chip8 = Chip8::new(&assemble(source).unwrap()).unwrap();
+        for _ in 0..1000 {
+            let pc = chip8.pc();
+            chip8.run(Keys::new(), 1).unwrap();
+            if chip8.pc() == pc {
+                return *chip8.registers();
+            }
+        }
+        panic!("The program didn't halt.");
+    }
+
+    #[test]
+    fn statements() {
+        let rom = assemble(
+            ": main
+               v0 := 5 v1 += v0 v2 -= 1 va := random 0xFF
+               i := data sprite v0 v1 3
+               delay := v0 v3 := key save v2 load v2 - v4
+               jump main
+             : data 0b11100000 0xA0 :byte 224 :pointer main",
+        )
+        .unwrap();
+        assert_eq!(
+            rom,
+            [
+                0x12, 0x02, // jump main
+                0x60, 0x05, 0x81, 0x04, 0x72, 0xFF, 0xCA, 0xFF, // arithmetic
+                0xA2, 0x18, 0xD0, 0x13, // sprite
+                0xF0, 0x15, 0xF3, 0x0A, 0xF2, 0x55, 0x52, 0x43, // timers and memory
+                0x12, 0x02, // jump main
+                0xE0, 0xA0, 0xE0, 0x02, 0x02, // data
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_and_directives() {
+        let rom = assemble(
+            ":const speed 3 :alias counter v7
+             : main counter := speed helper
+             :unpack 0xA data :next target v0 := 0 ;
+             : helper counter += 1 return
+             :org 0x280 : data 1",
+        )
+        .unwrap();
+        assert_eq!(
+            rom[..0x14],
+            [
+                0x12, 0x02, // jump main
+                0x67, 0x03, 0x22, 0x0E, // main
+                0x60, 0xA2, 0x61, 0x80, // unpack
+                0x60, 0x00, 0x00, 0xEE, // target
+                0x77, 0x01, 0x00, 0xEE, // helper
+                0x00, 0x00,
+            ]
+        );
+        assert_eq!(rom.len(), 0x81);
+        assert_eq!(rom[0x80], 1);
+    }
+
+    #[test]
+    fn comparisons() {
+        let registers = run(": main
+               v0 := 3 v1 := 7 v2 := 0
+               if v0 < v1 then v2 += 0x01
+               if v0 > v1 then v2 += 0x02
+               if v0 <= 3 then v2 += 0x04
+               if v0 >= 4 then v2 += 0x08
+               if v1 > 6 then v2 += 0x10
+               if v1 != v0 then v2 += 0x20
+               if v1 == 7 begin v3 := 1 else v3 := 2 end
+               if v1 key then v4 := 1
+               : halt jump halt");
+        assert_eq!(registers[2], 0x01 | 0x04 | 0x10 | 0x20);
+        assert_eq!(registers[3], 1);
+        assert_eq!(registers[4], 0);
+    }
+
+    #[test]
+    fn loops() {
+        let registers = run(": main
+               v0 := 0 v1 := 0
+               loop
+                 v0 += 1
+                 if v0 == 3 begin v1 := 9 end
+                 while v0 != 5
+               again
+               : halt jump halt");
+        assert_eq!(registers[0], 5);
+        assert_eq!(registers[1], 9);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source| assemble(source).unwrap_err().to_string();
+        assert_eq!(error(": start ;"), "The program doesn't have a main label.");
+        assert_eq!(
+            error(": main jump nowhere"),
+            "Line 1: nowhere isn't defined."
+        );
+        assert_eq!(error(": main\nv0 := 256"), "Line 2: 256 is out of range.");
+        assert_eq!(
+            error(": main\n\nloop"),
+            "Line 3: this block is never closed."
+        );
+        assert_eq!(error(": main end"), "Line 1: end doesn't match a block.");
+        assert_eq!(error(": main :macro"), "Line 1: :macro isn't supported.");
+        assert_eq!(
+            error(": main v0 =+ v1"),
+            "Line 1: expected an operator, found \"=+\"."
+        );
+        assert_eq!(
+            error(": main :org 0x200 clear"),
+            "Line 1: overwrites the byte at 0x200."
+        );
+    }
+}
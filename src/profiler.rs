@@ -0,0 +1,366 @@
+//! Provides a [`Profiler`] that shows where a ROM spends its cycles: how often
+//! every address and opcode class is executed, how many cycles are spent
+//! inside every subroutine, and how many sprites are drawn every frame. It's
+//! fed the [`Trace`] of every instruction executed, and writes a sorted report
+//! along with a folded-stack file for flamegraph tools (like `inferno` or
+//! `flamegraph.pl`).
+
+use crate::chip8::{Trace, ROM_LOC};
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt::{Display, Error as FmtError, Formatter},
+    fs::File,
+    io::{BufWriter, Error as IoError, Write},
+    path::Path,
+};
+
+/// How many of the hottest addresses are listed in the report.
+const HOT_ADDRESSES: usize = 32;
+
+/// The extension of the folded-stack file written next to the report.
+pub const FOLDED_EXTENSION: &str = "folded";
+
+/// How often a subroutine was called, and how many cycles were spent inside it
+/// (including in the subroutines it called).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Subroutine {
+    /// How many times it was called.
+    pub calls: u64,
+    /// How many instructions were executed inside it.
+    pub cycles: u64,
+}
+
+/// A call stack seen while profiling.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct Stack {
+    /// The subroutines in it, each only once (so that recursive subroutines are
+    /// only counted once).
+    subroutines: Vec<usize>,
+    /// How many instructions were executed in it.
+    instructions: u64,
+}
+
+/// Counts the instructions executed by a ROM (see the [module](self) docs).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Profiler {
+    instructions: u64,
+    addresses: BTreeMap<usize, u64>,
+    classes: BTreeMap<&'static str, u64>,
+    subroutines: BTreeMap<usize, Subroutine>,
+    stack: Vec<usize>,
+    // Every call stack is only looked up when a subroutine is called or
+    // returns, rather than for every instruction.
+    interned: BTreeMap<Vec<usize>, usize>,
+    stacks: Vec<Stack>,
+    current: usize,
+    frames: u64,
+    draws: u64,
+    frame_draws: u64,
+    max_frame_draws: u64,
+}
+
+impl Profiler {
+    /// Constructs a profiler that hasn't counted anything yet.
+    pub fn new() -> Self {
+        Self {
+            instructions: 0,
+            addresses: BTreeMap::new(),
+            classes: BTreeMap::new(),
+            subroutines: BTreeMap::new(),
+            stack: Vec::new(),
+            interned: BTreeMap::from([(Vec::new(), 0)]),
+            stacks: vec![Stack::default()],
+            current: 0,
+            frames: 0,
+            draws: 0,
+            frame_draws: 0,
+            max_frame_draws: 0,
+        }
+    }
+
+    /// Counts an executed instruction, following subroutine calls and returns
+    /// to know which subroutines it was executed inside.
+    pub fn record(&mut self, trace: &Trace) {
+        self.instructions += 1;
+        *self.addresses.entry(trace.pc).or_default() += 1;
+        let class = trace.instruction.class().unwrap_or("unknown");
+        *self.classes.entry(class).or_default() += 1;
+        let stack = &mut self.stacks[self.current];
+        stack.instructions += 1;
+        for &subroutine in &stack.subroutines {
+            self.subroutines.entry(subroutine).or_default().cycles += 1;
+        }
+        if trace.drew() {
+            self.draws += 1;
+            self.frame_draws += 1;
+        }
+        if let Some(subroutine) = trace.call() {
+            self.subroutines.entry(subroutine).or_default().calls += 1;
+            self.stack.push(subroutine);
+            self.current = self.intern();
+        } else if trace.returned() {
+            self.stack.pop();
+            self.current = self.intern();
+        }
+    }
+
+    /// Returns the index of the current call stack in `stacks`, adding it if it
+    /// hasn't been seen before.
+    fn intern(&mut self) -> usize {
+        if let Some(&index) = self.interned.get(self.stack.as_slice()) {
+            return index;
+        }
+        let mut subroutines = Vec::with_capacity(self.stack.len());
+        for &subroutine in &self.stack {
+            if !subroutines.contains(&subroutine) {
+                subroutines.push(subroutine);
+            }
+        }
+        let index = self.stacks.len();
+        self.stacks.push(Stack {
+            subroutines,
+            instructions: 0,
+        });
+        self.interned.insert(self.stack.clone(), index);
+        index
+    }
+
+    /// Ends a frame, which is needed to count draws per frame.
+    pub fn end_frame(&mut self) {
+        self.frames += 1;
+        self.max_frame_draws = self.max_frame_draws.max(self.frame_draws);
+        self.frame_draws = 0;
+    }
+
+    /// Returns how many instructions were executed.
+    pub const fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Returns how many times every address was executed.
+    pub const fn addresses(&self) -> &BTreeMap<usize, u64> {
+        &self.addresses
+    }
+
+    /// Returns how many times every opcode class (see
+    /// [`Instruction::class`](crate::instruction::Instruction::class)) was
+    /// executed.
+    pub const fn classes(&self) -> &BTreeMap<&'static str, u64> {
+        &self.classes
+    }
+
+    /// Returns the calls and cycles of every subroutine called, by address.
+    pub const fn subroutines(&self) -> &BTreeMap<usize, Subroutine> {
+        &self.subroutines
+    }
+
+    /// Writes how many instructions were executed in every call stack, as
+    /// folded stacks (frames separated by semicolons, followed by a count) for
+    /// flamegraph tools. The outermost frame is the start of the ROM.
+    pub fn write_folded(&self, mut writer: impl Write) -> Result<(), IoError> {
+        for (stack, &index) in &self.interned {
+            let count = self.stacks[index].instructions;
+            if count == 0 {
+                continue;
+            }
+            write!(writer, "{:#05X}", ROM_LOC)?;
+            for subroutine in stack {
+                write!(writer, ";{:#05X}", subroutine)?;
+            }
+            writeln!(writer, " {}", count)?;
+        }
+        Ok(())
+    }
+
+    /// Attempts to write the report to the file at the given path, and the
+    /// folded stacks next to it (see [`FOLDED_EXTENSION`]).
+    pub fn save(&self, path: &Path) -> Result<(), IoError> {
+        let mut report = BufWriter::new(File::create(path)?);
+        write!(report, "{}", self)?;
+        report.flush()?;
+        let mut folded = BufWriter::new(File::create(path.with_extension(FOLDED_EXTENSION))?);
+        self.write_folded(&mut folded)?;
+        folded.flush()
+    }
+
+    /// Returns the counts sorted from highest to lowest.
+    fn sorted<K: Copy>(counts: impl Iterator<Item = (K, u64)>) -> Vec<(K, u64)> {
+        let mut counts: Vec<_> = counts.collect();
+        counts.sort_by_key(|&(_, count)| Reverse(count));
+        counts
+    }
+
+    /// Returns the percentage of all instructions executed a count is.
+    fn percent(&self, count: u64) -> f64 {
+        count as f64 * 100.0 / self.instructions.max(1) as f64
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for Profiler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        let per_frame = |count: u64| count as f64 / self.frames.max(1) as f64;
+        writeln!(
+            f,
+            "{} instructions over {} frames ({:.1} per frame)",
+            self.instructions,
+            self.frames,
+            per_frame(self.instructions)
+        )?;
+        writeln!(
+            f,
+            "{} sprites drawn ({:.1} per frame, at most {})",
+            self.draws,
+            per_frame(self.draws),
+            self.max_frame_draws
+        )?;
+
+        writeln!(f, "\nHottest addresses:")?;
+        let addresses = Self::sorted(self.addresses.iter().map(|(a, c)| (*a, *c)));
+        for (address, count) in addresses.into_iter().take(HOT_ADDRESSES) {
+            writeln!(
+                f,
+                "  {:#05X}  {:>10}  {:5.1}%",
+                address,
+                count,
+                self.percent(count)
+            )?;
+        }
+
+        writeln!(f, "\nOpcode classes:")?;
+        for (class, count) in Self::sorted(self.classes.iter().map(|(c, n)| (*c, *n))) {
+            writeln!(
+                f,
+                "  {:<7}  {:>10}  {:5.1}%",
+                class,
+                count,
+                self.percent(count)
+            )?;
+        }
+
+        writeln!(f, "\nSubroutines (cycles include nested calls):")?;
+        let mut subroutines: Vec<_> = self.subroutines.iter().collect();
+        subroutines.sort_by_key(|(_, subroutine)| Reverse(subroutine.cycles));
+        for (address, subroutine) in subroutines {
+            writeln!(
+                f,
+                "  {:#05X}  {:>8} calls  {:>10} cycles  {:5.1}%",
+                address,
+                subroutine.calls,
+                subroutine.cycles,
+                self.percent(subroutine.cycles)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chip8::Chip8, keys::Keys};
+
+    /// Profiles a ROM that calls a subroutine twice, which calls another that
+    /// draws a sprite, then loops. The first frame ends after both calls.
+    fn profile() -> Profiler {
+        let rom = [
+            0x22, 0x06, 0x22, 0x06, 0x12, 0x04, 0x22, 0x0A, 0x00, 0xEE, 0xD0, 0x05, 0x00, 0xEE,
+        ];
+        let mut chip8 = Chip8::new(&rom).unwrap();
+        chip8.set_tracing(true);
+        let mut profiler = Profiler::new();
+        for frame in [10, 2] {
+            for _ in 0..frame {
+                chip8.run(Keys::new(), 1).unwrap();
+                profiler.record(&chip8.trace().unwrap());
+            }
+            profiler.end_frame();
+        }
+        profiler
+    }
+
+    #[test]
+    fn counts_instructions() {
+        let profiler = profile();
+        assert_eq!(profiler.instructions(), 12);
+        assert_eq!(
+            profiler.addresses(),
+            &BTreeMap::from([
+                (0x200, 1),
+                (0x202, 1),
+                (0x204, 2),
+                (0x206, 2),
+                (0x208, 2),
+                (0x20A, 2),
+                (0x20C, 2),
+            ])
+        );
+        assert_eq!(
+            profiler.classes(),
+            &BTreeMap::from([("00EE", 4), ("1NNN", 2), ("2NNN", 4), ("DXYN", 2)])
+        );
+        assert_eq!(
+            profiler.subroutines(),
+            &BTreeMap::from([
+                (
+                    0x206,
+                    Subroutine {
+                        calls: 2,
+                        cycles: 8
+                    }
+                ),
+                (
+                    0x20A,
+                    Subroutine {
+                        calls: 2,
+                        cycles: 4
+                    }
+                ),
+            ])
+        );
+        let report = profiler.to_string();
+        assert!(report.starts_with(
+            "12 instructions over 2 frames (6.0 per frame)\n\
+             2 sprites drawn (1.0 per frame, at most 2)\n"
+        ));
+    }
+
+    #[test]
+    fn counts_recursive_subroutines_once() {
+        // Calls itself until V0 reaches 3, then returns all the way out.
+        let rom = [
+            0x22, 0x04, 0x12, 0x02, 0x70, 0x01, 0x30, 0x03, 0x22, 0x04, 0x00, 0xEE,
+        ];
+        let mut chip8 = Chip8::new(&rom).unwrap();
+        chip8.set_tracing(true);
+        let mut profiler = Profiler::new();
+        for _ in 0..16 {
+            chip8.run(Keys::new(), 1).unwrap();
+            profiler.record(&chip8.trace().unwrap());
+        }
+        let subroutine = profiler.subroutines()[&0x204];
+        assert_eq!(
+            subroutine,
+            Subroutine {
+                calls: 3,
+                cycles: 11
+            }
+        );
+    }
+
+    #[test]
+    fn writes_folded_stacks() {
+        let mut folded = Vec::new();
+        profile().write_folded(&mut folded).unwrap();
+        assert_eq!(
+            String::from_utf8(folded).unwrap(),
+            "0x200 4\n0x200;0x206 4\n0x200;0x206;0x20A 4\n"
+        );
+    }
+}
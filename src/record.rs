@@ -0,0 +1,249 @@
+//! Provides gameplay recording through [`Recorder`], which encodes the screens
+//! shown by a [`Filter`] in a [`Palette`]'s colors to an animated GIF or a
+//! sequence of PNG frames (see [`RecordFormat`]), scaled up by a whole factor.
+
+use crate::{
+    audio::FRAME_RATE,
+    color::Palette,
+    filter::Filter,
+    screen::{HEIGHT, WIDTH},
+};
+use gif::{Encoder as GifEncoder, EncodingError as GifError, Frame, Repeat};
+use png::{BitDepth, ColorType, Encoder as PngEncoder, EncodingError as PngError};
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    fs::{self, File},
+    io::{BufWriter, Error as IoError},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The shortest delay between GIF frames (in hundredths of a second) that
+/// viewers respect; shorter frames are skipped.
+const MIN_GIF_DELAY: u32 = 2;
+
+/// How quickly (from 1 to 30) GIF frames with too many colors are quantized,
+/// trading quality for speed.
+const GIF_SPEED: i32 = 10;
+
+/// The formats gameplay can be recorded in.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RecordFormat {
+    /// An animated GIF, skipping frames that repeat the previous one.
+    #[default]
+    Gif,
+    /// A directory of numbered PNG files, one for every frame, which can be
+    /// encoded into a video later.
+    Png,
+}
+
+impl RecordFormat {
+    /// Returns the file extension of recordings in this format (empty for PNG
+    /// sequences, which are directories).
+    pub const fn extension(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Png => "",
+        }
+    }
+}
+
+impl Display for RecordFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        match self {
+            Self::Gif => write!(f, "gif"),
+            Self::Png => write!(f, "png"),
+        }
+    }
+}
+
+/// Records frames to a GIF or PNG sequence.
+pub struct Recorder {
+    path: PathBuf,
+    scale: u32,
+    width: u16,
+    height: u16,
+    frames: u32,
+    gif: Option<GifRecording>,
+}
+
+/// The state of a GIF being recorded: frames are only written once the next
+/// different frame is known, so that their delay can be set.
+struct GifRecording {
+    encoder: GifEncoder<BufWriter<File>>,
+    pending: Option<(Vec<u8>, u32)>,
+}
+
+impl Recorder {
+    /// Attempts to start recording in the given format to the given path (a
+    /// file for GIFs, or a directory for PNG sequences), with every pixel scaled
+    /// up by the given factor. An error is returned if that makes frames wider
+    /// or taller than 65535 pixels.
+    pub fn new(path: &Path, format: RecordFormat, scale: u32) -> Result<Self, RecordError> {
+        let scale = scale.max(1);
+        let (width, height) = Self::size(scale).ok_or(RecordError::TooLarge(scale))?;
+        let gif = match format {
+            RecordFormat::Gif => {
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = GifEncoder::new(file, width, height, &[])?;
+                encoder.set_repeat(Repeat::Infinite)?;
+                Some(GifRecording {
+                    encoder,
+                    pending: None,
+                })
+            }
+            RecordFormat::Png => {
+                fs::create_dir_all(path)?;
+                None
+            }
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            scale,
+            width,
+            height,
+            frames: 0,
+            gif,
+        })
+    }
+
+    /// Returns the path being recorded to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns how many frames have been recorded.
+    pub const fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Attempts to record a frame of what the filter shows in the palette's
+    /// colors. This should be done once every frame (60 times a second).
+    pub fn frame(&mut self, filter: &Filter, palette: &Palette) -> Result<(), RecordError> {
+        let rgb = self.render(filter, palette);
+        let frame = self.frames;
+        self.frames += 1;
+        match &mut self.gif {
+            Some(gif) => {
+                let time = Self::centiseconds(frame);
+                match gif.pending.take() {
+                    Some((pending, start)) if pending == rgb => {
+                        gif.pending = Some((pending, start))
+                    }
+                    Some((_, start)) if time - start < MIN_GIF_DELAY => {
+                        gif.pending = Some((rgb, start))
+                    }
+                    Some((pending, start)) => {
+                        gif.write(&pending, time - start, self.width, self.height)?;
+                        gif.pending = Some((rgb, time));
+                    }
+                    None => gif.pending = Some((rgb, time)),
+                }
+            }
+            None => {
+                let path = self.path.join(format!("frame-{:06}.png", frame));
+                let mut encoder = PngEncoder::new(
+                    BufWriter::new(File::create(path)?),
+                    self.width.into(),
+                    self.height.into(),
+                );
+                encoder.set_color(ColorType::Rgb);
+                encoder.set_depth(BitDepth::Eight);
+                encoder.write_header()?.write_image_data(&rgb)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts to finish the recording, writing any frame that's still
+    /// pending.
+    pub fn finish(mut self) -> Result<(), RecordError> {
+        if let Some(mut gif) = self.gif.take() {
+            if let Some((pending, start)) = gif.pending.take() {
+                let delay = (Self::centiseconds(self.frames) - start).max(MIN_GIF_DELAY);
+                gif.write(&pending, delay, self.width, self.height)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the RGB pixels of what the filter shows, scaled up.
+    fn render(&self, filter: &Filter, palette: &Palette) -> Vec<u8> {
+        let scale = self.scale as usize;
+        let mut rgb = Vec::with_capacity(WIDTH * HEIGHT * scale * scale * 3);
+        for y in 0..HEIGHT * scale {
+            for x in 0..WIDTH * scale {
+                rgb.extend(palette.blend(filter.intensity(x / scale, y / scale)));
+            }
+        }
+        rgb
+    }
+
+    /// Returns the size of recorded frames at the given scale, if it fits
+    /// the 16 bits GIFs have for it.
+    fn size(scale: u32) -> Option<(u16, u16)> {
+        let length = |pixels: usize| {
+            u32::try_from(pixels)
+                .ok()?
+                .checked_mul(scale)
+                .and_then(|length| u16::try_from(length).ok())
+        };
+        Some((length(WIDTH)?, length(HEIGHT)?))
+    }
+
+    /// Returns when the given frame starts, in hundredths of a second.
+    const fn centiseconds(frame: u32) -> u32 {
+        frame * 100 / FRAME_RATE
+    }
+}
+
+impl GifRecording {
+    /// Attempts to write the RGB pixels of the given size as a frame lasting
+    /// the given number of hundredths of a second (split into several frames
+    /// if that's longer than a GIF frame can last).
+    fn write(
+        &mut self,
+        rgb: &[u8],
+        delay: u32,
+        width: u16,
+        height: u16,
+    ) -> Result<(), RecordError> {
+        let mut frame = Frame::from_rgb_speed(width, height, rgb, GIF_SPEED);
+        let mut delay = delay;
+        while delay > u32::from(u16::MAX) {
+            frame.delay = u16::MAX;
+            self.encoder.write_frame(&frame)?;
+            delay -= u32::from(u16::MAX);
+        }
+        frame.delay = delay as u16;
+        self.encoder.write_frame(&frame)?;
+        Ok(())
+    }
+}
+
+/// Used to describe possible errors when recording.
+#[derive(Debug, Error)]
+pub enum RecordError {
+    #[error("{0}.")]
+    Io(#[from] IoError),
+    #[error("{0}")]
+    Gif(#[from] GifError),
+    #[error("{0}")]
+    Png(#[from] PngError),
+    #[error("Recordings can't be scaled up {0} times (frames can't be larger than 65535 pixels).")]
+    TooLarge(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_frames_too_large_for_gifs() {
+        let path = Path::new("too-large.gif");
+        let err = Recorder::new(path, RecordFormat::Gif, 1024).err();
+        assert!(matches!(err, Some(RecordError::TooLarge(1024))));
+        assert!(!path.exists());
+        assert_eq!(Recorder::size(1023), Some((65472, 32736)));
+    }
+}
@@ -0,0 +1,889 @@
+//! Provides a generic [`Runner`] driving any [`Frontend`]: it runs the emulator
+//! at 60 frames a second, maps input, handles hotkeys (switching palettes,
+//! muting, pausing, and recording), and takes care of the display filter, the
+//! synthesizer, cheats, input movies, recordings, profiling, and coverage.
+//! Errors are represented by [`RunnerError`].
+
+#[cfg(all(feature = "jit", target_arch = "x86_64"))]
+use crate::chip8::jit::{Jit, JitError};
+use crate::{
+    args::{self, Args, ArgsError},
+    audio::Synth,
+    cheats::{CheatError, Cheats},
+    chip8::{Chip8, Chip8Error},
+    color::{Palette, PalettePreset},
+    config::{Config, Settings},
+    coverage::{Coverage, CoverageError, Symbols},
+    filter::Filter,
+    frontend::{Frontend, Input, Status},
+    hotkeys::Hotkey,
+    movie::{Movie, MovieError},
+    profiler::Profiler,
+    record::{RecordError, Recorder},
+    wav::WavWriter,
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Error as IoError},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use thiserror::Error;
+
+/// How long a frame lasts (the emulator runs at 60 frames a second).
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+/// The input movie being replayed, and the one being recorded along with the
+/// path it's saved to.
+type Movies = (Option<Movie>, Option<(Movie, PathBuf)>);
+
+/// The coverage being tracked, the symbols labelling it, and the path it's
+/// written to.
+type Tracked = (Coverage, Symbols, PathBuf);
+
+/// Runs the emulator with a frontend.
+pub struct Runner<F: Frontend> {
+    frontend: F,
+    config: Config,
+    cli: Settings,
+    settings: Settings,
+    rom: Option<PathBuf>,
+    chip8: Chip8,
+    input: Input,
+    filter: Filter,
+    palette: Palette,
+    preset: Option<PalettePreset>,
+    synth: Synth,
+    cheats: Cheats,
+    paused: bool,
+    pacing: bool,
+    next_frame: Instant,
+    frame: usize,
+    playback: Option<Movie>,
+    movie: Option<(Movie, PathBuf)>,
+    recorder: Option<Recorder>,
+    wav: Option<WavWriter<BufWriter<File>>>,
+    profile: Option<(Profiler, PathBuf)>,
+    coverage: Option<Tracked>,
+    status: Option<Status>,
+    #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+    jit: Option<Jit>,
+}
+
+impl<F: Frontend> Runner<F> {
+    /// Attempts to set up a runner from the given arguments: the ROM (or the
+    /// frontend's placeholder), the config file and settings from the command
+    /// line (which take precedence), cheats, input movies, recordings,
+    /// profiling, and coverage. The frontend is constructed from the resulting
+    /// settings. Options only headless frontends support are rejected for
+    /// others.
+    pub fn new(
+        args: &Args,
+        frontend: impl FnOnce(&Settings) -> Result<F, F::Error>,
+    ) -> Result<Self, F::Error> {
+        match (F::HEADLESS, args.analyze, args.frames) {
+            (false, true, _) => return Err(RunnerError::HeadlessOnly("--analyze").into()),
+            (false, _, Some(_)) => return Err(RunnerError::HeadlessOnly("--frames").into()),
+            _ => (),
+        }
+        let config = args.config().map_err(RunnerError::from)?;
+        let cli = args.settings().map_err(RunnerError::from)?;
+        let (mut chip8, mut settings) = match (&args.path, F::PLACEHOLDER_ROM) {
+            (Some(rom), _) => args::load_rom(rom, &config, &cli).map_err(RunnerError::from)?,
+            (None, Some(placeholder)) => {
+                let settings = config
+                    .settings_for(None)
+                    .map_err(|err| RunnerError::from(ArgsError::from(err)))?
+                    .merge(cli.clone());
+                let chip8 = Chip8::new(placeholder).map_err(RunnerError::from)?;
+                (chip8, settings)
+            }
+            (None, None) => return Err(RunnerError::NoRom.into()),
+        };
+        let cheats = match (&args.cheats, &args.path) {
+            (Some(cheats), _) => Cheats::load(cheats).map_err(RunnerError::from)?,
+            (None, Some(rom)) => Cheats::load_for_rom(rom).map_err(RunnerError::from)?,
+            (None, None) => Cheats::new(),
+        };
+        if args.record_movie.is_some() && cheats.cheats().iter().any(|cheat| cheat.enabled) {
+            return Err(RunnerError::MovieWithCheats.into());
+        }
+        let (playback, movie) = Self::movies(args, &mut chip8, &mut settings)?;
+        let profile = args.profile.clone().map(|path| (Profiler::new(), path));
+        let coverage = Self::coverage(args, F::PLACEHOLDER_ROM)?;
+        chip8.set_tracing(profile.is_some() || coverage.is_some());
+        let frontend = frontend(&settings)?;
+        let mut synth = settings.synth(frontend.sample_rate());
+        synth.set_seed(chip8.seed());
+        let wav = args
+            .wav
+            .as_deref()
+            .map(|path| WavWriter::create(path, frontend.sample_rate()))
+            .transpose()
+            .map_err(RunnerError::from)?;
+        let recorder = args
+            .record
+            .as_deref()
+            .map(|path| Recorder::new(path, settings.record_format(), settings.scale()))
+            .transpose()
+            .map_err(RunnerError::from)?;
+
+        let mut runner = Self {
+            input: Input::new(&settings),
+            filter: settings.filter(),
+            palette: settings
+                .palette()
+                .map_err(|err| RunnerError::from(ArgsError::from(err)))?,
+            preset: settings.palette_preset(),
+            synth,
+            cheats,
+            frontend,
+            config,
+            cli,
+            settings,
+            rom: args.path.clone(),
+            chip8,
+            paused: false,
+            pacing: true,
+            next_frame: Instant::now(),
+            frame: 0,
+            playback,
+            movie,
+            recorder,
+            wav,
+            profile,
+            coverage,
+            status: None,
+            #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+            jit: None,
+        };
+        #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+        if F::HEADLESS {
+            match Jit::new() {
+                Ok(jit) => runner.jit = Some(jit),
+                Err(err) => runner.frontend.report(&err.into()),
+            }
+        }
+        runner.update_status()?;
+        Ok(runner)
+    }
+
+    /// Attempts to load the input movie to replay and start the one to record
+    /// given by the arguments, if there are any, preparing the emulator for
+    /// them.
+    fn movies(
+        args: &Args,
+        chip8: &mut Chip8,
+        settings: &mut Settings,
+    ) -> Result<Movies, RunnerError> {
+        let rom = match (&args.path, &args.play_movie, &args.record_movie) {
+            (_, None, None) => return Ok((None, None)),
+            (None, _, _) => return Err(RunnerError::MovieWithoutRom),
+            (Some(rom), _, _) => args::read_rom(rom)?,
+        };
+        let playback = args.play_movie.as_deref().map(Movie::load).transpose()?;
+        if let Some(playback) = &playback {
+            playback.play(chip8, &rom)?;
+            settings.speed = Some(playback.speed);
+        }
+        let movie = args.record_movie.clone().map(|path| {
+            let movie = match &playback {
+                Some(playback) => playback.rerecord(),
+                None => Movie::record(chip8, &rom, settings.speed()),
+            };
+            (movie, path)
+        });
+        Ok((playback, movie))
+    }
+
+    /// Attempts to start tracking coverage of the ROM (or the placeholder) if
+    /// the arguments ask for it, reading the symbol map labelling it.
+    fn coverage(args: &Args, placeholder: Option<&[u8]>) -> Result<Option<Tracked>, RunnerError> {
+        let Some(path) = args.coverage.clone() else {
+            return Ok(None);
+        };
+        let rom = match &args.path {
+            Some(rom) => args::read_rom(rom)?,
+            None => placeholder.unwrap_or_default().to_vec(),
+        };
+        let symbols = match &args.symbols {
+            Some(symbols) => Symbols::load(symbols)?,
+            None => Symbols::new(),
+        };
+        Ok(Some((Coverage::new(&rom), symbols, path)))
+    }
+
+    /// Returns the frontend.
+    pub const fn frontend(&self) -> &F {
+        &self.frontend
+    }
+
+    /// Returns the frontend mutably.
+    pub fn frontend_mut(&mut self) -> &mut F {
+        &mut self.frontend
+    }
+
+    /// Returns the emulator.
+    pub const fn chip8(&self) -> &Chip8 {
+        &self.chip8
+    }
+
+    /// Returns the cheats for the current ROM.
+    pub const fn cheats(&self) -> &Cheats {
+        &self.cheats
+    }
+
+    /// Returns the cheats for the current ROM mutably (like to add ones found
+    /// with a [`Search`](crate::cheats::Search)).
+    pub fn cheats_mut(&mut self) -> &mut Cheats {
+        &mut self.cheats
+    }
+
+    /// Returns the settings for the current ROM.
+    pub const fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Sets whether frames are paced to run 60 times a second (the default), or
+    /// run as fast as possible (like when running headlessly).
+    pub fn set_pacing(&mut self, pacing: bool) {
+        self.pacing = pacing;
+    }
+
+    /// Returns how many frames long the input movie being replayed is, if
+    /// there is one.
+    pub fn playback_len(&self) -> Option<usize> {
+        Some(self.playback.as_ref()?.len())
+    }
+
+    /// Runs frames until the frontend wants to quit or something fails,
+    /// finishing recordings either way.
+    pub fn run(&mut self) -> Result<(), F::Error> {
+        let mut result = Ok(());
+        while result.is_ok() && !self.frontend.should_quit() {
+            result = self.frame();
+        }
+        let finished = self.finish();
+        result.and(finished.map_err(F::Error::from))
+    }
+
+    /// Runs the given number of frames (stopping early if the frontend wants to
+    /// quit or something fails), finishing recordings either way.
+    pub fn run_frames(&mut self, frames: usize) -> Result<(), F::Error> {
+        let mut result = Ok(());
+        for _ in 0..frames {
+            if result.is_err() || self.frontend.should_quit() {
+                break;
+            }
+            result = self.frame();
+        }
+        let finished = self.finish();
+        result.and(finished.map_err(F::Error::from))
+    }
+
+    /// Runs a frame: waits for it (when pacing), handles input, hotkeys, and
+    /// toggled cheats (which can't be enabled while recording an input movie),
+    /// runs the emulator for as many instructions as the speed
+    /// setting says (unless paused), and gets the frontend to draw and play it.
+    pub fn frame(&mut self) -> Result<(), F::Error> {
+        self.wait()?;
+        for hotkey in self.input.take_hotkeys() {
+            self.hotkey_handler(hotkey)?;
+        }
+        if let Some(rom) = self.input.take_rom() {
+            if let Err(err) = self.load_rom(&rom) {
+                self.frontend.report(&err);
+            }
+        }
+        for index in self.input.take_cheats() {
+            // Cheats aren't part of input movies, so they'd be lost on replay.
+            let disabled = self
+                .cheats
+                .cheats()
+                .get(index)
+                .is_some_and(|cheat| !cheat.enabled);
+            if disabled && self.movie.is_some() {
+                self.frontend
+                    .message("Cheats can't be enabled while recording a movie.");
+                continue;
+            }
+            let Some(enabled) = self.cheats.toggle(index) else {
+                continue;
+            };
+            let state = if enabled { "enabled" } else { "disabled" };
+            let name = &self.cheats.cheats()[index].name;
+            self.frontend.message(&format!("Cheat {} {}.", name, state));
+        }
+        self.update_status()?;
+        if !self.paused {
+            self.emulate()?;
+        }
+        self.synth.set_playing(!self.paused && self.chip8.beeping());
+        if !self.paused {
+            self.chip8.decrement_timers();
+            self.filter.frame();
+            if let Some(recorder) = &mut self.recorder {
+                recorder
+                    .frame(&self.filter, &self.palette)
+                    .map_err(RunnerError::from)?;
+            }
+        }
+        self.frontend.present(&self.filter, &self.palette)?;
+        let samples = self.synth.frame();
+        if let Some(wav) = &mut self.wav {
+            wav.write(&samples).map_err(RunnerError::from)?;
+        }
+        let beeping = !self.paused && !self.synth.muted() && self.chip8.beeping();
+        self.frontend.set_sound(beeping, &samples)
+    }
+
+    /// Handles input until it's time for the next frame (or just once, when
+    /// not pacing).
+    fn wait(&mut self) -> Result<(), F::Error> {
+        if !self.pacing {
+            return self.frontend.poll_input(&mut self.input, Instant::now());
+        }
+        loop {
+            self.frontend.poll_input(&mut self.input, self.next_frame)?;
+            let now = Instant::now();
+            if now >= self.next_frame || self.frontend.should_quit() {
+                self.next_frame += FRAME;
+                if self.next_frame < now {
+                    self.next_frame = now + FRAME;
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    /// Executes up to the given number of instructions like [`Chip8::run`],
+    /// with the JIT if there is one (only headless frontends have it).
+    fn execute(&mut self, cycles: usize) -> Result<(usize, bool), Chip8Error> {
+        #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+        if let Some(jit) = &mut self.jit {
+            return jit.run(&mut self.chip8, self.input.keys(), cycles);
+        }
+        self.chip8.run(self.input.keys(), cycles)
+    }
+
+    /// Runs the emulator for as many instructions as the speed setting says,
+    /// applying cheats first (disabling and reporting any that fail), replaying
+    /// and recording input movies, and profiling and tracking coverage.
+    fn emulate(&mut self) -> Result<(), RunnerError> {
+        if let Some(playback) = &self.playback {
+            match playback.keys(self.frame) {
+                Some(keys) => self.input.set_keys(keys),
+                None => {
+                    self.playback = None;
+                    self.input.release_all();
+                    self.frontend.message("Movie finished.");
+                }
+            }
+        }
+        if let Some((movie, _)) = &mut self.movie {
+            movie.push(self.input.keys());
+        }
+        self.frame += 1;
+        for err in self.cheats.apply(&mut self.chip8) {
+            self.frontend.report(&err.into());
+        }
+        // Instructions are run one at a time while tracing, so that every one
+        // is seen.
+        let batch = match self.profile.is_some() || self.coverage.is_some() {
+            true => 1,
+            false => usize::MAX,
+        };
+        let mut cycles = self.settings.speed() as usize;
+        while cycles > 0 {
+            let run = self.execute(cycles.min(batch));
+            if let Some(trace) = self.chip8.trace() {
+                if let Some((profiler, _)) = &mut self.profile {
+                    profiler.record(&trace);
+                }
+                if let Some((coverage, ..)) = &mut self.coverage {
+                    coverage.record(&trace);
+                }
+            }
+            let (executed, drew) = run?;
+            if drew {
+                self.filter.draw(self.chip8.screen());
+            }
+            self.input.reset_last_pressed();
+            cycles -= executed;
+        }
+        if let Some((profiler, _)) = &mut self.profile {
+            profiler.end_frame();
+        }
+        Ok(())
+    }
+
+    /// Performs the action of the pressed hotkey, passing those the runner
+    /// doesn't handle to the frontend.
+    fn hotkey_handler(&mut self, hotkey: Hotkey) -> Result<(), F::Error> {
+        match hotkey {
+            Hotkey::CyclePalette => {
+                let preset = self
+                    .preset
+                    .map_or(PalettePreset::ALL[0], |preset| preset.next());
+                match self.settings.apply_colors(preset.palette()) {
+                    Ok(palette) => self.palette = palette,
+                    Err(err) => self.frontend.report(&ArgsError::from(err).into()),
+                }
+                self.preset = Some(preset);
+            }
+            Hotkey::Mute => self.synth.set_muted(!self.synth.muted()),
+            Hotkey::Pause => self.paused = !self.paused,
+            Hotkey::Record if self.recorder.is_some() => {
+                if let Err(err) = self.stop_recording() {
+                    self.frontend.report(&err);
+                }
+            }
+            Hotkey::Record => self.start_recording(),
+            _ => self.frontend.hotkey(hotkey, &mut self.input)?,
+        }
+        Ok(())
+    }
+
+    /// Attempts to replace the running emulator with one using the ROM at the
+    /// given path (along with its cheats), stopping any input movies (failing
+    /// to save the one being recorded is reported, but otherwise ignored).
+    pub fn load_rom(&mut self, path: &Path) -> Result<(), RunnerError> {
+        let (chip8, settings) = args::load_rom(path, &self.config, &self.cli)?;
+        let palette = settings.palette().map_err(ArgsError::from)?;
+        let cheats = Cheats::load_for_rom(path)?;
+        if let Err(err) = self.stop_movies() {
+            self.frontend.report(&err);
+        }
+        if let Some((coverage, ..)) = &mut self.coverage {
+            *coverage = Coverage::new(&args::read_rom(path)?);
+        }
+        self.chip8 = chip8;
+        self.chip8
+            .set_tracing(self.profile.is_some() || self.coverage.is_some());
+        self.cheats = cheats;
+        self.rom = Some(path.to_path_buf());
+        self.input.apply_settings(&settings);
+        self.filter = settings.filter();
+        self.palette = palette;
+        self.preset = settings.palette_preset();
+        let muted = self.synth.muted();
+        self.synth = settings.synth(self.frontend.sample_rate());
+        self.synth.set_seed(self.chip8.seed());
+        self.synth.set_muted(muted);
+        self.settings = settings;
+        self.paused = false;
+        Ok(())
+    }
+
+    /// Starts recording gameplay to a file (or directory) in the current
+    /// directory named after the current time. Failing to start isn't fatal;
+    /// the error is reported.
+    fn start_recording(&mut self) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let format = self.settings.record_format();
+        let path = PathBuf::from(format!("biscuit8-{}", time)).with_extension(format.extension());
+        match Recorder::new(&path, format, self.settings.scale()) {
+            Ok(recorder) => {
+                self.frontend
+                    .message(&format!("Recording to {}.", path.display()));
+                self.recorder = Some(recorder);
+            }
+            Err(err) => self.frontend.report(&err.into()),
+        }
+    }
+
+    /// Attempts to stop recording gameplay, if it's being recorded, finishing
+    /// the recording.
+    fn stop_recording(&mut self) -> Result<(), RunnerError> {
+        let Some(recorder) = self.recorder.take() else {
+            return Ok(());
+        };
+        let path = recorder.path().to_path_buf();
+        recorder.finish()?;
+        self.frontend
+            .message(&format!("Recorded to {}.", path.display()));
+        Ok(())
+    }
+
+    /// Attempts to stop replaying and recording input movies, saving the one
+    /// being recorded.
+    fn stop_movies(&mut self) -> Result<(), RunnerError> {
+        self.playback = None;
+        self.frame = 0;
+        let Some((movie, path)) = self.movie.take() else {
+            return Ok(());
+        };
+        movie.save(&path)?;
+        self.frontend
+            .message(&format!("Recorded movie to {}.", path.display()));
+        Ok(())
+    }
+
+    /// Attempts to finish the recording, save the input movie being recorded,
+    /// and write the profiling and coverage reports, which is done once the
+    /// runner stops running.
+    pub fn finish(&mut self) -> Result<(), RunnerError> {
+        let recording = self.stop_recording();
+        let movie = self.stop_movies();
+        let profile = self.save_profile();
+        let coverage = self.save_coverage();
+        recording.and(movie).and(profile).and(coverage)
+    }
+
+    /// Attempts to write the profiling report, if profiling.
+    fn save_profile(&mut self) -> Result<(), RunnerError> {
+        let Some((profiler, path)) = self.profile.take() else {
+            return Ok(());
+        };
+        profiler.save(&path)?;
+        self.frontend
+            .message(&format!("Wrote profiling report to {}.", path.display()));
+        Ok(())
+    }
+
+    /// Attempts to write the coverage report, if tracking coverage.
+    fn save_coverage(&mut self) -> Result<(), RunnerError> {
+        let Some((coverage, symbols, path)) = self.coverage.take() else {
+            return Ok(());
+        };
+        let rom = self.rom.clone().unwrap_or_default();
+        coverage.save(&path, &rom, &symbols)?;
+        self.frontend.message(&format!(
+            "Wrote coverage report to {} ({}).",
+            path.display(),
+            coverage
+        ));
+        Ok(())
+    }
+
+    /// Tells the frontend about the status of the runner if it changed.
+    fn update_status(&mut self) -> Result<(), F::Error> {
+        let status = Status {
+            rom: self.rom.clone(),
+            paused: self.paused,
+            muted: self.synth.muted(),
+            recording: self.recorder.is_some(),
+            replaying: self.playback.is_some(),
+            cheats: self.cheats.clone(),
+        };
+        if self.status.as_ref() != Some(&status) {
+            self.frontend.status(&status)?;
+            self.status = Some(status);
+        }
+        Ok(())
+    }
+}
+
+/// Used to describe possible errors when running the emulator.
+#[derive(Debug, Error)]
+pub enum RunnerError {
+    #[error("{0}")]
+    Args(#[from] ArgsError),
+    #[error("{0}")]
+    Cheat(#[from] CheatError),
+    #[error("{0}")]
+    Chip8(#[from] Chip8Error),
+    #[error("{0}")]
+    Coverage(#[from] CoverageError),
+    #[error("{0}.")]
+    Io(#[from] IoError),
+    #[cfg(all(feature = "jit", target_arch = "x86_64"))]
+    #[error("{0}")]
+    Jit(#[from] JitError),
+    #[error("{0}")]
+    Movie(#[from] MovieError),
+    #[error("{0}")]
+    Record(#[from] RecordError),
+    #[error("A ROM is required.")]
+    NoRom,
+    #[error("Input movies need a ROM.")]
+    MovieWithoutRom,
+    #[error("Input movies can't be recorded with cheats enabled.")]
+    MovieWithCheats,
+    #[error("{0} is only supported by headless frontends.")]
+    HeadlessOnly(&'static str),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cheats::{Cheat, Target},
+        chip8::RAM_SIZE,
+        keys::Keys,
+        screen::Screen,
+    };
+    use argh::FromArgs;
+    use std::fs;
+
+    /// A frontend with a window (that isn't shown).
+    struct Windowed;
+
+    impl Frontend for Windowed {
+        type Error = RunnerError;
+
+        fn poll_input(
+            &mut self,
+            _input: &mut Input,
+            _deadline: Instant,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn present(&mut self, _filter: &Filter, _palette: &Palette) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// A frontend (with a window that isn't shown) that holds a different key
+    /// down every few frames and keeps the messages it's given.
+    #[derive(Default)]
+    struct Scripted {
+        frame: usize,
+        messages: Vec<String>,
+        reports: Vec<String>,
+    }
+
+    impl Frontend for Scripted {
+        type Error = RunnerError;
+
+        fn poll_input(&mut self, input: &mut Input, _deadline: Instant) -> Result<(), Self::Error> {
+            let key = (self.frame / 4 % 16) as u8;
+            input.set_keys(match self.frame % 4 < 2 {
+                true => Keys::from_raw(1 << key, Some(key)),
+                false => Keys::new(),
+            });
+            self.frame += 1;
+            Ok(())
+        }
+
+        fn present(&mut self, _filter: &Filter, _palette: &Palette) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn message(&mut self, message: &str) {
+            self.messages.push(message.to_string());
+        }
+
+        fn report(&mut self, error: &RunnerError) {
+            self.reports.push(error.to_string());
+        }
+    }
+
+    /// A headless frontend.
+    struct Headless;
+
+    impl Frontend for Headless {
+        type Error = RunnerError;
+
+        const HEADLESS: bool = true;
+
+        fn poll_input(
+            &mut self,
+            _input: &mut Input,
+            _deadline: Instant,
+        ) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn present(&mut self, _filter: &Filter, _palette: &Palette) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn message(&mut self, _message: &str) {}
+    }
+
+    /// Creates an empty directory for a test's files, with an empty config
+    /// file so that the user's isn't read.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("biscuit8-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("config.toml"), "").unwrap();
+        dir
+    }
+
+    /// Parses the given options, with the test directory's config file.
+    fn args(dir: &Path, options: &[&str]) -> Args {
+        let config = dir.join("config.toml");
+        let mut all = vec!["--config", config.to_str().unwrap()];
+        all.extend_from_slice(options);
+        Args::from_args(&["biscuit8"], &all).unwrap()
+    }
+
+    #[test]
+    fn movies_replay_recorded_input() {
+        let dir = test_dir("movie");
+        let rom = dir.join("rom.ch8");
+        let movie = dir.join("movie.toml");
+        // Waits for a key, then draws its digit somewhere random.
+        fs::write(
+            &rom,
+            [0xF0, 0x0A, 0xC1, 0xFF, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x00],
+        )
+        .unwrap();
+        let (rom, movie) = (rom.to_str().unwrap(), movie.to_str().unwrap());
+
+        let args = args(&dir, &["--record-movie", movie, rom]);
+        let mut recording = Runner::new(&args, |_| Ok(Scripted::default())).unwrap();
+        recording.set_pacing(false);
+        recording.run_frames(120).unwrap();
+        let recorded = recording.chip8().state();
+
+        let args = self::args(&dir, &["--play-movie", movie, rom]);
+        let mut replaying = Runner::new(&args, |_| Ok(Windowed)).unwrap();
+        replaying.set_pacing(false);
+        assert_eq!(replaying.playback_len(), Some(120));
+        replaying.run_frames(120).unwrap();
+        let replayed = replaying.chip8().state();
+
+        assert_ne!(recorded.screen, Screen::new());
+        assert_eq!(replayed.screen, recorded.screen);
+        assert_eq!(replayed.v, recorded.v);
+        assert_eq!(replayed.i, recorded.i);
+        assert_eq!(replayed.pc, recorded.pc);
+        assert_eq!(replayed.ram, recorded.ram);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cheats_are_refused_while_recording_movies() {
+        let dir = test_dir("movie-cheats");
+        let rom = dir.join("rom.ch8");
+        let cheats = dir.join("cheats.toml");
+        let movie = dir.join("movie.toml");
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        let (rom, cheats, movie) = (
+            rom.to_str().unwrap(),
+            cheats.to_str().unwrap(),
+            movie.to_str().unwrap(),
+        );
+        let options = ["--cheats", cheats, "--record-movie", movie, rom];
+
+        fs::write(
+            cheats,
+            "[[cheat]]\nname = \"Lives\"\nregister = 3\nvalue = 9\n",
+        )
+        .unwrap();
+        let err = Runner::new(&args(&dir, &options), |_| Ok(Windowed)).err();
+        assert!(matches!(err, Some(RunnerError::MovieWithCheats)));
+
+        fs::write(
+            cheats,
+            "[[cheat]]\nname = \"Lives\"\nregister = 3\nvalue = 9\nenabled = false\n",
+        )
+        .unwrap();
+        let mut runner = Runner::new(&args(&dir, &options), |_| Ok(Scripted::default())).unwrap();
+        runner.set_pacing(false);
+        runner.input.toggle_cheat(0);
+        runner.frame().unwrap();
+        assert!(!runner.cheats().cheats()[0].enabled);
+        assert_eq!(runner.chip8().state().v[3], 0);
+        assert_eq!(
+            runner.frontend().messages,
+            ["Cheats can't be enabled while recording a movie."]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failing_cheats_are_reported_and_disabled() {
+        let dir = test_dir("cheats");
+        let rom = dir.join("rom.ch8");
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        let args = args(&dir, &[rom.to_str().unwrap()]);
+        let mut runner = Runner::new(&args, |_| Ok(Scripted::default())).unwrap();
+        runner.set_pacing(false);
+        for (name, target) in [
+            ("Broken", Target::Address(RAM_SIZE)),
+            ("Lives", Target::Register(3)),
+        ] {
+            runner.cheats.push(Cheat {
+                name: name.to_string(),
+                target,
+                value: 9,
+                enabled: true,
+            });
+        }
+        runner.run_frames(2).unwrap();
+        assert_eq!(
+            runner.frontend().reports,
+            [
+                CheatError::Disabled("Broken".to_string(), Chip8Error::InvalidAddress(RAM_SIZE))
+                    .to_string()
+            ]
+        );
+        let enabled = runner.cheats().cheats().iter().map(|cheat| cheat.enabled);
+        assert_eq!(enabled.collect::<Vec<_>>(), [false, true]);
+        assert_eq!(runner.chip8().state().v[3], 9);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn coverage_reports_are_written() {
+        let dir = test_dir("coverage");
+        let (rom, symbols) = (dir.join("rom.ch8"), dir.join("rom.sym"));
+        let (report, lcov) = (dir.join("coverage.txt"), dir.join("coverage.info"));
+        // Draws the sprite after the loop, which never runs.
+        fs::write(&rom, [0xA2, 0x08, 0xD0, 0x01, 0x12, 0x04, 0x00, 0xE0, 0x80]).unwrap();
+        fs::write(
+            &symbols,
+            "main 0x200
+sprite 0x208
+",
+        )
+        .unwrap();
+        let [rom, symbols, report, lcov] =
+            [&rom, &symbols, &report, &lcov].map(|path| path.to_str().unwrap());
+
+        for output in [report, lcov] {
+            let options = [
+                "--coverage",
+                output,
+                "--symbols",
+                symbols,
+                "--frames",
+                "1",
+                rom,
+            ];
+            let mut runner = Runner::new(&args(&dir, &options), |_| Ok(Headless)).unwrap();
+            runner.set_pacing(false);
+            runner.run_frames(1).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(report).unwrap(),
+            "; 9 ROM bytes: 6 executed (66.7%), 1 read (11.1%), 0 written (0.0%)\n\
+             main:\n\
+             \x20 X--  0x200  A208      ANNN     1x\n\
+             \x20 X--  0x202  D001      DXYN     1x\n\
+             \x20 X--  0x204  1204      1NNN     8x\n\
+             \x20 ---  0x206  00        00000000\n\
+             \x20 ---  0x207  E0        11100000\n\
+             sprite:\n\
+             \x20 -R-  0x208  80        10000000\n"
+        );
+        assert_eq!(
+            fs::read_to_string(lcov).unwrap(),
+            "TN:\nSF:{rom}\nFN:512,main\nFN:520,sprite\nFNDA:1,main\nFNDA:0,sprite\n\
+             FNF:2\nFNH:1\nDA:512,1\nDA:514,1\nDA:516,8\nDA:518,0\n\
+             LF:4\nLH:3\nend_of_record\n"
+                .replace("{rom}", rom)
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn headless_options_are_rejected() {
+        for (options, rejected) in [
+            (&["--analyze", "rom.ch8"][..], "--analyze"),
+            (&["--frames", "60", "rom.ch8"][..], "--frames"),
+        ] {
+            let args = Args::from_args(&["biscuit8"], options).unwrap();
+            let err = Runner::new(&args, |_| Ok(Windowed)).err();
+            assert!(matches!(err, Some(RunnerError::HeadlessOnly(option)) if option == rejected));
+        }
+    }
+}
@@ -7,54 +7,92 @@ pub const WIDTH: usize = 64;
 /// The default height of the emulator's screen.
 pub const HEIGHT: usize = 32;
 
-/// Represents the screen of the emulator.
+/// Represents the screen of the emulator, as a row of bits (the leftmost pixel
+/// being the most significant one) per line so that a sprite's row is drawn
+/// all at once.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Screen {
-    raw: [bool; WIDTH * HEIGHT],
+    rows: [u64; HEIGHT],
 }
 
 impl Screen {
     /// Initializes a new screen.
     pub const fn new() -> Self {
-        Self {
-            raw: [false; WIDTH * HEIGHT],
-        }
+        Self { rows: [0; HEIGHT] }
+    }
+
+    /// Draws the given sprite at the specified location, clipping any part of it
+    /// going past the edges. Returns true if a pixel is erased.
+    pub fn draw_sprite(&mut self, sprite: &[u8], x: usize, y: usize) -> bool {
+        self.draw(sprite, x, y, false)
     }
 
-    /// Draws the given sprite at the specified location. Returns true if a pixel is
+    /// Draws the given sprite at the specified location, wrapping any part of it
+    /// going past the edges around to the other side. Returns true if a pixel is
     /// erased.
-    pub fn draw_sprite(&mut self, sprite: &[u8], mut x: usize, mut y: usize) -> bool {
-        x %= WIDTH;
-        y %= HEIGHT;
-        let mut erased = false;
-        for (i, row) in sprite.iter().enumerate() {
-            if y + i >= HEIGHT {
-                break;
-            }
-            for j in 0..8 {
-                if x + j >= WIDTH {
-                    break;
+    pub fn draw_sprite_wrapped(&mut self, sprite: &[u8], x: usize, y: usize) -> bool {
+        self.draw(sprite, x, y, true)
+    }
+
+    /// Draws the given sprite at the specified location, either wrapping or
+    /// clipping it. Returns true if a pixel is erased.
+    #[inline]
+    fn draw(&mut self, sprite: &[u8], x: usize, y: usize, wrap: bool) -> bool {
+        let (x, y) = (x % WIDTH, y % HEIGHT);
+        let row = |byte: &u8| (*byte as u64) << (WIDTH - 8);
+        let mut erased = 0;
+        // Shifting clips the rows at the right edge, and rotating wraps them.
+        match wrap {
+            true => {
+                for (i, byte) in sprite.iter().enumerate() {
+                    let line = &mut self.rows[(y + i) % HEIGHT];
+                    let bits = row(byte).rotate_right(x as u32);
+                    erased |= *line & bits;
+                    *line ^= bits;
                 }
-                let bit = (row & 0b10000000 >> j) << j;
-                let pos = (y + i) * WIDTH + x + j;
-                let pixel = self.raw[pos];
-                self.raw[pos] ^= bit != 0;
-                if pixel && !self.raw[pos] {
-                    erased = true;
+            }
+            false => {
+                for (line, byte) in self.rows[y..].iter_mut().zip(sprite) {
+                    let bits = row(byte) >> x;
+                    erased |= *line & bits;
+                    *line ^= bits;
                 }
             }
         }
-        erased
+        erased != 0
+    }
+
+    /// Lights every pixel that's lit on the given screen.
+    pub fn or(&mut self, other: &Self) {
+        for (row, other) in self.rows.iter_mut().zip(other.rows) {
+            *row |= other;
+        }
     }
 
     /// Clears the screen.
     pub fn clear(&mut self) {
-        self.raw.fill(false);
+        self.rows.fill(0);
+    }
+
+    /// Lights or clears the pixel at the provided position.
+    pub fn set_pixel(&mut self, x: usize, y: usize, lit: bool) {
+        let bit = Self::bit(x);
+        match lit {
+            true => self.rows[y] |= bit,
+            false => self.rows[y] &= !bit,
+        }
     }
 
     /// Returns true if the provided position has a pixel, and false otherwise.
     pub const fn pixel(&self, x: usize, y: usize) -> bool {
-        self.raw[y * WIDTH + x]
+        self.rows[y] & Self::bit(x) != 0
+    }
+
+    /// Returns the bit of a row standing for the pixel in the column (which has
+    /// to be on the screen).
+    const fn bit(x: usize) -> u64 {
+        assert!(x < WIDTH);
+        1 << (WIDTH - 1 - x)
     }
 }
 
@@ -0,0 +1,90 @@
+//! Provides a way to record the samples produced by a [`Synth`](crate::audio::Synth)
+//! to a WAV file through [`WavWriter`], which doesn't need an audio device. The
+//! header is kept up to date after every write, so the file stays valid even if
+//! a frontend exits without finishing it.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Error as IoError, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// The size of a WAV header in bytes.
+const HEADER_SIZE: u32 = 44;
+
+/// How many bits every sample is written with.
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Writes mono 16-bit PCM samples to a WAV file.
+#[derive(Debug)]
+pub struct WavWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    samples: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    /// Attempts to create a WAV file at the given path.
+    pub fn create(path: &Path, sample_rate: u32) -> Result<Self, IoError> {
+        Self::new(BufWriter::new(File::create(path)?), sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Attempts to start writing a WAV file with the given sample rate to the
+    /// writer.
+    pub fn new(writer: W, sample_rate: u32) -> Result<Self, IoError> {
+        let mut wav = Self {
+            writer,
+            sample_rate,
+            samples: 0,
+        };
+        wav.write_header()?;
+        Ok(wav)
+    }
+
+    /// Attempts to append the samples (from -1 to 1) to the file.
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), IoError> {
+        for sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+        self.write_header()?;
+        self.writer.flush()
+    }
+
+    /// Returns how many samples have been written.
+    pub const fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Consumes the WAV writer, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes the header for the samples written so far, going back to the end
+    /// of the file afterwards.
+    fn write_header(&mut self) -> Result<(), IoError> {
+        let block_align = BITS_PER_SAMPLE / 8;
+        let data_size = self.samples * block_align as u32;
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(b"RIFF")?;
+        self.writer
+            .write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.writer.write_all(b"WAVEfmt ")?;
+        self.writer.write_all(&16u32.to_le_bytes())?;
+        self.writer.write_all(&1u16.to_le_bytes())?;
+        self.writer.write_all(&1u16.to_le_bytes())?;
+        self.writer.write_all(&self.sample_rate.to_le_bytes())?;
+        self.writer
+            .write_all(&(self.sample_rate * block_align as u32).to_le_bytes())?;
+        self.writer.write_all(&block_align.to_le_bytes())?;
+        self.writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+        self.writer.write_all(b"data")?;
+        self.writer.write_all(&data_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+//! Checks the beep a known ROM makes against golden WAV files, the way the
+//! runner produces it: the timers are decremented once a frame and the noise is
+//! seeded from the emulator. Set `BISCUIT8_BLESS` to rewrite the golden files
+//! after an intended change to the sound.
+
+#![cfg(feature = "std")]
+
+use biscuit8::{
+    audio::{Synth, Waveform},
+    chip8::Chip8,
+    keys::Keys,
+    wav::WavWriter,
+};
+use std::{env, fs, io::Cursor, path::Path, time::Duration};
+
+/// How many frames the ROM is run for.
+const FRAMES: usize = 120;
+
+/// How many instructions are executed every frame.
+const SPEED: usize = 200;
+
+/// The sample rate the beep is recorded at, kept low so the golden files stay
+/// small.
+const SAMPLE_RATE: u32 = 8000;
+
+/// The seed the emulator is run with.
+const SEED: u64 = 0x8008;
+
+/// Runs the beep ROM, returning the beep recorded as a WAV file.
+fn record(waveform: Waveform) -> Vec<u8> {
+    let mut chip8 = Chip8::new(&fs::read("roms/beep.ch8").unwrap()).unwrap();
+    chip8.set_seed(SEED);
+    let mut synth = Synth::new(
+        waveform,
+        700.0,
+        1.0,
+        Duration::from_millis(5),
+        Duration::from_millis(5),
+        SAMPLE_RATE,
+    );
+    synth.set_seed(chip8.seed());
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), SAMPLE_RATE).unwrap();
+    for _ in 0..FRAMES {
+        let mut cycles = SPEED;
+        while cycles > 0 {
+            cycles -= chip8.run(Keys::new(), cycles).unwrap().0;
+        }
+        chip8.decrement_timers();
+        synth.set_playing(chip8.beeping());
+        wav.write(&synth.frame()).unwrap();
+    }
+    wav.into_inner().into_inner()
+}
+
+/// Compares the beep with the given waveform against its golden file.
+fn check(waveform: Waveform) {
+    let wav = record(waveform);
+    let path = Path::new("tests/golden").join(format!("beep-{waveform}.wav"));
+    if env::var_os("BISCUIT8_BLESS").is_some() {
+        fs::write(&path, &wav).unwrap();
+    }
+    let golden = fs::read(&path).unwrap();
+    assert!(wav == golden, "{} doesn't match", path.display());
+}
+
+#[test]
+fn square() {
+    check(Waveform::Square);
+}
+
+#[test]
+fn noise() {
+    check(Waveform::Noise);
+}
+
+#[test]
+fn noise_follows_seed() {
+    assert_eq!(record(Waveform::Noise), record(Waveform::Noise));
+}
@@ -0,0 +1,116 @@
+//! Cross-checks the JIT against the interpreter: every bundled ROM is run with
+//! both, under every platform's quirks and with scripted input, and the
+//! emulators have to end every frame in exactly the same state.
+
+#![cfg(all(feature = "jit", target_arch = "x86_64"))]
+
+use biscuit8::{
+    chip8::{jit::Jit, Chip8, Chip8Error, Platform, RAM_SIZE},
+    keys::Keys,
+};
+use std::fs;
+
+/// How many frames every ROM is run for.
+const FRAMES: usize = 600;
+
+/// How many instructions are executed every frame.
+const SPEED: usize = 200;
+
+/// Returns the keys held during the frame: a different key every few frames
+/// (pressed on the first one), with gaps where nothing is held.
+fn keys(frame: usize) -> Keys {
+    let key = (frame / 6 * 7 % 17) as u8;
+    match (key, frame % 6) {
+        (0x10, _) | (_, 5) => Keys::new(),
+        (key, 0) => Keys::from_raw(1 << key, Some(key)),
+        (key, _) => Keys::from_raw(1 << key, None),
+    }
+}
+
+/// Runs a frame the way the runner does, returning what every batch of
+/// instructions did.
+fn frame(
+    mut run: impl FnMut(Keys, usize) -> Result<(usize, bool), Chip8Error>,
+    mut keys: Keys,
+) -> Vec<Result<(usize, bool), Chip8Error>> {
+    let mut batches = Vec::new();
+    let mut cycles = SPEED;
+    while cycles > 0 {
+        let batch = run(keys, cycles);
+        batches.push(batch);
+        let Ok((executed, _)) = batch else {
+            break;
+        };
+        keys.reset_last_pressed();
+        cycles -= executed;
+    }
+    batches
+}
+
+#[test]
+fn jit_matches_interpreter() {
+    let mut paths: Vec<_> = fs::read_dir("roms")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let rom = fs::read(&path).unwrap();
+        for platform in [Platform::Chip8, Platform::Schip, Platform::XoChip] {
+            let mut interpreted = Chip8::new(&rom).unwrap();
+            interpreted.set_quirks(platform.quirks());
+            interpreted.set_seed(8);
+            let mut compiled = interpreted.clone();
+            let mut jit = Jit::new().unwrap();
+            for frame_number in 0..FRAMES {
+                let keys = keys(frame_number);
+                let expected = frame(|keys, cycles| interpreted.run(keys, cycles), keys);
+                let actual = frame(|keys, cycles| jit.run(&mut compiled, keys, cycles), keys);
+                let context = format!("{} ({}), frame {}", path.display(), platform, frame_number);
+                assert_eq!(actual, expected, "{}", context);
+                assert!(
+                    compiled.save_state() == interpreted.save_state(),
+                    "{}: the states differ",
+                    context
+                );
+                if expected.last().is_some_and(Result::is_err) {
+                    break;
+                }
+                interpreted.decrement_timers();
+                compiled.decrement_timers();
+            }
+        }
+    }
+}
+
+#[test]
+fn index_register_wraps_around_ram() {
+    // Adds V0 to I past the end of RAM, draws from where it wrapped to, and
+    // loops in place.
+    let rom = [
+        0x60, 0xFF, 0xAF, 0xFF, 0xF0, 0x1E, 0x61, 0x01, 0xD0, 0x15, 0x12, 0x0A,
+    ];
+    let mut interpreted = Chip8::new(&rom).unwrap();
+    let mut compiled = interpreted.clone();
+    let mut jit = Jit::new().unwrap();
+    for _ in 0..2 {
+        let expected = interpreted.run(Keys::new(), SPEED);
+        assert_eq!(jit.run(&mut compiled, Keys::new(), SPEED), expected);
+        assert_eq!(compiled.state().i, 0x0FE);
+        assert!(compiled.save_state() == interpreted.save_state());
+    }
+}
+
+#[test]
+fn sprites_past_the_end_of_ram_fail() {
+    // Draws a sprite running past the end of RAM in the middle of a block.
+    let rom = [0x60, 0x00, 0xAF, 0xFF, 0xD0, 0x05, 0x12, 0x06];
+    let mut interpreted = Chip8::new(&rom).unwrap();
+    let mut compiled = interpreted.clone();
+    let mut jit = Jit::new().unwrap();
+    let expected = interpreted.run(Keys::new(), SPEED);
+    assert_eq!(expected, Err(Chip8Error::InvalidAddress(RAM_SIZE)));
+    assert_eq!(jit.run(&mut compiled, Keys::new(), SPEED), expected);
+    assert!(compiled.save_state() == interpreted.save_state());
+}